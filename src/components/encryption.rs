@@ -0,0 +1,223 @@
+use crate::managers::flush_all;
+use crate::storage::{
+    change_passphrase, disable_encryption, enable_encryption, encryption_state,
+    get_storage_backend, replace_key, unlock, EncryptionState, StorageBackend,
+};
+use dioxus::prelude::*;
+use tracing::error;
+
+const ENCRYPTION_CSS: Asset = asset!("/assets/styling/encryption.css");
+
+const MIN_PASSPHRASE_LEN: usize = 8;
+
+// Deriving the key from a passphrase is slow on purpose, so it runs off the UI thread
+async fn run_blocking<R: Send + 'static>(
+    f: impl FnOnce() -> Result<R, String> + Send + 'static,
+) -> Result<R, String> {
+    tokio::task::spawn_blocking(f)
+        .await
+        .map_err(|e| e.to_string())?
+}
+
+fn check_new_passphrase(new: &str, confirm: &str) -> Result<(), String> {
+    if new.chars().count() < MIN_PASSPHRASE_LEN {
+        Err(format!(
+            "The passphrase needs at least {} characters",
+            MIN_PASSPHRASE_LEN
+        ))
+    } else if new != confirm {
+        Err("The passphrases don't match".to_string())
+    } else {
+        Ok(())
+    }
+}
+
+// Shown instead of the app while the data is encrypted and locked. Nothing is
+// loaded until the passphrase has been entered.
+#[component]
+pub fn UnlockPrompt(on_unlock: EventHandler<()>) -> Element {
+    let mut passphrase = use_signal(String::new);
+    let mut error = use_signal(|| None::<String>);
+    let mut busy = use_signal(|| false);
+
+    let submit = move |ev: FormEvent| {
+        ev.prevent_default();
+        let value = passphrase();
+        busy.set(true);
+        spawn(async move {
+            match run_blocking(move || unlock(&value)).await {
+                Ok(()) => on_unlock.call(()),
+                Err(e) => {
+                    error.set(Some(e));
+                    passphrase.set(String::new());
+                }
+            }
+            busy.set(false);
+        });
+    };
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: ENCRYPTION_CSS }
+        form { class: "unlock-prompt", onsubmit: submit,
+            h2 { "Your data is locked" }
+            p { "Enter the passphrase to open it." }
+            input {
+                r#type: "password",
+                autofocus: true,
+                placeholder: "Passphrase",
+                value: "{passphrase}",
+                oninput: move |ev| passphrase.set(ev.value())
+            }
+            if let Some(message) = error() {
+                div { class: "encryption-error", "{message}" }
+            }
+            button { r#type: "submit", disabled: busy() || passphrase.read().is_empty(),
+                if busy() { "Unlocking…" } else { "Unlock" }
+            }
+        }
+    }
+}
+
+// Turning encryption on or off, changing the passphrase and replacing the key
+#[component]
+pub fn EncryptionSettings() -> Element {
+    let mut state = use_signal(encryption_state);
+    let mut current = use_signal(String::new);
+    let mut new = use_signal(String::new);
+    let mut confirm = use_signal(String::new);
+    let mut status = use_signal(|| None::<Result<String, String>>);
+    let mut busy = use_signal(|| false);
+
+    let mut finish = move |result: Result<String, String>| {
+        if let Err(e) = &result {
+            error!("Encryption settings: {}", e);
+        }
+        if result.is_ok() {
+            current.set(String::new());
+            new.set(String::new());
+            confirm.set(String::new());
+        }
+        status.set(Some(result));
+        state.set(encryption_state());
+        busy.set(false);
+    };
+
+    let turn_on = move |_| {
+        let (passphrase, confirmation) = (new(), confirm());
+        if let Err(e) = check_new_passphrase(&passphrase, &confirmation) {
+            status.set(Some(Err(e)));
+            return;
+        }
+        busy.set(true);
+        spawn(async move {
+            // Saves still queued would otherwise land after their file was encrypted
+            flush_all().await;
+            let result = run_blocking(move || enable_encryption(&passphrase)).await;
+            finish(result.map(|count| {
+                format!(
+                    "Encryption is on, {} files were encrypted. Without the passphrase the data can't be recovered.",
+                    count
+                )
+            }));
+        });
+    };
+
+    let change = move |_| {
+        let (old, passphrase, confirmation) = (current(), new(), confirm());
+        if let Err(e) = check_new_passphrase(&passphrase, &confirmation) {
+            status.set(Some(Err(e)));
+            return;
+        }
+        busy.set(true);
+        spawn(async move {
+            let result = run_blocking(move || change_passphrase(&old, &passphrase)).await;
+            finish(result.map(|()| "The passphrase was changed".to_string()));
+        });
+    };
+
+    let rekey = move |_| {
+        let passphrase = current();
+        busy.set(true);
+        spawn(async move {
+            flush_all().await;
+            let result = run_blocking(move || replace_key(&passphrase)).await;
+            finish(result.map(|count| {
+                format!(
+                    "The encryption key was replaced, {} files were re-encrypted",
+                    count
+                )
+            }));
+        });
+    };
+
+    let turn_off = move |_| {
+        let passphrase = current();
+        busy.set(true);
+        spawn(async move {
+            flush_all().await;
+            let result = run_blocking(move || disable_encryption(&passphrase)).await;
+            finish(
+                result.map(|count| format!("Encryption is off, {} files were decrypted", count)),
+            );
+        });
+    };
+
+    let password_input = |label: &'static str, mut value: Signal<String>| {
+        rsx! {
+            input {
+                r#type: "password",
+                placeholder: label,
+                value: "{value}",
+                oninput: move |ev| value.set(ev.value())
+            }
+        }
+    };
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: ENCRYPTION_CSS }
+        div { class: "encryption-settings",
+            if state() == EncryptionState::Off {
+                p { "Data files are stored as plain text. With encryption on, they can only be opened with your passphrase." }
+                div { class: "encryption-inputs",
+                    {password_input("New passphrase", new)}
+                    {password_input("Repeat passphrase", confirm)}
+                    button {
+                        disabled: busy() || get_storage_backend() == StorageBackend::Sqlite,
+                        onclick: turn_on,
+                        "Encrypt data files"
+                    }
+                }
+            } else {
+                p { "Data files and their backups are encrypted. Exported timelines are not." }
+                div { class: "encryption-inputs",
+                    {password_input("Current passphrase", current)}
+                    {password_input("New passphrase", new)}
+                    {password_input("Repeat new passphrase", confirm)}
+                    button { disabled: busy(), onclick: change, "Change passphrase" }
+                }
+                div { class: "encryption-inputs",
+                    button {
+                        disabled: busy() || current.read().is_empty(),
+                        title: "Re-encrypts every data file with a new key, keeping the passphrase",
+                        onclick: rekey,
+                        "Replace encryption key"
+                    }
+                    button {
+                        class: "encryption-off",
+                        disabled: busy() || current.read().is_empty(),
+                        onclick: turn_off,
+                        "Turn off encryption"
+                    }
+                }
+            }
+            if get_storage_backend() == StorageBackend::Sqlite {
+                p { class: "encryption-note", "Encryption isn't available with SQLite storage. The databases in use are not encrypted." }
+            }
+            match status() {
+                Some(Ok(message)) => rsx! { div { class: "settings-status", "{message}" } },
+                Some(Err(message)) => rsx! { div { class: "encryption-error", "{message}" } },
+                None => rsx! {},
+            }
+        }
+    }
+}
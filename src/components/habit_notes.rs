@@ -0,0 +1,73 @@
+use crate::components::habit_tracker::MOODS;
+use crate::models::habit::HabitData;
+use chrono::{Datelike, Duration, Local, NaiveDate};
+use dioxus::prelude::*;
+
+fn month_bounds(month_start: NaiveDate) -> (NaiveDate, NaiveDate) {
+    let next_month = if month_start.month() == 12 {
+        NaiveDate::from_ymd_opt(month_start.year() + 1, 1, 1)
+    } else {
+        NaiveDate::from_ymd_opt(month_start.year(), month_start.month() + 1, 1)
+    }
+    .unwrap_or(month_start);
+    (month_start, next_month - Duration::days(1))
+}
+
+#[derive(Props, Clone, PartialEq)]
+pub struct HabitNotesTimelineProps {
+    habit_data: HabitData,
+}
+
+#[component]
+pub fn HabitNotesTimeline(props: HabitNotesTimelineProps) -> Element {
+    let mut month_start = use_signal(|| Local::now().date_naive().with_day(1).unwrap_or_default());
+
+    let (from, to) = month_bounds(month_start());
+    let notes = props.habit_data.notes_between(from, to);
+
+    rsx! {
+        div { class: "habit-notes",
+            div { class: "habit-notes-header",
+                button {
+                    class: "week-nav-btn",
+                    onclick: move |_| {
+                        let previous = month_start() - Duration::days(1);
+                        month_start.set(previous.with_day(1).unwrap_or(previous));
+                    },
+                    "‹"
+                }
+                span { class: "habit-notes-month", {from.format("%B %Y").to_string()} }
+                button {
+                    class: "week-nav-btn",
+                    onclick: move |_| month_start.set(to + Duration::days(1)),
+                    "›"
+                }
+            }
+            if notes.is_empty() {
+                div { class: "habit-notes-empty", "No notes this month" }
+            }
+            ul { class: "habit-notes-list",
+                {notes.iter().rev().map(|(date, note)| {
+                    let completed = props.habit_data.is_completed(*date);
+                    let mood = note
+                        .mood
+                        .and_then(|mood| MOODS.get((mood as usize).checked_sub(1)?))
+                        .copied()
+                        .unwrap_or("");
+                    rsx! {
+                        li {
+                            key: "{date}",
+                            class: if completed { "habit-note completed" } else { "habit-note" },
+                            style: "--selected-color: {props.habit_data.color};",
+                            div { class: "habit-note-meta",
+                                span { class: "habit-note-date", {date.format("%a %d").to_string()} }
+                                span { class: "habit-note-mood", "{mood}" }
+                            }
+                            p { class: "habit-note-text", "{note.text}" }
+                        }
+                    }
+                })}
+            }
+        }
+    }
+}
@@ -1,10 +1,28 @@
 use crate::managers::habit_manager::get_habit_manager;
-use crate::models::habit::{Habit, WeekStart};
+use crate::managers::DataStore;
+use crate::models::habit::{Habit, HabitData, WeekStart};
+use crate::storage::StorageEvent;
 use chrono::Local;
 use dioxus::prelude::*;
 use tracing::{error, info};
 use uuid::Uuid;
 
+// Habits grouped by category in bar order; uncategorized habits come first under `None`
+fn group_by_category(habits: &[Habit]) -> Vec<(Option<String>, Vec<Habit>)> {
+    let mut groups: Vec<(Option<String>, Vec<Habit>)> = vec![(None, Vec::new())];
+    for habit in habits {
+        match groups
+            .iter_mut()
+            .find(|(category, _)| *category == habit.category)
+        {
+            Some((_, group)) => group.push(habit.clone()),
+            None => groups.push((habit.category.clone(), vec![habit.clone()])),
+        }
+    }
+    groups.retain(|(_, group)| !group.is_empty());
+    groups
+}
+
 const TAB_BAR_CSS: Asset = asset!("/assets/styling/habits_tab_bar.css");
 
 #[derive(Props, Clone, PartialEq)]
@@ -18,14 +36,32 @@ pub fn TabBar(props: TabBarProps) -> Element {
     let mut habits = use_signal(Vec::<Habit>::new);
     let mut editing_tab_id = use_signal(|| None::<Uuid>);
     let mut show_delete_confirm = use_signal(|| None::<Uuid>);
+    let mut show_archived = use_signal(|| false);
+    let mut collapsed = use_signal(Vec::<String>::new);
+    let mut dragged_habit = use_signal(|| None::<Uuid>);
+    let mut refresh = use_signal(|| 0u32);
+
+    // Reload the bar when the habits file was changed outside the app
+    use_future(move || async move {
+        let mut events = DataStore::Habits.subscribe().await;
+        loop {
+            if DataStore::Habits.next_event(&mut events).await == StorageEvent::Reloaded {
+                refresh += 1;
+            }
+        }
+    });
 
     // Load habits effect
     {
         let mut habits = habits.clone();
         use_effect(move || {
+            refresh();
             spawn(async move {
                 info!("Loading habits");
                 let manager = get_habit_manager();
+                if let Ok(categories) = manager.get_collapsed_categories().await {
+                    collapsed.set(categories);
+                }
                 match manager.get_all_habits().await {
                     Ok(loaded_habits_data) => {
                         info!("Successfully loaded {} habits", loaded_habits_data.len());
@@ -37,6 +73,8 @@ pub fn TabBar(props: TabBarProps) -> Element {
                                 start_date: data.start_date,
                                 color: data.color,
                                 week_start: data.week_start.to_string(),
+                                category: data.category,
+                                archived: data.archived,
                             })
                             .collect();
                         habits.set(habits_vec);
@@ -59,6 +97,8 @@ pub fn TabBar(props: TabBarProps) -> Element {
                 start_date: Local::now().date_naive(),
                 color: "#800080".to_string(),
                 week_start: "sunday".to_string(),
+                category: None,
+                archived: false,
             };
 
             spawn({
@@ -69,13 +109,12 @@ pub fn TabBar(props: TabBarProps) -> Element {
                 async move {
                     info!("Creating new habit with id: {}", new_habit.id);
                     let manager = get_habit_manager();
-                    let habit_data = crate::models::habit::HabitData {
-                        title: new_habit.title.clone(),
-                        start_date: new_habit.start_date,
-                        color: new_habit.color.clone(),
-                        week_start: WeekStart::from_string(&new_habit.week_start),
-                        completed_days: Vec::new(),
-                    };
+                    let habit_data = HabitData::new(
+                        new_habit.title.clone(),
+                        new_habit.start_date,
+                        new_habit.color.clone(),
+                        WeekStart::from_string(&new_habit.week_start),
+                    );
 
                     match manager.create_habit(new_habit.id, habit_data).await {
                         Ok(_) => {
@@ -92,8 +131,53 @@ pub fn TabBar(props: TabBarProps) -> Element {
         }
     };
 
+    // Dropping a tab onto another moves it before that tab and into its category
+    let handle_drop = move |target: Habit| {
+        let Some(dragged_id) = dragged_habit() else {
+            return;
+        };
+        dragged_habit.set(None);
+        if dragged_id == target.id {
+            return;
+        }
+
+        let mut current_habits = habits.read().clone();
+        let Some(old_index) = current_habits.iter().position(|h| h.id == dragged_id) else {
+            return;
+        };
+        let mut moved = current_habits.remove(old_index);
+        let category_changed = moved.category != target.category;
+        moved.category = target.category.clone();
+        let new_index = current_habits
+            .iter()
+            .position(|h| h.id == target.id)
+            .unwrap_or(current_habits.len());
+        current_habits.insert(new_index, moved);
+
+        let updates: Vec<(Uuid, i32)> = current_habits
+            .iter()
+            .enumerate()
+            .map(|(i, h)| (h.id, i as i32))
+            .collect();
+        habits.set(current_habits);
+
+        spawn(async move {
+            let manager = get_habit_manager();
+            if category_changed {
+                if let Err(e) = manager.set_category(dragged_id, target.category).await {
+                    error!("Failed to move habit to category: {:?}", e);
+                }
+            }
+            if let Err(e) = manager.update_positions(updates).await {
+                error!("Failed to reorder habits: {:?}", e);
+            }
+        });
+    };
+
     let render_habit = move |habit: &Habit| {
         let habit = habit.clone();
+        let drop_target = habit.clone();
+        let mut handle_drop = handle_drop.clone();
         let is_selected = habit.id == props.selected_habit_id;
         let is_editing = editing_tab_id.read().map_or(false, |id| id == habit.id);
         let showing_confirm = show_delete_confirm
@@ -103,7 +187,18 @@ pub fn TabBar(props: TabBarProps) -> Element {
         rsx! {
             div {
                 key: habit.id.to_string(),
-                class: if is_selected { "habits-tab selected" } else { "habits-tab" },
+                class: format!(
+                    "habits-tab {} {}",
+                    if is_selected { "selected" } else { "" },
+                    if habit.archived { "archived" } else { "" }
+                ),
+                draggable: !is_editing && !habit.archived,
+                ondragstart: move |_| dragged_habit.set(Some(habit.id)),
+                ondragover: move |ev| ev.prevent_default(),
+                ondrop: move |ev| {
+                    ev.prevent_default();
+                    handle_drop(drop_target.clone());
+                },
                 onclick: move |_| {
                     if !is_editing && !showing_confirm {
                         editing_tab_id.set(None);
@@ -118,6 +213,7 @@ pub fn TabBar(props: TabBarProps) -> Element {
 
                 {if is_editing {
                     let habit_id = habit.id;
+                    let archived = habit.archived;
                     rsx! {
                         div { class: "habits-tab-edit",
                             input {
@@ -125,6 +221,9 @@ pub fn TabBar(props: TabBarProps) -> Element {
                                 value: habit.title.clone(),
                                 onkeydown: {
                                     move |evt: Event<KeyboardData>| {
+                                        if evt.key() == Key::Escape {
+                                            editing_tab_id.set(None);
+                                        }
                                         if evt.key() == Key::Enter {
                                             let habit_title = habit.title.clone();
                                             spawn({
@@ -143,7 +242,6 @@ pub fn TabBar(props: TabBarProps) -> Element {
                                         }
                                     }
                                 },
-                                onblur: move |_| editing_tab_id.set(None),
                                 oninput: move |evt: Event<FormData>| {
                                     let mut current_habits = habits.read().clone();
                                     if let Some(habit) = current_habits.iter_mut().find(|h| h.id == habit_id) {
@@ -152,6 +250,39 @@ pub fn TabBar(props: TabBarProps) -> Element {
                                     }
                                 }
                             }
+                            input {
+                                r#type: "text",
+                                class: "category-input",
+                                list: "habit-categories",
+                                placeholder: "Category",
+                                value: habit.category.clone().unwrap_or_default(),
+                                onchange: move |evt: Event<FormData>| {
+                                    let category = Some(evt.data.value());
+                                    spawn(async move {
+                                        match get_habit_manager().set_category(habit_id, category).await {
+                                            Ok(_) => refresh += 1,
+                                            Err(e) => error!("Failed to set category: {:?}", e),
+                                        }
+                                    });
+                                }
+                            }
+                            button {
+                                class: "archive-btn",
+                                title: if archived { "Unarchive" } else { "Archive" },
+                                onclick: move |evt| {
+                                    evt.stop_propagation();
+                                    spawn(async move {
+                                        match get_habit_manager().set_archived(habit_id, !archived).await {
+                                            Ok(_) => {
+                                                editing_tab_id.set(None);
+                                                refresh += 1;
+                                            }
+                                            Err(e) => error!("Failed to archive habit: {:?}", e),
+                                        }
+                                    });
+                                },
+                                i { class: if archived { "fas fa-box-open" } else { "fas fa-box-archive" } }
+                            }
                             button {
                                 class: "delete-btn",
                                 onclick: move |_| show_delete_confirm.set(Some(habit_id)),
@@ -205,16 +336,85 @@ pub fn TabBar(props: TabBarProps) -> Element {
         }
     };
 
+    let (archived_habits, active_habits): (Vec<Habit>, Vec<Habit>) =
+        habits.read().iter().cloned().partition(|h| h.archived);
+    let groups = group_by_category(&active_habits);
+    let mut categories: Vec<String> = habits
+        .read()
+        .iter()
+        .filter_map(|h| h.category.clone())
+        .collect();
+    categories.sort();
+    categories.dedup();
+
+    let mut toggle_category = move |category: String| {
+        let is_collapsed = collapsed.read().contains(&category);
+        let mut current = collapsed.read().clone();
+        current.retain(|c| *c != category);
+        if !is_collapsed {
+            current.push(category.clone());
+        }
+        collapsed.set(current);
+        spawn(async move {
+            if let Err(e) = get_habit_manager()
+                .set_category_collapsed(category, !is_collapsed)
+                .await
+            {
+                error!("Failed to save collapsed category: {:?}", e);
+            }
+        });
+    };
+
     rsx! {
         document::Link { rel: "stylesheet", href: TAB_BAR_CSS }
+        datalist { id: "habit-categories",
+            {categories.iter().map(|category| rsx! { option { key: "{category}", value: "{category}" } })}
+        }
         div { class: "habits-tab-bar",
             div { class: "habits-tabs",
-                {habits.read().iter().map(render_habit)}
+                {groups.into_iter().map(|(category, group)| {
+                    match category {
+                        None => rsx! { {group.iter().map(render_habit)} },
+                        Some(category) => {
+                            let is_collapsed = collapsed.read().contains(&category);
+                            // A collapsed group still shows the selected habit
+                            let visible: Vec<Habit> = group
+                                .iter()
+                                .filter(|h| !is_collapsed || h.id == props.selected_habit_id)
+                                .cloned()
+                                .collect();
+                            let toggle_name = category.clone();
+                            rsx! {
+                                div { key: "{category}", class: "habits-category",
+                                    button {
+                                        class: "habits-category-header",
+                                        onclick: move |_| toggle_category(toggle_name.clone()),
+                                        if is_collapsed { "▸ " } else { "▾ " }
+                                        "{category} ({group.len()})"
+                                    }
+                                    {visible.iter().map(render_habit)}
+                                }
+                            }
+                        }
+                    }
+                })}
                 button {
                     class: "new-habits-tab",
                     onclick: create_new_habit,
                     "+"
                 }
+                if !archived_habits.is_empty() {
+                    button {
+                        class: if show_archived() { "archived-toggle active" } else { "archived-toggle" },
+                        onclick: move |_| show_archived.toggle(),
+                        "Archived ({archived_habits.len()})"
+                    }
+                }
+            }
+            if show_archived() && !archived_habits.is_empty() {
+                div { class: "habits-tabs archived-tabs",
+                    {archived_habits.iter().map(render_habit)}
+                }
             }
         }
     }
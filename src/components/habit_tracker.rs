@@ -1,12 +1,52 @@
 use crate::managers::habit_manager::get_habit_manager;
-use crate::models::habit::{HabitData, WeekStart};
-use chrono::{Datelike, Duration, Local, NaiveDate};
+use crate::models::habit::{
+    DayStatus, HabitData, HabitMeasure, HabitNote, HabitSchedule, WeekStart,
+};
+use crate::models::habit_stats::HabitStats;
+use chrono::{Datelike, Duration, Local, NaiveDate, Weekday};
 use dioxus::prelude::*;
 use tracing::info;
 use uuid::Uuid;
 
 const HABIT_TRACKER_CSS: Asset = asset!("/assets/styling/habit_tracker.css");
 
+const SCHEDULE_WEEKDAYS: [(Weekday, &str); 7] = [
+    (Weekday::Mon, "M"),
+    (Weekday::Tue, "T"),
+    (Weekday::Wed, "W"),
+    (Weekday::Thu, "T"),
+    (Weekday::Fri, "F"),
+    (Weekday::Sat, "S"),
+    (Weekday::Sun, "S"),
+];
+
+pub const MOODS: [&str; 5] = ["😞", "😕", "😐", "🙂", "😄"];
+
+fn save_measure(habit_id: Uuid, measure: Option<HabitMeasure>, on_data_change: EventHandler<()>) {
+    spawn(async move {
+        match get_habit_manager().set_measure(habit_id, measure).await {
+            Ok(_) => on_data_change.call(()),
+            Err(e) => println!("Failed to update measure: {:?}", e),
+        }
+    });
+}
+
+fn save_schedule(habit_id: Uuid, schedule: HabitSchedule, on_data_change: EventHandler<()>) {
+    spawn(async move {
+        let manager = get_habit_manager();
+        match manager.get_habit(habit_id).await {
+            Ok(Some(mut current_data)) => {
+                current_data.schedule = schedule;
+                match manager.update_habit(habit_id, current_data).await {
+                    Ok(_) => on_data_change.call(()),
+                    Err(e) => println!("Failed to update schedule: {:?}", e),
+                }
+            }
+            _ => println!("Failed to get habit"),
+        }
+    });
+}
+
 #[derive(Props, Clone, PartialEq)]
 pub struct HabitTrackerProps {
     habit_id: Uuid,
@@ -17,12 +57,34 @@ pub struct HabitTrackerProps {
 #[component]
 pub fn HabitTracker(props: HabitTrackerProps) -> Element {
     let completed_days = props.habit_data.completed_days.clone();
+    let measure = props.habit_data.measure.clone();
+    let mut selected_date = use_signal(|| None::<NaiveDate>);
+    let mut note_draft = use_signal(String::new);
+    let mut mood_draft = use_signal(|| None::<u8>);
+
+    // Opens the day panel with that day's note loaded into the drafts
+    let select_day = {
+        let notes = props.habit_data.notes.clone();
+        move |date: NaiveDate| {
+            let note = notes.get(&date).cloned().unwrap_or_default();
+            note_draft.set(note.text);
+            mood_draft.set(note.mood);
+            selected_date.set(Some(date));
+        }
+    };
 
     let toggle_day = {
+        let mut select_day = select_day.clone();
         let on_data_change = props.on_data_change.clone();
         let completed_days = completed_days.clone();
+        let is_measured = measure.is_some();
         move |date: NaiveDate| {
             if date <= Local::now().date_naive() {
+                // Measured habits open the value editor instead of toggling
+                if is_measured {
+                    select_day(date);
+                    return;
+                }
                 spawn({
                     let on_data_change = on_data_change.clone();
                     let habit_id = props.habit_id;
@@ -45,6 +107,15 @@ pub fn HabitTracker(props: HabitTrackerProps) -> Element {
         }
     };
 
+    let stats = HabitStats::compute(&props.habit_data, Local::now().date_naive());
+    let max_weekday_count = stats
+        .weekday_counts
+        .iter()
+        .map(|(_, count)| *count)
+        .max()
+        .unwrap_or(0)
+        .max(1);
+
     let color_clone = props.habit_data.color.clone();
     use_effect(move || {
         info!("HabitTracker color changed to: {}", color_clone);
@@ -53,6 +124,38 @@ pub fn HabitTracker(props: HabitTrackerProps) -> Element {
     rsx! {
         document::Link { rel: "stylesheet", href: HABIT_TRACKER_CSS }
         div { class: "habit-tracker",
+            div { class: "habit-stats",
+                div { class: "stat",
+                    span { class: "stat-value", "{stats.current_streak}" }
+                    span { class: "stat-label", "Current streak" }
+                }
+                div { class: "stat",
+                    span { class: "stat-value", "{stats.longest_streak}" }
+                    span { class: "stat-label", "Longest streak" }
+                }
+                {[("7 days", stats.rate_7), ("30 days", stats.rate_30), ("365 days", stats.rate_365)]
+                    .into_iter()
+                    .map(|(label, rate)| rsx! {
+                        div { class: "stat", key: "{label}",
+                            span { class: "stat-value", {format!("{:.0}%", rate * 100.0)} }
+                            span { class: "stat-label", "{label}" }
+                        }
+                    })}
+                div { class: "weekday-distribution",
+                    {stats.weekday_counts.iter().map(|(weekday, count)| {
+                        let height = *count as f64 / max_weekday_count as f64 * 100.0;
+                        rsx! {
+                            div { class: "weekday-bar", key: "{weekday}", title: "{weekday}: {count}",
+                                div {
+                                    class: "weekday-bar-fill",
+                                    style: "height: {height}%; background: {props.habit_data.color};"
+                                }
+                                span { {weekday.to_string().chars().next().map(String::from).unwrap_or_default()} }
+                            }
+                        }
+                    })}
+                }
+            }
             div { class: "date-picker",
                 label { "Start Date: " }
                 input {
@@ -159,9 +262,250 @@ pub fn HabitTracker(props: HabitTrackerProps) -> Element {
                         }
                     }
                 }
+
+                label { "Schedule: " }
+                select {
+                    value: props.habit_data.schedule.to_string(),
+                    onchange: {
+                        let on_data_change = props.on_data_change.clone();
+                        let habit_id = props.habit_id;
+                        move |evt: Event<FormData>| {
+                            let schedule = match evt.data.value().as_str() {
+                                "weekdays" => HabitSchedule::Weekdays {
+                                    days: vec![
+                                        Weekday::Mon,
+                                        Weekday::Tue,
+                                        Weekday::Wed,
+                                        Weekday::Thu,
+                                        Weekday::Fri,
+                                    ],
+                                },
+                                "per_week" => HabitSchedule::TimesPerWeek { times: 3 },
+                                "per_month" => HabitSchedule::TimesPerMonth { times: 10 },
+                                _ => HabitSchedule::Daily,
+                            };
+                            save_schedule(habit_id, schedule, on_data_change.clone());
+                        }
+                    },
+                    option { value: "daily", "Every day" },
+                    option { value: "weekdays", "Specific weekdays" },
+                    option { value: "per_week", "Times per week" },
+                    option { value: "per_month", "Times per month" }
+                }
+
+                match props.habit_data.schedule.clone() {
+                    HabitSchedule::Weekdays { days } => rsx! {
+                        div { class: "schedule-weekdays",
+                            {SCHEDULE_WEEKDAYS.iter().map(|(weekday, label)| {
+                                let weekday = *weekday;
+                                let is_active = days.contains(&weekday);
+                                let days = days.clone();
+                                let on_data_change = props.on_data_change.clone();
+                                let habit_id = props.habit_id;
+                                rsx! {
+                                    button {
+                                        key: "{weekday}",
+                                        class: if is_active { "weekday-toggle active" } else { "weekday-toggle" },
+                                        title: "{weekday}",
+                                        onclick: move |_| {
+                                            let mut days = days.clone();
+                                            if is_active {
+                                                days.retain(|d| *d != weekday);
+                                            } else {
+                                                days.push(weekday);
+                                                days.sort_by_key(|d| d.num_days_from_monday());
+                                            }
+                                            save_schedule(habit_id, HabitSchedule::Weekdays { days }, on_data_change.clone());
+                                        },
+                                        "{label}"
+                                    }
+                                }
+                            })}
+                        }
+                    },
+                    HabitSchedule::TimesPerWeek { times } | HabitSchedule::TimesPerMonth { times } => {
+                        let per_week = matches!(props.habit_data.schedule, HabitSchedule::TimesPerWeek { .. });
+                        let max = if per_week { 7 } else { 31 };
+                        rsx! {
+                            input {
+                                r#type: "number",
+                                class: "schedule-times",
+                                min: "1",
+                                max: "{max}",
+                                value: "{times}",
+                                onchange: {
+                                    let on_data_change = props.on_data_change.clone();
+                                    let habit_id = props.habit_id;
+                                    move |evt: Event<FormData>| {
+                                        if let Ok(times) = evt.data.value().parse::<u32>() {
+                                            let times = times.clamp(1, max);
+                                            let schedule = if per_week {
+                                                HabitSchedule::TimesPerWeek { times }
+                                            } else {
+                                                HabitSchedule::TimesPerMonth { times }
+                                            };
+                                            save_schedule(habit_id, schedule, on_data_change.clone());
+                                        }
+                                    }
+                                }
+                            }
+                        }
+                    }
+                    HabitSchedule::Daily => rsx! {},
+                }
+
+                label { "Track: " }
+                select {
+                    value: if measure.is_some() { "amount" } else { "check" },
+                    onchange: {
+                        let on_data_change = props.on_data_change.clone();
+                        let habit_id = props.habit_id;
+                        move |evt: Event<FormData>| {
+                            let measure = match evt.data.value().as_str() {
+                                "amount" => Some(HabitMeasure {
+                                    unit: "times".to_string(),
+                                    target: 1.0,
+                                }),
+                                _ => None,
+                            };
+                            selected_date.set(None);
+                            save_measure(habit_id, measure, on_data_change.clone());
+                        }
+                    },
+                    option { value: "check", "Done / not done" },
+                    option { value: "amount", "Amount" }
+                }
+
+                if let Some(current) = measure.clone() {
+                    input {
+                        r#type: "number",
+                        class: "measure-target",
+                        title: "Daily target",
+                        min: "0",
+                        step: "any",
+                        value: "{current.target}",
+                        onchange: {
+                            let on_data_change = props.on_data_change.clone();
+                            let habit_id = props.habit_id;
+                            let current = current.clone();
+                            move |evt: Event<FormData>| {
+                                if let Ok(target) = evt.data.value().parse::<f64>() {
+                                    let measure = HabitMeasure {
+                                        target: target.max(0.0),
+                                        ..current.clone()
+                                    };
+                                    save_measure(habit_id, Some(measure), on_data_change.clone());
+                                }
+                            }
+                        }
+                    }
+                    input {
+                        r#type: "text",
+                        class: "measure-unit",
+                        title: "Unit",
+                        placeholder: "unit",
+                        value: "{current.unit}",
+                        onchange: {
+                            let on_data_change = props.on_data_change.clone();
+                            let habit_id = props.habit_id;
+                            let current = current.clone();
+                            move |evt: Event<FormData>| {
+                                let measure = HabitMeasure {
+                                    unit: evt.data.value().trim().to_string(),
+                                    ..current.clone()
+                                };
+                                save_measure(habit_id, Some(measure), on_data_change.clone());
+                            }
+                        }
+                    }
+                }
             }
             br {}
 
+            if let Some(date) = selected_date() {
+                div { class: "day-panel",
+                    div { class: "day-panel-header",
+                        label { {date.format("%b %d, %Y").to_string()} }
+                        if let Some(current) = measure.clone() {
+                            input {
+                                r#type: "number",
+                                min: "0",
+                                step: "any",
+                                value: "{props.habit_data.value_on(date)}",
+                                onchange: {
+                                    let on_data_change = props.on_data_change.clone();
+                                    let habit_id = props.habit_id;
+                                    move |evt: Event<FormData>| {
+                                        if let Ok(value) = evt.data.value().parse::<f64>() {
+                                            let on_data_change = on_data_change.clone();
+                                            spawn(async move {
+                                                match get_habit_manager().set_value(habit_id, date, value.max(0.0)).await {
+                                                    Ok(_) => on_data_change.call(()),
+                                                    Err(e) => println!("Failed to set value: {:?}", e),
+                                                }
+                                            });
+                                        }
+                                    }
+                                }
+                            }
+                            span { "/ {current.target} {current.unit}" }
+                        }
+                        div { class: "mood-picker",
+                            {MOODS.iter().enumerate().map(|(index, symbol)| {
+                                let mood = index as u8 + 1;
+                                rsx! {
+                                    button {
+                                        key: "{mood}",
+                                        class: if mood_draft() == Some(mood) { "mood active" } else { "mood" },
+                                        title: "Mood {mood}",
+                                        onclick: move |_| {
+                                            if mood_draft() == Some(mood) {
+                                                mood_draft.set(None);
+                                            } else {
+                                                mood_draft.set(Some(mood));
+                                            }
+                                        },
+                                        "{symbol}"
+                                    }
+                                }
+                            })}
+                        }
+                        button {
+                            class: "day-panel-close",
+                            onclick: move |_| selected_date.set(None),
+                            "×"
+                        }
+                    }
+                    textarea {
+                        class: "day-note",
+                        placeholder: "How did it go?",
+                        value: "{note_draft}",
+                        oninput: move |evt| note_draft.set(evt.value())
+                    }
+                    button {
+                        class: "day-note-save",
+                        onclick: {
+                            let on_data_change = props.on_data_change.clone();
+                            let habit_id = props.habit_id;
+                            move |_| {
+                                let note = HabitNote {
+                                    text: note_draft(),
+                                    mood: mood_draft(),
+                                };
+                                let on_data_change = on_data_change.clone();
+                                spawn(async move {
+                                    match get_habit_manager().set_note(habit_id, date, note).await {
+                                        Ok(_) => on_data_change.call(()),
+                                        Err(e) => println!("Failed to save note: {:?}", e),
+                                    }
+                                });
+                            }
+                        },
+                        "Save note"
+                    }
+                }
+            }
+
             div { class: "calendar-header",
                 {["S", "M", "T", "W", "T", "F", "S"].iter().cycle()
                     .skip(props.habit_data.week_start.to_weekday().num_days_from_sunday() as usize)
@@ -171,29 +515,29 @@ pub fn HabitTracker(props: HabitTrackerProps) -> Element {
 
             div { class: "calendar-grid",
                 {render_calendar(
-                    props.habit_data.start_date,
+                    &props.habit_data,
                     Local::now().date_naive(),
-                    &completed_days,
-                    props.habit_data.color.clone(),
-                    props.habit_data.week_start.clone(),
-                    toggle_day
+                    toggle_day,
+                    select_day
                 )}
             }
         }
     }
 }
 
-fn render_calendar<F>(
-    start_date: NaiveDate,
+fn render_calendar<F, G>(
+    habit: &HabitData,
     current_date: NaiveDate,
-    completed_days: &[NaiveDate],
-    color: String,
-    week_start: WeekStart,
     on_click: F,
+    on_select: G,
 ) -> Element
 where
     F: FnMut(NaiveDate) + Clone + 'static,
+    G: FnMut(NaiveDate) + Clone + 'static,
 {
+    let start_date = habit.start_date;
+    let week_start = &habit.week_start;
+    let color = habit.color.clone();
     let days_to_start = week_start.get_days_from_start(start_date);
     let week_start_date = start_date - Duration::days(days_to_start);
 
@@ -206,23 +550,49 @@ where
     rsx! {
         Fragment {
             {days.map(|date| {
-                let is_completed = completed_days.contains(&date);
-                let is_future = date > current_date;
-                let is_before_start = date < start_date;
+                let status_class = match habit.day_status(date, current_date) {
+                    DayStatus::Completed => "completed",
+                    DayStatus::Missed => "missed",
+                    DayStatus::Neutral => "neutral",
+                    DayStatus::Pending => "pending",
+                    DayStatus::BeforeStart => "past",
+                    DayStatus::Future => "future",
+                };
                 let mut on_click = on_click.clone();
+                let mut on_select = on_select.clone();
+                let has_note = habit.notes.contains_key(&date);
+
+                // Partial progress on measured habits shades the cell proportionally
+                let progress = habit.progress(date);
+                let shading = if habit.measure.is_some() && progress > 0.0 && progress < 1.0 {
+                    format!(" background: {}{:02x};", color, (progress * 204.0) as u8)
+                } else {
+                    String::new()
+                };
+                let title = match &habit.measure {
+                    Some(measure) => format!("{} / {} {}", habit.value_on(date), measure.target, measure.unit),
+                    None => String::new(),
+                };
 
                 rsx! {
                     div {
                         key: "{date}",
                         class: format!(
-                            "calendar-cell {} {} {} {}",
-                            if is_completed { "completed" } else { "" },
-                            if is_future { "future" } else { "" },
+                            "calendar-cell {} {} {}",
+                            status_class,
                             if date == current_date { "current-day" } else { "" },
-                            if is_before_start { "past" } else { "" }
+                            if has_note { "has-note" } else { "" }
                         ),
-                        style: "--selected-color: {color}; --selected-color-light: {color}44;",
+                        style: "--selected-color: {color}; --selected-color-light: {color}44;{shading}",
+                        title: "{title}",
                         onclick: move |_| on_click(date),
+                        // Right-click opens the note for any past day
+                        oncontextmenu: move |ev| {
+                            ev.prevent_default();
+                            if date <= current_date {
+                                on_select(date);
+                            }
+                        },
                         "{date.day()}"
                     }
                 }
@@ -1,6 +1,11 @@
+pub mod encryption;
+pub mod habit_notes;
 pub mod habit_tab_bar;
 pub mod habit_tracker;
 pub mod navbar;
+pub mod routine_editor;
+pub mod routine_schedule;
+pub mod storage_recovery;
 pub mod timeline;
 pub mod todo_day_tabs;
 pub mod todo_item;
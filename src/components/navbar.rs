@@ -22,10 +22,18 @@ pub fn Navbar() -> Element {
                 to: Route::TodosPage {},
                 "Todos"
             }
+            Link {
+                to: Route::RoutinesPage {},
+                "Routines"
+            }
             Link {
                 to: Route::TimelinePageNoParam {},
                 "Life"
             }
+            Link {
+                to: Route::SettingsPage {},
+                "Settings"
+            }
         }
 
         Outlet::<Route> {}
@@ -0,0 +1,228 @@
+use crate::managers::routine_manager::get_routine_manager;
+use crate::models::routine::{RoutineData, TimeBlock, MAX_DURATION_MINUTES};
+use chrono::{NaiveTime, Weekday};
+use dioxus::prelude::*;
+use tracing::error;
+use uuid::Uuid;
+
+const WEEKDAYS: [(Weekday, &str); 7] = [
+    (Weekday::Mon, "M"),
+    (Weekday::Tue, "T"),
+    (Weekday::Wed, "W"),
+    (Weekday::Thu, "T"),
+    (Weekday::Fri, "F"),
+    (Weekday::Sat, "S"),
+    (Weekday::Sun, "S"),
+];
+
+#[derive(Props, Clone, PartialEq)]
+pub struct RoutineEditorProps {
+    routine_id: Uuid,
+    routine_data: RoutineData,
+    on_data_change: EventHandler<()>,
+}
+
+#[component]
+pub fn RoutineEditor(props: RoutineEditorProps) -> Element {
+    let mut new_title = use_signal(String::new);
+    let mut new_start = use_signal(|| "08:00".to_string());
+    let mut new_duration = use_signal(|| "60".to_string());
+    let mut new_color = use_signal(|| props.routine_data.color.clone());
+    let mut form_error = use_signal(String::new);
+    let routine_id = props.routine_id;
+
+    let update_routine = {
+        let on_data_change = props.on_data_change;
+        move |update: Box<dyn FnOnce(&mut RoutineData)>| {
+            spawn(async move {
+                let manager = get_routine_manager();
+                match manager.get_routine(routine_id).await {
+                    Ok(Some(mut data)) => {
+                        update(&mut data);
+                        match manager.update_routine(routine_id, data).await {
+                            Ok(_) => on_data_change.call(()),
+                            Err(e) => error!("Failed to update routine: {:?}", e),
+                        }
+                    }
+                    _ => error!("Failed to get routine {}", routine_id),
+                }
+            });
+        }
+    };
+
+    let add_block = {
+        let on_data_change = props.on_data_change;
+        move |ev: FormEvent| {
+            ev.prevent_default();
+
+            let title = new_title.read().trim().to_string();
+            if title.is_empty() {
+                form_error.set("Title is required".to_string());
+                return;
+            }
+            let Ok(start) = NaiveTime::parse_from_str(&new_start.read(), "%H:%M") else {
+                form_error.set("Invalid start time".to_string());
+                return;
+            };
+            let duration = match new_duration.read().parse::<u32>() {
+                Ok(minutes) if (1..=MAX_DURATION_MINUTES).contains(&minutes) => minutes,
+                _ => {
+                    form_error.set(format!(
+                        "Duration must be between 1 and {} minutes",
+                        MAX_DURATION_MINUTES
+                    ));
+                    return;
+                }
+            };
+
+            form_error.set(String::new());
+            new_title.set(String::new());
+            let block = TimeBlock::new(title, start, duration, new_color.read().clone());
+
+            spawn(async move {
+                match get_routine_manager().add_block(routine_id, block).await {
+                    Ok(_) => on_data_change.call(()),
+                    Err(e) => error!("Failed to add block: {:?}", e),
+                }
+            });
+        }
+    };
+
+    let delete_block = {
+        let on_data_change = props.on_data_change;
+        move |block_id: Uuid| {
+            spawn(async move {
+                match get_routine_manager()
+                    .delete_block(routine_id, block_id)
+                    .await
+                {
+                    Ok(_) => on_data_change.call(()),
+                    Err(e) => error!("Failed to delete block: {:?}", e),
+                }
+            });
+        }
+    };
+
+    let toggle_weekday = {
+        let on_data_change = props.on_data_change;
+        move |(mut block, weekday): (TimeBlock, Weekday)| {
+            let active = block.is_active_on(weekday);
+            block.set_active_on(weekday, !active);
+            spawn(async move {
+                match get_routine_manager().update_block(routine_id, block).await {
+                    Ok(_) => on_data_change.call(()),
+                    Err(e) => error!("Failed to update block: {:?}", e),
+                }
+            });
+        }
+    };
+
+    rsx! {
+        div { class: "routine-editor",
+            div { class: "routine-settings",
+                label { "Name: " }
+                input {
+                    r#type: "text",
+                    value: "{props.routine_data.name}",
+                    onchange: {
+                        let update_routine = update_routine.clone();
+                        move |evt: Event<FormData>| {
+                            let name = evt.value();
+                            update_routine(Box::new(move |data| data.name = name));
+                        }
+                    }
+                }
+                label { "Color: " }
+                input {
+                    r#type: "color",
+                    class: "color-input",
+                    value: "{props.routine_data.color}",
+                    onchange: {
+                        let update_routine = update_routine.clone();
+                        move |evt: Event<FormData>| {
+                            let color = evt.value();
+                            update_routine(Box::new(move |data| data.color = color));
+                        }
+                    }
+                }
+            }
+
+            ul { class: "routine-blocks",
+                {props.routine_data.blocks.iter().map(|block| {
+                    let block_id = block.id;
+                    let start_label = block.start.format("%H:%M").to_string();
+                    let end_label = block.end().format("%H:%M").to_string();
+                    rsx! {
+                        li {
+                            key: "{block.id}",
+                            class: "routine-block",
+                            span {
+                                class: "routine-block-color",
+                                style: "background-color: {block.color};"
+                            }
+                            span { class: "routine-block-time", "{start_label} - {end_label}" }
+                            span { class: "routine-block-title", "{block.title}" }
+                            div { class: "weekday-toggles",
+                                {WEEKDAYS.iter().map(|(weekday, label)| {
+                                    let weekday = *weekday;
+                                    let block = block.clone();
+                                    let toggle_weekday = toggle_weekday.clone();
+                                    rsx! {
+                                        button {
+                                            key: "{weekday}",
+                                            class: if block.is_active_on(weekday) { "weekday-toggle active" } else { "weekday-toggle" },
+                                            title: "{weekday}",
+                                            onclick: move |_| toggle_weekday((block.clone(), weekday)),
+                                            "{label}"
+                                        }
+                                    }
+                                })}
+                            }
+                            button {
+                                class: "delete-btn",
+                                onclick: {
+                                    let delete_block = delete_block.clone();
+                                    move |_| delete_block(block_id)
+                                },
+                                "Delete"
+                            }
+                        }
+                    }
+                })}
+            }
+
+            form {
+                class: "routine-block-form",
+                onsubmit: add_block,
+                input {
+                    r#type: "text",
+                    placeholder: "Block title...",
+                    value: "{new_title}",
+                    oninput: move |ev| new_title.set(ev.value())
+                }
+                input {
+                    r#type: "time",
+                    value: "{new_start}",
+                    oninput: move |ev| new_start.set(ev.value())
+                }
+                input {
+                    r#type: "number",
+                    min: "1",
+                    max: "{MAX_DURATION_MINUTES}",
+                    value: "{new_duration}",
+                    oninput: move |ev| new_duration.set(ev.value())
+                }
+                input {
+                    r#type: "color",
+                    class: "color-input",
+                    value: "{new_color}",
+                    oninput: move |ev| new_color.set(ev.value())
+                }
+                button { r#type: "submit", "Add" }
+            }
+            {(!form_error().is_empty()).then(|| rsx!(
+                span { class: "error", "{form_error}" }
+            ))}
+        }
+    }
+}
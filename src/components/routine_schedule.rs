@@ -0,0 +1,47 @@
+use crate::managers::routine_manager::{ScheduledBlock, MINUTES_PER_DAY};
+use dioxus::prelude::*;
+
+#[component]
+pub fn DaySchedule(blocks: Vec<ScheduledBlock>) -> Element {
+    rsx! {
+        div { class: "day-schedule",
+            {(0..24).map(|hour| {
+                let top = hour as f32 * 60.0 / MINUTES_PER_DAY as f32 * 100.0;
+                rsx! {
+                    div {
+                        key: "hour-{hour}",
+                        class: "schedule-hour",
+                        style: "top: {top}%;",
+                        span { class: "schedule-hour-label", "{hour:02}:00" }
+                    }
+                }
+            })}
+            {blocks.iter().map(|scheduled| {
+                let block = &scheduled.block;
+                // Blocks running past midnight end with the day and go on at the
+                // top of the next one
+                let (start, end) = if scheduled.continued {
+                    (0, block.end_minute() - MINUTES_PER_DAY)
+                } else {
+                    (block.start_minute(), block.end_minute())
+                };
+                let end = end.min(MINUTES_PER_DAY);
+                let top = start as f32 / MINUTES_PER_DAY as f32 * 100.0;
+                let height = (end - start) as f32 / MINUTES_PER_DAY as f32 * 100.0;
+                let start_label = block.start.format("%H:%M").to_string();
+                let end_label = block.end().format("%H:%M").to_string();
+
+                rsx! {
+                    div {
+                        key: "{block.id}-{scheduled.continued}",
+                        class: "schedule-block",
+                        style: "top: {top}%; height: {height}%; background-color: {block.color};",
+                        title: "{scheduled.routine_name}",
+                        span { class: "schedule-block-time", "{start_label} - {end_label}" }
+                        span { class: "schedule-block-title", "{block.title}" }
+                    }
+                }
+            })}
+        }
+    }
+}
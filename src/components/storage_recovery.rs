@@ -0,0 +1,196 @@
+use crate::managers::DataStore;
+use crate::storage::{Conflict, ConflictResolution, Corruption, StorageEvent};
+use dioxus::prelude::*;
+use tracing::error;
+
+const STORAGE_RECOVERY_CSS: Asset = asset!("/assets/styling/storage_recovery.css");
+
+// Shown when a data file failed to load and was quarantined instead of being replaced,
+// or was written by a newer version of the app and is only being read
+#[component]
+pub fn StorageRecovery() -> Element {
+    let mut problems = use_signal(Vec::<(DataStore, Corruption)>::new);
+    let mut newer = use_signal(Vec::<(DataStore, u32)>::new);
+    let mut messages = use_signal(Vec::<String>::new);
+
+    use_future(move || async move {
+        let mut found = Vec::new();
+        let mut found_newer = Vec::new();
+        for store in DataStore::ALL {
+            if let Some(corruption) = store.corruption().await {
+                found.push((store, corruption));
+            }
+            if let Some(version) = store.newer_version().await {
+                found_newer.push((store, version));
+            }
+        }
+        problems.set(found);
+        newer.set(found_newer);
+    });
+
+    let resolve = move |store: DataStore, restore: bool| {
+        spawn(async move {
+            let result = if restore {
+                store.restore_from_backup().await.map(|backup| {
+                    let name = backup
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    format!("{} restored from {}", store.label(), name)
+                })
+            } else {
+                store
+                    .start_fresh()
+                    .await
+                    .map(|_| format!("{} started fresh", store.label()))
+            };
+            match result {
+                Ok(message) => {
+                    problems.write().retain(|(s, _)| *s != store);
+                    messages
+                        .write()
+                        .push(format!("{}. Reopen the page to see it.", message));
+                }
+                Err(e) => {
+                    error!("Failed to resolve {} storage: {}", store.label(), e);
+                    messages.write().push(format!("{}: {}", store.label(), e));
+                }
+            }
+        });
+    };
+
+    if problems.read().is_empty() && newer.read().is_empty() && messages.read().is_empty() {
+        return rsx! {};
+    }
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: STORAGE_RECOVERY_CSS }
+        div { class: "storage-recovery",
+            {problems.read().iter().map(|(store, corruption)| {
+                let store = *store;
+                rsx! {
+                    div { class: "storage-problem", key: "{store.label()}",
+                        div { class: "storage-problem-text",
+                            strong { "{store.label()} data could not be read." }
+                            span { " It was moved to {corruption.quarantined_path.display()} and nothing has been overwritten." }
+                            div { class: "storage-problem-reason", "{corruption.reason}" }
+                        }
+                        div { class: "storage-problem-actions",
+                            button {
+                                class: "restore-btn",
+                                onclick: move |_| resolve(store, true),
+                                "Restore from backup"
+                            }
+                            button {
+                                class: "fresh-btn",
+                                onclick: move |_| resolve(store, false),
+                                "Start fresh"
+                            }
+                        }
+                    }
+                }
+            })}
+            {newer.read().iter().map(|(store, version)| rsx! {
+                div { class: "storage-problem", key: "newer-{store.label()}",
+                    div { class: "storage-problem-text",
+                        strong { "{store.label()} was saved by a newer version of the app." }
+                        span { " It is open read-only and changes won't be saved. Update the app to edit it." }
+                        div { class: "storage-problem-reason", "Schema version {version}" }
+                    }
+                }
+            })}
+            {messages.read().iter().enumerate().map(|(index, message)| rsx! {
+                div { class: "storage-message", key: "{index}",
+                    "{message}"
+                }
+            })}
+            if problems.read().is_empty() {
+                button {
+                    class: "storage-dismiss",
+                    onclick: move |_| messages.write().clear(),
+                    "Dismiss"
+                }
+            }
+        }
+    }
+}
+
+// Shown when a data file was changed elsewhere, e.g. by a sync client, while this
+// window had edits that weren't saved yet
+#[component]
+pub fn StorageConflicts() -> Element {
+    let mut conflicts = use_signal(Vec::<(DataStore, Conflict)>::new);
+
+    use_future(move || async move {
+        for store in DataStore::ALL {
+            spawn(async move {
+                let mut events = store.subscribe().await;
+                loop {
+                    let event = store.next_event(&mut events).await;
+                    conflicts.write().retain(|(s, _)| *s != store);
+                    if event == StorageEvent::Conflict {
+                        if let Some(conflict) = store.conflict().await {
+                            conflicts.write().push((store, conflict));
+                        }
+                    }
+                }
+            });
+        }
+    });
+
+    let resolve = move |store: DataStore, resolution: ConflictResolution| {
+        spawn(async move {
+            match store.resolve_conflict(resolution).await {
+                Ok(()) => conflicts.write().retain(|(s, _)| *s != store),
+                Err(e) => error!("Failed to resolve {} conflict: {}", store.label(), e),
+            }
+        });
+    };
+
+    if conflicts.read().is_empty() {
+        return rsx! {};
+    }
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: STORAGE_RECOVERY_CSS }
+        div { class: "storage-recovery",
+            {conflicts.read().iter().map(|(store, conflict)| {
+                let store = *store;
+                let detected_at = conflict.detected_at.format("%H:%M");
+                rsx! {
+                    div { class: "storage-problem", key: "{store.label()}",
+                        div { class: "storage-problem-text",
+                            strong { "{store.label()} changed elsewhere at {detected_at}." }
+                            span { " Everything else was combined, but these were changed in both places:" }
+                            ul { class: "storage-conflict-fields",
+                                {conflict.fields.iter().map(|field| {
+                                    let mine = field.mine.as_deref().unwrap_or("deleted");
+                                    let theirs = field.theirs.as_deref().unwrap_or("deleted");
+                                    rsx! {
+                                        li { key: "{field.path}",
+                                            span { class: "storage-conflict-path", "{field.path}" }
+                                            " here: {mine}, elsewhere: {theirs}"
+                                        }
+                                    }
+                                })}
+                            }
+                            div { class: "storage-problem-reason", "{conflict.file_path.display()}" }
+                        }
+                        div { class: "storage-problem-actions",
+                            button {
+                                class: "restore-btn",
+                                onclick: move |_| resolve(store, ConflictResolution::KeepMine),
+                                "Keep mine"
+                            }
+                            button {
+                                class: "fresh-btn",
+                                onclick: move |_| resolve(store, ConflictResolution::UseTheirs),
+                                "Use theirs"
+                            }
+                        }
+                    }
+                }
+            })}
+        }
+    }
+}
@@ -11,8 +11,8 @@ pub fn BottomPanel() -> Element {
     let yaml_state = use_context::<Signal<Yaml>>();
 
     let add_new_item = move |_| {
-        let current_view = &app_state().view;
-        let default_start = if *current_view == "Lifetime" {
+        let adds_period = app_state().shows_periods();
+        let default_start = if adds_period {
             let now = Local::now();
             format!("{}-{:02}", now.year(), now.month())
         } else {
@@ -36,13 +36,15 @@ pub fn BottomPanel() -> Element {
             }
         };
 
-        let new_item = if *current_view == "Lifetime" {
+        let new_item = if adds_period {
             LegendItem {
                 id: Uuid::new_v4(),
                 name: "New Period".to_string(),
                 start: default_start.clone(),
                 color: "#6495ED".to_string(),
                 is_event: false,
+                end: None,
+                track: None,
             }
         } else {
             LegendItem {
@@ -51,6 +53,8 @@ pub fn BottomPanel() -> Element {
                 start: default_start.clone(),
                 color: "#6495ED".to_string(),
                 is_event: true,
+                end: None,
+                track: None,
             }
         };
 
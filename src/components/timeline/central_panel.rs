@@ -1,6 +1,7 @@
 use crate::components::timeline::events_view::EventView;
 use crate::components::timeline::lifetime_view::LifetimeView;
-use crate::models::timeline::MyLifeApp;
+use crate::components::timeline::track_view::TrackView;
+use crate::models::timeline::{MyLifeApp, ZoomLevel};
 use dioxus::prelude::*;
 use uuid::Uuid;
 
@@ -9,25 +10,48 @@ pub fn CentralPanel() -> Element {
     let mut app_state = use_context::<Signal<MyLifeApp>>();
 
     let on_period_click = move |period_id: Uuid| {
-        app_state.with_mut(|state| {
-            state.view = "EventView".to_string();
-            state.selected_life_period = Some(period_id);
-        });
+        app_state.write().open_period(period_id);
+    };
+
+    // Escape or Backspace zooms out, Home shows the whole life again, 1-4 pick the
+    // level the whole life is shown at and 5 stacks the periods by track
+    let on_key_down = move |evt: Event<KeyboardData>| match evt.key() {
+        Key::Escape | Key::Backspace => app_state.write().zoom_out(),
+        Key::Home => app_state.write().zoom_to(0),
+        Key::Character(key) => {
+            let index = key.parse::<usize>().ok().and_then(|n| n.checked_sub(1));
+            match index {
+                Some(index) if index < ZoomLevel::ROOTS.len() => {
+                    app_state.write().set_root_level(ZoomLevel::ROOTS[index])
+                }
+                Some(index) if index == ZoomLevel::ROOTS.len() => app_state.write().show_tracks(),
+                _ => {}
+            }
+        }
+        _ => {}
     };
 
     rsx! {
         div {
             class: "central-panel",
+            tabindex: "0",
+            onkeydown: on_key_down,
             div {
                 class: "central-content",
                 {
                     match app_state().view.as_str() {
-                        "Lifetime" => rsx! {
+                        _ if app_state().is_grid_view() => rsx! {
                             LifetimeView {
                                 on_period_click: on_period_click
                             }
                         },
 
+                        "Tracks" => rsx! {
+                            TrackView {
+                                on_period_click: on_period_click
+                            }
+                        },
+
                         "EventView" => {
                             if let Some(period_id) = app_state().selected_life_period {
                                 rsx! {
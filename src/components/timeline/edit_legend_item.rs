@@ -1,15 +1,12 @@
 use crate::managers::timeline_manager::get_timeline_manager;
 use crate::models::timeline::{LifePeriod, LifePeriodEvent, MyLifeApp, Yaml};
 use crate::utils::date_utils::is_valid_date;
+use crate::utils::validation::is_valid_hex_color;
 use chrono::NaiveDate;
 use dioxus::prelude::*;
 use tracing::{debug, warn};
 use uuid::Uuid;
 
-fn is_valid_hex_color(color: &str) -> bool {
-    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
-}
-
 #[component]
 pub fn EditLegendItem() -> Element {
     let mut app_state = use_context::<Signal<MyLifeApp>>();
@@ -17,6 +14,7 @@ pub fn EditLegendItem() -> Element {
     let mut color_input = use_signal(String::new);
     let mut date_error = use_signal(String::new);
     let mut current_date = use_signal(String::new);
+    let mut end_error = use_signal(String::new);
     let mut pending_update =
         use_signal(|| None::<(Option<LifePeriod>, Option<(Uuid, LifePeriodEvent)>)>);
 
@@ -28,22 +26,12 @@ pub fn EditLegendItem() -> Element {
                     .iter()
                     .find(|p| p.id == Some(app_state().selected_life_period.unwrap()))
                 {
-                    let period_start =
-                        NaiveDate::parse_from_str(&format!("{}-01", period.start), "%Y-%m-%d")
-                            .unwrap_or_default();
-                    let period_end = yaml_state()
-                        .life_periods
-                        .iter()
-                        .find(|p| p.start > period.start)
-                        .map(|next_period| {
-                            NaiveDate::parse_from_str(
-                                &format!("{}-01", next_period.start),
-                                "%Y-%m-%d",
-                            )
-                            .unwrap_or_default()
-                        })
-                        .unwrap_or_else(|| chrono::Local::now().date_naive());
-                    return (Some(period_start), Some(period_end));
+                    let today = chrono::Local::now().date_naive();
+                    if let Some((start, end)) =
+                        period.id.and_then(|id| yaml_state().period_span(id, today))
+                    {
+                        return (Some(start), Some(end));
+                    }
                 }
             }
         }
@@ -89,7 +77,7 @@ pub fn EditLegendItem() -> Element {
     });
 
     let update_yaml_item = move |_| {
-        if date_error().is_empty() {
+        if date_error().is_empty() && end_error().is_empty() {
             if let Some(item) = app_state().item_state {
                 let new_yaml = yaml_state();
 
@@ -135,6 +123,8 @@ pub fn EditLegendItem() -> Element {
                     {
                         period.name = item.name.clone();
                         period.start = item.start.clone();
+                        period.end = item.end.clone();
+                        period.track = item.track.clone();
                         period.color = item.color.clone();
                         period.clone()
                     } else {
@@ -142,6 +132,8 @@ pub fn EditLegendItem() -> Element {
                             id: Some(item.id),
                             name: item.name.clone(),
                             start: item.start.clone(),
+                            end: item.end.clone(),
+                            track: item.track.clone(),
                             color: item.color.clone(),
                             events: Vec::new(),
                         };
@@ -163,6 +155,7 @@ pub fn EditLegendItem() -> Element {
     };
 
     let close_modal = move |_| {
+        end_error.set(String::new());
         app_state.write().item_state = None;
         app_state.write().temp_start_date = String::new();
     };
@@ -219,6 +212,26 @@ pub fn EditLegendItem() -> Element {
         }
     };
 
+    // An empty end leaves the period open until the next one on its track
+    let update_end = move |evt: Event<FormData>| {
+        let end = evt.value().trim().to_string();
+        let start = app_state()
+            .item_state
+            .as_ref()
+            .map(|item| item.start.clone())
+            .unwrap_or_default();
+        if !end.is_empty() && !is_valid_date(&end, true) {
+            end_error.set("Invalid end, expected YYYY-MM".to_string());
+        } else if !end.is_empty() && end < start {
+            end_error.set("The end can't be before the start".to_string());
+        } else {
+            end_error.set(String::new());
+        }
+        if let Some(item) = app_state.write().item_state.as_mut() {
+            item.end = Some(end).filter(|end| !end.is_empty());
+        }
+    };
+
     let color_preview = move || {
         if is_valid_hex_color(&color_input()) {
             color_input().to_string()
@@ -298,6 +311,25 @@ pub fn EditLegendItem() -> Element {
                     {(!date_error().is_empty()).then(|| rsx!(
                         span { class: "error", "{date_error}" }
                     ))}
+                    if !app_state().item_state.as_ref().unwrap().is_event {
+                        input {
+                            placeholder: "Last month (optional)",
+                            value: "{app_state().item_state.as_ref().unwrap().end.clone().unwrap_or_default()}",
+                            oninput: update_end,
+                        }
+                        {(!end_error().is_empty()).then(|| rsx!(
+                            span { class: "error", "{end_error}" }
+                        ))}
+                        input {
+                            placeholder: "Track (optional)",
+                            value: "{app_state().item_state.as_ref().unwrap().track.clone().unwrap_or_default()}",
+                            oninput: move |evt| {
+                                if let Some(item) = app_state.write().item_state.as_mut() {
+                                    item.track = Some(evt.value()).filter(|track| !track.trim().is_empty());
+                                }
+                            }
+                        }
+                    }
                     div {
                         class: "color-picker",
                         label { "Color: " }
@@ -315,7 +347,7 @@ pub fn EditLegendItem() -> Element {
                         class: "modal-buttons",
                         button {
                             onclick: update_yaml_item,
-                            disabled: !date_error().is_empty(),
+                            disabled: !date_error().is_empty() || !end_error().is_empty(),
                             "Save"
                         }
                         button {
@@ -1,5 +1,6 @@
-use crate::models::timeline::{LifePeriodEvent, Yaml};
-use chrono::{Duration, Local, NaiveDate};
+use crate::models::timeline::Yaml;
+use crate::utils::calendar;
+use chrono::Local;
 use dioxus::prelude::*;
 use uuid::Uuid;
 
@@ -25,40 +26,34 @@ pub fn EventView(selected_life_period_id: Uuid) -> Element {
                 };
             }
 
-            let mut sorted_events = period.events.clone();
-            sorted_events.sort_by(|a, b| a.start.cmp(&b.start));
+            let today = Local::now().date_naive();
+            let yaml = yaml_state();
+            let end_date = yaml
+                .period_span(selected_life_period_id, today)
+                .map_or(today, |(_, end)| end);
+            let events = calendar::spans(
+                &period.events,
+                |event| calendar::day(&event.start),
+                end_date,
+            );
+            let start_date = events
+                .first()
+                .map(|span| span.start)
+                .or_else(|| calendar::month_start(&period.start))
+                .unwrap_or(today);
 
-            let start_date = sorted_events
-                .iter()
-                .filter_map(|event| NaiveDate::parse_from_str(&event.start, "%Y-%m-%d").ok())
-                .min()
-                .unwrap_or_else(|| {
-                    NaiveDate::parse_from_str(&format!("{}-01", period.start), "%Y-%m-%d")
-                        .unwrap_or(Local::now().date_naive())
-                });
-
-            let end_date = yaml_state()
-                .life_periods
-                .iter()
-                .find(|p| p.start > period.start)
-                .and_then(|next_period| {
-                    NaiveDate::parse_from_str(&format!("{}-01", next_period.start), "%Y-%m-%d").ok()
-                })
-                .unwrap_or_else(|| Local::now().date_naive());
-
-            let total_days = (end_date - start_date).num_days() as usize;
             let cols = 28;
 
             rsx! {
                 div {
                     class: "event-view",
                     style: "grid-template-columns: repeat({cols}, 1fr);",
-                    {(0..total_days).map(|day| {
-                        let date = start_date + Duration::days(day as i64);
-                        let color = get_color_for_event(&date, &sorted_events, &end_date);
+                    {calendar::days(start_date, end_date).map(|date| {
+                        let color = calendar::span_at(&events, date)
+                            .map_or("transparent", |span| span.item.color.as_str());
                         rsx! {
                             div {
-                                key: "{day}",
+                                key: "{date}",
                                 class: "event-cell",
                                 style: "background-color: {color};",
                                 title: "{date}"
@@ -75,29 +70,3 @@ pub fn EventView(selected_life_period_id: Uuid) -> Element {
         },
     }
 }
-
-fn get_color_for_event(
-    date: &NaiveDate,
-    events: &[LifePeriodEvent],
-    period_end: &NaiveDate,
-) -> String {
-    for (i, event) in events.iter().enumerate() {
-        if let Ok(event_start) = NaiveDate::parse_from_str(&event.start, "%Y-%m-%d") {
-            let event_end = if i < events.len() - 1 {
-                if let Ok(next_start) = NaiveDate::parse_from_str(&events[i + 1].start, "%Y-%m-%d")
-                {
-                    next_start
-                } else {
-                    *period_end
-                }
-            } else {
-                *period_end
-            };
-
-            if date >= &event_start && date < &event_end {
-                return event.color.clone();
-            }
-        }
-    }
-    "transparent".to_string()
-}
@@ -15,7 +15,7 @@ pub fn Legend() -> Element {
     let legend_items = {
         let mut legend_items = Vec::new();
         match app_state().view.as_str() {
-            "Lifetime" => {
+            _ if app_state().shows_periods() => {
                 let mut sorted_periods = yaml_state().life_periods.clone();
                 sorted_periods.sort_by(|a, b| a.start.cmp(&b.start));
 
@@ -26,6 +26,8 @@ pub fn Legend() -> Element {
                         start: period.start,
                         color: period.color,
                         is_event: false,
+                        end: period.end,
+                        track: period.track,
                     };
                     legend_items.push(rsx! {
                         div {
@@ -36,7 +38,7 @@ pub fn Legend() -> Element {
                             div {
                                 class: "legend-item-text",
                                 style: "color: black; text-align: center; width: 100%;",
-                                "{item.name} ({item.start})"
+                                "{item.name} ({item.dates()})"
                             }
                         }
                     });
@@ -56,6 +58,8 @@ pub fn Legend() -> Element {
                                 start: event.start.clone(),
                                 color: event.color.clone(),
                                 is_event: true,
+                                end: None,
+                                track: None,
                             };
                             legend_items.push(rsx! {
                                 div {
@@ -66,7 +70,7 @@ pub fn Legend() -> Element {
                                     div {
                                         class: "legend-item-text",
                                         style: "color: black; text-align: center; width: 100%;",
-                                        "{item.name} ({item.start})"
+                                        "{item.name} ({item.dates()})"
                                     }
                                 }
                             });
@@ -1,19 +1,57 @@
-use crate::models::timeline::{CellData, LifePeriod, SizeInfo, Yaml};
-use chrono::{Duration, Local, NaiveDate};
+use crate::models::timeline::{
+    CellData, LifePeriod, LifePeriodEvent, MyLifeApp, SizeInfo, Yaml, ZoomFrame, ZoomLevel,
+};
+use crate::utils::calendar;
+use chrono::{Datelike, Duration, Local, NaiveDate};
 use dioxus::prelude::*;
-use tracing::debug;
 use uuid::Uuid;
 
-// Grid calculation functions remain the same
-fn calculate_grid_dimensions(size_info: &SizeInfo, life_expectancy: u32) -> (usize, usize) {
-    let is_landscape = size_info.window_width > size_info.window_height;
-    let cols = if is_landscape { 48 } else { 24 };
-    let rows = if is_landscape {
-        (life_expectancy as usize + 7) / 4
-    } else {
-        (life_expectancy as usize + 3) / 2
+// Cells outside every period
+const EMPTY_CELL_COLOR: &str = "#fafafa";
+// Radius of the dot marking an event, relative to the cell size
+const EVENT_MARK_RATIO: f32 = 0.3;
+
+// The grid on screen: the whole life at the root level, or a zoomed-in cell
+#[derive(Debug, Clone, PartialEq)]
+pub struct GridFrame {
+    level: ZoomLevel,
+    zoomed: Option<ZoomFrame>,
+}
+
+impl GridFrame {
+    pub fn for_app(app: &MyLifeApp) -> Self {
+        let root = ZoomLevel::for_view(&app.view).unwrap_or(ZoomLevel::Months);
+        GridFrame {
+            level: app.zoom.last().map_or(root, |frame| frame.level),
+            zoomed: app.zoom.last().cloned(),
+        }
+    }
+}
+
+fn calculate_grid_dimensions(
+    size_info: &SizeInfo,
+    life_expectancy: u32,
+    frame: &GridFrame,
+    cell_count: usize,
+) -> (usize, usize) {
+    // A zoomed-in cell is laid out as a single row
+    let cols = match (frame.level, &frame.zoomed) {
+        (_, Some(_)) => cell_count.max(1),
+        (ZoomLevel::Decades, None) => cell_count.max(1),
+        (ZoomLevel::Years, None) => 10,
+        (ZoomLevel::Weeks, None) => calendar::WEEKS_PER_YEAR as usize,
+        (_, None) => {
+            let is_landscape = size_info.window_width > size_info.window_height;
+            let cols = if is_landscape { 48 } else { 24 };
+            let rows = if is_landscape {
+                (life_expectancy as usize + 7) / 4
+            } else {
+                (life_expectancy as usize + 3) / 2
+            };
+            return (cols, rows);
+        }
     };
-    (cols, rows)
+    (cols, cell_count.div_ceil(cols).max(1))
 }
 
 fn calculate_cell_size(size_info: &SizeInfo, cols: usize, rows: usize) -> (f32, f32) {
@@ -31,35 +69,126 @@ fn calculate_cell_size(size_info: &SizeInfo, cols: usize, rows: usize) -> (f32,
     (cell_size, gap)
 }
 
+// Cells where periods on several tracks meet are split into a band per period.
+// The cell's own fill is the first band; this gives the x, width and color of
+// the ones drawn over it.
+fn bands(cell: &CellData, x: f32, cell_size: f32) -> Vec<(f32, f32, &str)> {
+    if cell.periods.len() < 2 {
+        return Vec::new();
+    }
+    let width = cell_size / cell.periods.len() as f32;
+    cell.periods
+        .iter()
+        .enumerate()
+        .skip(1)
+        .map(|(index, period)| (x + index as f32 * width, width, period.color.as_str()))
+        .collect()
+}
+
+// The first event starting from `start` up to `end`. `events` is sorted by date.
+fn event_in<'a>(
+    events: &[(NaiveDate, &'a LifePeriodEvent)],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Option<&'a LifePeriodEvent> {
+    let index = events.partition_point(|(date, _)| *date < start);
+    events
+        .get(index)
+        .filter(|(date, _)| *date < end)
+        .map(|(_, event)| *event)
+}
+
+// Each cell's first day and the day after its last
+fn grid_cells(
+    frame: &GridFrame,
+    dob: NaiveDate,
+    life_expectancy: u32,
+) -> Vec<(NaiveDate, NaiveDate)> {
+    let (start, end) = frame.zoomed.as_ref().map_or_else(
+        || (dob, calendar::nth_month(dob, life_expectancy * 12)),
+        |zoomed| (zoomed.start, zoomed.end),
+    );
+    match (frame.level, &frame.zoomed) {
+        (ZoomLevel::Decades, _) => calendar::month_steps(start, end, 120),
+        (ZoomLevel::Years, _) => calendar::month_steps(start, end, 12),
+        (ZoomLevel::Months, _) => calendar::month_steps(start, end, 1),
+        // Each row of the whole-life week grid starts on a birthday
+        (ZoomLevel::Weeks, None) => calendar::life_weeks(dob, life_expectancy).collect(),
+        (ZoomLevel::Weeks, Some(_)) => calendar::week_steps(start, end),
+        (ZoomLevel::Days, _) => calendar::days(start, end)
+            .map(|day| (day, day + Duration::days(1)))
+            .collect(),
+    }
+}
+
+fn cell_label(level: ZoomLevel, start: NaiveDate, end: NaiveDate, dob: NaiveDate) -> String {
+    let age = calendar::months_between(dob, start) / 12;
+    match level {
+        ZoomLevel::Decades => format!("Ages {}–{}", age, age + 9),
+        ZoomLevel::Years => format!("Age {}", age),
+        ZoomLevel::Months => start.format("%B %Y").to_string(),
+        // The last cell of a row in the week grid may hold a 53rd week
+        ZoomLevel::Weeks if end - start > Duration::weeks(1) => {
+            let (first, last) = (start.iso_week(), (end - Duration::days(1)).iso_week());
+            format!("Weeks {}–{}, {}", first.week(), last.week(), first.year())
+        }
+        ZoomLevel::Weeks => {
+            let week = start.iso_week();
+            format!("Week {}, {}", week.week(), week.year())
+        }
+        ZoomLevel::Days => start.format("%a %Y-%m-%d").to_string(),
+    }
+}
+
 fn generate_lifetime_data(
     yaml: &Yaml,
     size_info: &SizeInfo,
+    frame: &GridFrame,
 ) -> Result<(Vec<CellData>, usize, usize, f32, f32, f32, f32), String> {
     let years = yaml.life_expectancy;
-    let (cols, rows) = calculate_grid_dimensions(size_info, years);
+    let dob = calendar::month_start(&yaml.date_of_birth)
+        .ok_or("Invalid date_of_birth format in yaml. Expected YYYY-MM")?;
+    let cells = grid_cells(frame, dob, years);
+
+    let (cols, rows) = calculate_grid_dimensions(size_info, years, frame, cells.len());
     let (cell_size, gap) = calculate_cell_size(size_info, cols, rows);
 
     let total_width = cols as f32 * (cell_size + gap) - gap;
     let total_height = rows as f32 * (cell_size + gap) - gap;
 
-    let dob = parse_date(
-        &yaml.date_of_birth,
-        "Invalid date_of_birth format in yaml. Expected YYYY-MM",
-    )?;
-
     let current_date = Local::now().date_naive();
-    let cell_data: Vec<CellData> = (0..years * 12)
-        .map(|index| {
-            let year = index / 12;
-            let month = index % 12;
-            let cell_date = dob + Duration::days((year * 365 + month * 30) as i64);
-            let (color, period) =
-                get_color_and_period_for_date(cell_date, &yaml.life_periods, current_date);
+    let tracks = yaml.period_tracks(current_date);
+    let mut events: Vec<(NaiveDate, &LifePeriodEvent)> = yaml
+        .life_periods
+        .iter()
+        .flat_map(|period| &period.events)
+        .filter_map(|event| calendar::day(&event.start).map(|date| (date, event)))
+        .collect();
+    events.sort_by_key(|(date, _)| *date);
+    let marks_events = matches!(frame.level, ZoomLevel::Weeks | ZoomLevel::Days);
 
+    // On each track a cell goes to the period covering most of it. For weeks that
+    // is the period holding its Thursday, as ISO weeks go to the month and year
+    // their Thursday falls in.
+    let cell_data: Vec<CellData> = cells
+        .into_iter()
+        .map(|(start, end)| {
+            let periods: Vec<LifePeriod> = tracks
+                .iter()
+                .filter_map(|(_, spans)| calendar::dominant_span(spans, start, end))
+                .map(|span| span.item.clone())
+                .collect();
             CellData {
-                color,
-                period,
-                date: cell_date,
+                color: periods
+                    .first()
+                    .map_or_else(|| EMPTY_CELL_COLOR.to_string(), |p| p.color.clone()),
+                periods,
+                date: start,
+                end,
+                label: cell_label(frame.level, start, end, dob),
+                event_color: event_in(&events, start, end)
+                    .filter(|_| marks_events)
+                    .map(|event| event.color.clone()),
             }
         })
         .collect();
@@ -79,9 +208,13 @@ fn generate_lifetime_data(
 pub fn LifetimeView(on_period_click: EventHandler<Uuid>) -> Element {
     let yaml_state = use_context::<Signal<Yaml>>();
     let mut hovered_period = use_signal(|| None::<Uuid>);
+    let mut hovered_label = use_signal(|| None::<String>);
     let size_info = use_context::<Signal<SizeInfo>>();
+    let mut app_state = use_context::<Signal<MyLifeApp>>();
 
-    let lifetime_data = use_memo(move || generate_lifetime_data(&yaml_state(), &size_info()))();
+    let frame = use_memo(move || GridFrame::for_app(&app_state.read()));
+    let lifetime_data =
+        use_memo(move || generate_lifetime_data(&yaml_state(), &size_info(), &frame()))();
 
     if let Err(error) = &lifetime_data {
         return rsx! {
@@ -97,6 +230,7 @@ pub fn LifetimeView(on_period_click: EventHandler<Uuid>) -> Element {
 
     let handle_mouse_leave = move |_| {
         hovered_period.set(None);
+        hovered_label.set(None);
     };
 
     rsx! {
@@ -111,44 +245,87 @@ pub fn LifetimeView(on_period_click: EventHandler<Uuid>) -> Element {
                 {cell_data.iter().enumerate().map(|(index, cell)| {
                     let row = index / cols;
                     let col = index % cols;
-                    let is_hovered = cell.period.as_ref().map_or(false, |p| p.id == (*hovered_period)());
+                    let is_hovered = cell.periods.iter().any(|p| p.id.is_some() && p.id == hovered_period());
 
                     let x = col as f32 * (cell_size + gap);
                     let y = row as f32 * (cell_size + gap);
                     rsx! {
-                        rect {
-                            key: "{cell.date}",
-                            x: "{x}",
-                            y: "{y}",
-                            width: "{cell_size}",
-                            height: "{cell_size}",
-                            fill: "{cell.color}",
-                            stroke: if is_hovered { "#c800c8" } else { "gray" },
-                            stroke_width: if is_hovered { "0.05" } else { "0.02" },
-                            onclick: {
-                                let period = cell.period.clone();
-                                let on_period_click = on_period_click;
-                                move |_| {
-                                    if let Some(period) = &period {
-                                        on_period_click.call(period.id.unwrap_or_default());
+                        g { key: "{cell.date}",
+                            rect {
+                                x: "{x}",
+                                y: "{y}",
+                                width: "{cell_size}",
+                                height: "{cell_size}",
+                                fill: "{cell.color}",
+                                stroke: if is_hovered { "#c800c8" } else { "gray" },
+                                stroke_width: if is_hovered { "0.05" } else { "0.02" },
+                                onclick: {
+                                    let period = cell.periods.first().cloned();
+                                    let on_period_click = on_period_click;
+                                    let zoomed = frame().level.finer().map(|level| ZoomFrame {
+                                        level,
+                                        start: cell.date,
+                                        end: cell.end,
+                                        label: cell.label.clone(),
+                                    });
+                                    // Cells open into finer ones, days into their period
+                                    move |_| match &zoomed {
+                                        Some(zoomed) => app_state.write().zoom_in(zoomed.clone()),
+                                        None => {
+                                            if let Some(period) = &period {
+                                                on_period_click.call(period.id.unwrap_or_default());
+                                            }
+                                        }
+                                    }
+                                },
+                                onmouseenter: {
+                                    let period_id = cell.periods.first().map(|p| p.id);
+                                    let names: Vec<&str> = cell.periods.iter().map(|p| p.name.as_str()).collect();
+                                    let label = if names.is_empty() {
+                                        cell.label.clone()
+                                    } else {
+                                        format!("{} · {}", cell.label, names.join(", "))
+                                    };
+                                    move |_| {
+                                        hovered_period.set(period_id.flatten());
+                                        hovered_label.set(Some(label.clone()));
                                     }
+                                },
+                            }
+                            for (band_x, band_width, color) in bands(cell, x, cell_size) {
+                                rect {
+                                    x: "{band_x}",
+                                    y: "{y}",
+                                    width: "{band_width}",
+                                    height: "{cell_size}",
+                                    fill: "{color}",
+                                    pointer_events: "none",
+                                }
+                            }
+                            if let Some(event_color) = &cell.event_color {
+                                circle {
+                                    cx: "{x + cell_size / 2.0}",
+                                    cy: "{y + cell_size / 2.0}",
+                                    r: "{cell_size * EVENT_MARK_RATIO}",
+                                    fill: "{event_color}",
+                                    pointer_events: "none",
                                 }
-                            },
-                            onmouseenter: {
-                                let period_id = cell.period.as_ref().map(|p| p.id);
-                                move |_| hovered_period.set(period_id.flatten())
-                            },
+                            }
                         }
                     }
                 })}
             }
+            div {
+                class: "lifetime-cell-label",
+                "{hovered_label().unwrap_or_default()}"
+            }
         }
     }
 }
 
 #[cfg(not(target_arch = "wasm32"))]
-pub fn generate_svg_content(yaml: &Yaml, size_info: &SizeInfo) -> String {
-    let data = generate_lifetime_data(yaml, size_info).unwrap_or_else(|_| {
+pub fn generate_svg_content(yaml: &Yaml, size_info: &SizeInfo, frame: &GridFrame) -> String {
+    let data = generate_lifetime_data(yaml, size_info, frame).unwrap_or_else(|_| {
         // Return default values in case of error
         (Vec::new(), 0, 0, 0.0, 0.0, 0.0, 0.0)
     });
@@ -169,6 +346,21 @@ pub fn generate_svg_content(yaml: &Yaml, size_info: &SizeInfo) -> String {
             r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}" stroke="gray" stroke-width="0.02"/>"#,
             x, y, cell_size, cell_size, cell.color
         ));
+        for (band_x, band_width, color) in bands(cell, x, cell_size) {
+            svg.push_str(&format!(
+                r#"<rect x="{}" y="{}" width="{}" height="{}" fill="{}"/>"#,
+                band_x, y, band_width, cell_size, color
+            ));
+        }
+        if let Some(event_color) = &cell.event_color {
+            svg.push_str(&format!(
+                r#"<circle cx="{}" cy="{}" r="{}" fill="{}"/>"#,
+                x + cell_size / 2.0,
+                y + cell_size / 2.0,
+                cell_size * EVENT_MARK_RATIO,
+                event_color
+            ));
+        }
     }
 
     svg.push_str("</svg>");
@@ -179,53 +371,10 @@ pub fn generate_svg_content(yaml: &Yaml, size_info: &SizeInfo) -> String {
 pub fn get_svg_content() -> Option<String> {
     let yaml_state = use_context::<Signal<Yaml>>();
     let size_info = use_context::<Signal<SizeInfo>>();
+    let app_state = use_context::<Signal<MyLifeApp>>();
 
     let yaml = yaml_state();
     let size_info = size_info();
-    Some(generate_svg_content(&yaml, &size_info))
-}
-
-fn get_color_and_period_for_date(
-    date: NaiveDate,
-    life_periods: &[LifePeriod],
-    current_date: NaiveDate,
-) -> (String, Option<LifePeriod>) {
-    if life_periods.is_empty() {
-        return ("#fafafa".to_string(), None);
-    }
-
-    let mut periods = life_periods.to_vec();
-    periods.sort_by(|a, b| {
-        let a_date = parse_date(&a.start, "Failed to parse period start")
-            .unwrap_or_else(|_| NaiveDate::from_ymd_opt(2000, 1, 1).unwrap());
-        let b_date = parse_date(&b.start, "Failed to parse period start")
-            .unwrap_or_else(|_| NaiveDate::from_ymd_opt(2000, 1, 1).unwrap());
-        a_date.cmp(&b_date)
-    });
-
-    for (idx, period) in periods.iter().enumerate() {
-        let period_start = parse_date(&period.start, "Failed to parse period start")
-            .unwrap_or_else(|_| NaiveDate::from_ymd_opt(2000, 1, 1).unwrap());
-
-        let period_end = if idx == periods.len() - 1 {
-            current_date
-        } else {
-            parse_date(&periods[idx + 1].start, "Failed to parse next period start")
-                .unwrap_or_else(|_| current_date)
-        };
-
-        if date >= period_start && date < period_end {
-            debug!(
-                "Found matching period: name={}, id={:?}",
-                period.name, period.id
-            );
-            return (period.color.clone(), Some(period.clone()));
-        }
-    }
-
-    ("#fafafa".to_string(), None)
-}
-fn parse_date(date_str: &str, error_msg: &str) -> Result<NaiveDate, String> {
-    NaiveDate::parse_from_str(&format!("{}-01", date_str), "%Y-%m-%d")
-        .map_err(|e| format!("{}: {}", error_msg, e))
+    let frame = GridFrame::for_app(&app_state());
+    Some(generate_svg_content(&yaml, &size_info, &frame))
 }
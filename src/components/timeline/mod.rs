@@ -4,4 +4,6 @@ pub mod edit_legend_item;
 pub mod events_view;
 pub mod legend;
 pub mod lifetime_view;
+pub mod problems_panel;
 pub mod top_panel;
+pub mod track_view;
@@ -0,0 +1,101 @@
+use crate::managers::timeline_manager::get_timeline_manager;
+use crate::models::timeline::{LegendItem, MyLifeApp, Yaml};
+use crate::utils::validation::{validate, Diagnostic, Severity};
+use dioxus::prelude::*;
+use uuid::Uuid;
+
+fn count(n: usize, noun: &str) -> String {
+    if n == 1 {
+        format!("1 {}", noun)
+    } else {
+        format!("{} {}s", n, noun)
+    }
+}
+
+// What `validate` finds in the timeline, kept up to date as it's edited, after why
+// its file didn't load if it was set aside. Clicking a problem in a period opens
+// that period for editing.
+#[component]
+pub fn ProblemsPanel() -> Element {
+    let mut app_state = use_context::<Signal<MyLifeApp>>();
+    let yaml_state = use_context::<Signal<Yaml>>();
+    let mut expanded = use_signal(|| false);
+    // Checked again whenever the timeline changes, which includes switching timelines
+    let load_problems = use_resource(move || async move {
+        yaml_state.read();
+        get_timeline_manager().load_problems().await
+    });
+    let found = use_memo(move || validate(&yaml_state()))();
+    let diagnostics: Vec<Diagnostic> = load_problems()
+        .unwrap_or_default()
+        .into_iter()
+        .chain(found)
+        .collect();
+
+    if diagnostics.is_empty() {
+        return rsx! {};
+    }
+    let errors = diagnostics
+        .iter()
+        .filter(|diagnostic| diagnostic.severity == Severity::Error)
+        .count();
+    let warnings = diagnostics.len() - errors;
+    let summary = match (errors, warnings) {
+        (0, warnings) => count(warnings, "warning"),
+        (errors, 0) => count(errors, "error"),
+        (errors, warnings) => format!("{}, {}", count(errors, "error"), count(warnings, "warning")),
+    };
+
+    let mut edit_period = move |index: usize| {
+        let Some(period) = yaml_state().life_periods.get(index).cloned() else {
+            return;
+        };
+        let item = LegendItem {
+            id: period.id.unwrap_or_else(Uuid::new_v4),
+            name: period.name,
+            start: period.start,
+            color: period.color,
+            is_event: false,
+            end: period.end,
+            track: period.track,
+        };
+        app_state.write().temp_start_date = item.start.clone();
+        app_state.write().item_state = Some(item);
+    };
+
+    rsx! {
+        div {
+            class: "problems-panel",
+            button {
+                class: if errors > 0 { "problems-summary has-errors" } else { "problems-summary" },
+                onclick: move |_| expanded.set(!expanded()),
+                "⚠ {summary} in this timeline "
+                span { if expanded() { "▾" } else { "▸" } }
+            }
+            if expanded() {
+                ul {
+                    class: "problems-list",
+                    {diagnostics.into_iter().enumerate().map(|(index, diagnostic)| {
+                        let period = diagnostic.period;
+                        rsx! {
+                            li {
+                                key: "{index}",
+                                class: match diagnostic.severity {
+                                    Severity::Error => "problem problem-error",
+                                    Severity::Warning => "problem problem-warning",
+                                },
+                                onclick: move |_| {
+                                    if let Some(period) = period {
+                                        edit_period(period);
+                                    }
+                                },
+                                code { class: "problem-path", "{diagnostic.path}" }
+                                span { class: "problem-message", "{diagnostic.message}" }
+                            }
+                        }
+                    })}
+                }
+            }
+        }
+    }
+}
@@ -1,5 +1,5 @@
 use crate::managers::timeline_manager::get_timeline_manager;
-use crate::models::timeline::{MyLifeApp, SizeInfo, Yaml};
+use crate::models::timeline::{MyLifeApp, SizeInfo, Yaml, ZoomLevel};
 use crate::utils::screenshot::{save_screenshot, take_screenshot};
 #[cfg(all(not(target_arch = "wasm32"), not(target_os = "android")))]
 use arboard::Clipboard;
@@ -15,6 +15,9 @@ use crate::utils::compression::compress_and_encode;
 #[cfg(target_arch = "wasm32")]
 use crate::utils::screenshot::share_screenshot;
 
+const ZOOM_KEYS_HINT: &str =
+    "Click a cell to zoom in. Esc zooms out, Home shows the whole life, 1-4 switch between decades, years, months and weeks, 5 shows the tracks.";
+
 #[component]
 fn YamlSelector(
     app_state: Signal<MyLifeApp>,
@@ -247,24 +250,92 @@ pub fn TopPanel(y: String) -> Element {
             .build()
     };
 
+    // "Life", then each zoomed-in cell, then the period open in the event view
+    let breadcrumbs: Vec<String> = {
+        let app = app_state();
+        let period = app
+            .selected_life_period
+            .filter(|_| app.view == "EventView")
+            .and_then(|id| {
+                yaml_state()
+                    .life_periods
+                    .iter()
+                    .find(|period| period.id == Some(id))
+                    .map(|period| period.name.clone())
+            });
+        std::iter::once("Life".to_string())
+            .chain(app.zoom.iter().map(|frame| frame.label.clone()))
+            .chain(period)
+            .collect()
+    };
+
     rsx! {
         div {
             class: "top-panel",
-            if app_state().view == "EventView" {
-                button {
-                    onclick: move |_| {
-                        app_state.write().view = "Lifetime".to_string();
-                    },
-                    span { "⬅" },
+            if app_state().is_grid_view() || app_state().view == "EventView" {
+                nav {
+                    class: "zoom-breadcrumbs",
+                    title: ZOOM_KEYS_HINT,
+                    if !app_state().zoom.is_empty() || app_state().view == "EventView" {
+                        button {
+                            onclick: move |_| app_state.write().zoom_out(),
+                            span { "⬅" },
+                        }
+                    }
+                    {breadcrumbs.iter().enumerate().map(|(depth, label)| {
+                        let is_current = depth + 1 == breadcrumbs.len();
+                        rsx! {
+                            span { key: "{depth}",
+                                if depth > 0 {
+                                    span { class: "zoom-separator", "› " }
+                                }
+                                if is_current {
+                                    span { class: "zoom-current", "{label}" }
+                                } else {
+                                    button {
+                                        class: "zoom-crumb",
+                                        onclick: move |_| app_state.write().zoom_to(depth),
+                                        "{label}"
+                                    }
+                                }
+                            }
+                        }
+                    })}
                 }
             }
-            if app_state().view == "Lifetime" {
+            if app_state().shows_periods() {
                 div {
                     class: "action-buttons",
+                    select {
+                        class: "zoom-level-select",
+                        onchange: move |evt: Event<FormData>| {
+                            let level = ZoomLevel::ROOTS
+                                .into_iter()
+                                .find(|level| level.label() == evt.value());
+                            match level {
+                                Some(level) => app_state.write().set_root_level(level),
+                                None => app_state.write().show_tracks(),
+                            }
+                        },
+                        for level in ZoomLevel::ROOTS {
+                            option {
+                                value: level.label(),
+                                selected: ZoomLevel::for_view(&app_state().view) == Some(level),
+                                "{level.label()}"
+                            }
+                        }
+                        option {
+                            value: "Tracks",
+                            selected: app_state().view == "Tracks",
+                            "Tracks"
+                        }
+                    }
                     button { onclick: load_timeline, "📥 Import" }
                     button { onclick: export_timeline, "📤 Export" }
                     button { onclick: share_timeline, "🔗 Share" }
-                    button { onclick: take_screenshot, "📸 Screenshot" }
+                    if app_state().is_grid_view() {
+                        button { onclick: take_screenshot, "📸 Screenshot" }
+                    }
                 }
 
                 div {
@@ -0,0 +1,101 @@
+use crate::models::timeline::Yaml;
+use crate::utils::calendar;
+use chrono::{Local, NaiveDate};
+use dioxus::prelude::*;
+use uuid::Uuid;
+
+// Height of a bar in the units of its width, which are months
+const BAR_HEIGHT: u32 = 24;
+// Years between the age marks under the tracks
+const AGE_MARK_STEP: usize = 10;
+
+// One row per track across the whole life, so periods that overlap in time show
+// up stacked on top of each other
+#[component]
+pub fn TrackView(on_period_click: EventHandler<Uuid>) -> Element {
+    let yaml_state = use_context::<Signal<Yaml>>();
+    let mut hovered_label = use_signal(|| None::<String>);
+
+    let yaml = yaml_state();
+    let Some(dob) = calendar::month_start(&yaml.date_of_birth) else {
+        return rsx! {
+            div {
+                class: "error-message",
+                "Failed to load timeline: Invalid date_of_birth format in yaml. Expected YYYY-MM"
+            }
+        };
+    };
+    let life_months = yaml.life_expectancy * 12;
+    let today = Local::now().date_naive();
+    let offset =
+        move |date: NaiveDate| calendar::months_between(dob, date).clamp(0, life_months as i32);
+    let tracks = yaml.period_tracks(today);
+
+    rsx! {
+        div {
+            class: "track-view",
+            onmouseleave: move |_| hovered_label.set(None),
+            {tracks.iter().map(|(track, spans)| {
+                rsx! {
+                    div { key: "{track}", class: "track-row",
+                        div { class: "track-name", "{track}" }
+                        svg {
+                            class: "track-bar",
+                            preserve_aspect_ratio: "none",
+                            view_box: "0 0 {life_months} {BAR_HEIGHT}",
+                            rect {
+                                class: "track-background",
+                                width: "{life_months}",
+                                height: "{BAR_HEIGHT}",
+                            }
+                            {spans.iter().filter(|span| span.end > span.start).map(|span| {
+                                let x = offset(span.start);
+                                let width = offset(span.end) - x;
+                                let period_id = span.item.id.unwrap_or_default();
+                                let last_month = span.end.pred_opt().unwrap_or(span.end);
+                                let label = format!(
+                                    "{}: {} – {}",
+                                    span.item.name,
+                                    span.start.format("%Y-%m"),
+                                    last_month.format("%Y-%m")
+                                );
+                                rsx! {
+                                    rect {
+                                        key: "{span.start}-{span.item.name}",
+                                        x: "{x}",
+                                        width: "{width}",
+                                        height: "{BAR_HEIGHT}",
+                                        fill: "{span.item.color}",
+                                        onclick: move |_| on_period_click.call(period_id),
+                                        onmouseenter: move |_| hovered_label.set(Some(label.clone())),
+                                    }
+                                }
+                            })}
+                            line {
+                                class: "track-today",
+                                x1: "{offset(today)}",
+                                x2: "{offset(today)}",
+                                y2: "{BAR_HEIGHT}",
+                            }
+                        }
+                    }
+                }
+            })}
+            div { class: "track-row",
+                div { class: "track-name" }
+                div { class: "track-ages",
+                    {(0..=yaml.life_expectancy as usize).step_by(AGE_MARK_STEP).map(|age| {
+                        let left = age as f32 * 12.0 / life_months.max(1) as f32 * 100.0;
+                        rsx! {
+                            span { key: "{age}", style: "left: {left}%;", "{age}" }
+                        }
+                    })}
+                }
+            }
+            div {
+                class: "lifetime-cell-label",
+                "{hovered_label().unwrap_or_default()}"
+            }
+        }
+    }
+}
@@ -1,5 +1,8 @@
+use chrono::{Duration, Local, NaiveDate};
 use dioxus::prelude::*;
 
+use crate::utils::date_utils::week_start_monday;
+
 #[derive(Clone)]
 pub struct DayInfo {
     pub name: &'static str,
@@ -38,12 +41,17 @@ pub const DAYS: [DayInfo; 7] = [
 ];
 
 #[component]
-pub fn DayTabs(active_day: String, on_day_change: EventHandler<String>) -> Element {
+pub fn DayTabs(
+    active_day: String,
+    on_day_change: EventHandler<String>,
+    week_start: Option<NaiveDate>,
+) -> Element {
     rsx! {
         div {
             class: "todo-tabs",
-            { DAYS.iter().map(|day| {
+            { DAYS.iter().enumerate().map(|(index, day)| {
                 let is_active = day.name == active_day;
+                let date = week_start.map(|start| start + Duration::days(index as i64));
                 rsx! {
                     button {
                         key: "{day.name}",
@@ -57,9 +65,77 @@ pub fn DayTabs(active_day: String, on_day_change: EventHandler<String>) -> Eleme
                             class: "name",
                             "{day.name}"
                         }
+                        if let Some(date) = date {
+                            span {
+                                class: "date",
+                                {date.format("%d.%m").to_string()}
+                            }
+                        }
                     }
                 }
             }) }
         }
     }
 }
+
+#[component]
+pub fn WeekNavigation(
+    week_start: NaiveDate,
+    history: Vec<NaiveDate>,
+    on_week_change: EventHandler<NaiveDate>,
+) -> Element {
+    let current_week = week_start_monday(Local::now().date_naive());
+    let week_end = week_start + Duration::days(6);
+    let label = if week_start == current_week {
+        "This week".to_string()
+    } else {
+        format!(
+            "{} – {}",
+            week_start.format("%b %d"),
+            week_end.format("%b %d, %Y")
+        )
+    };
+
+    rsx! {
+        div {
+            class: "week-navigation",
+            button {
+                class: "week-nav-btn",
+                onclick: move |_| on_week_change.call(week_start - Duration::days(7)),
+                "‹"
+            }
+            span { class: "week-label", "{label}" }
+            button {
+                class: "week-nav-btn",
+                onclick: move |_| on_week_change.call(week_start + Duration::days(7)),
+                "›"
+            }
+            if week_start != current_week {
+                button {
+                    class: "week-nav-today",
+                    onclick: move |_| on_week_change.call(current_week),
+                    "Today"
+                }
+            }
+            if !history.is_empty() {
+                select {
+                    class: "week-history",
+                    value: "",
+                    onchange: move |ev: Event<FormData>| {
+                        if let Ok(date) = NaiveDate::parse_from_str(&ev.value(), "%Y-%m-%d") {
+                            on_week_change.call(date);
+                        }
+                    },
+                    option { value: "", "History" }
+                    {history.iter().map(|week| rsx! {
+                        option {
+                            key: "{week}",
+                            value: "{week}",
+                            {format!("Week of {}", week.format("%b %d, %Y"))}
+                        }
+                    })}
+                }
+            }
+        }
+    }
+}
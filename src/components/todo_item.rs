@@ -11,8 +11,15 @@ pub fn TodoItem(
     ondragover: EventHandler<(DragEvent, usize)>,
     ondrop: EventHandler<DragEvent>,
     ondelete: EventHandler<Uuid>,
+    ontoggle: EventHandler<Todo>,
 ) -> Element {
     let todo_id = todo.id;
+    let is_completed = todo.is_completed();
+    let toggle_todo = todo.clone();
+    let completed_label = todo
+        .completed_at
+        .map(|at| at.format("Done %Y-%m-%d %H:%M").to_string())
+        .unwrap_or_default();
 
     rsx! {
         Fragment {
@@ -24,8 +31,8 @@ pub fn TodoItem(
             }
             li {
                 key: "{todo.id}",
-                class: "todo-item",
-                draggable: true,
+                class: if is_completed { "todo-item completed" } else { "todo-item" },
+                draggable: !is_completed,
                 ondragstart: move |ev| ondragstart.call((ev, todo.clone())),
                 ondragover: move |ev| ondragover.call((ev, index)),
                 ondrop: move |ev| ondrop.call(ev),
@@ -33,10 +40,31 @@ pub fn TodoItem(
                     class: "drag-handle",
                     "⋮"
                 }
+                input {
+                    r#type: "checkbox",
+                    class: "todo-checkbox",
+                    checked: is_completed,
+                    onchange: move |_| ontoggle.call(toggle_todo.clone())
+                }
                 span {
                     class: "todo-content",
+                    title: "{completed_label}",
                     "{todo.content}"
                 }
+                if todo.carried_count > 0 {
+                    span {
+                        class: if todo.carried_count >= 3 { "todo-carried procrastinated" } else { "todo-carried" },
+                        title: "Carried over {todo.carried_count} times",
+                        "↷{todo.carried_count}"
+                    }
+                }
+                if let Some(rule) = &todo.recurrence {
+                    span {
+                        class: "todo-recurrence",
+                        title: "{rule.describe()}",
+                        "↻"
+                    }
+                }
                 button {
                     class: "delete-btn",
                     onclick: {
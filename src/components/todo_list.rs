@@ -1,14 +1,47 @@
 use crate::components::todo_item::TodoItem;
 use crate::managers::todo_manager::get_todo_manager;
+use crate::models::recurrence::{Recurrence, RecurrenceRule};
 use crate::models::todo::Todo;
+use chrono::{Datelike, NaiveDate};
 use dioxus::prelude::*;
 use uuid::Uuid;
 
 const TODO_LIST_CSS: Asset = asset!("/assets/styling/todo_list.css");
 
+fn build_rule(kind: &str, date: NaiveDate, until: &str) -> Option<RecurrenceRule> {
+    let pattern = match kind {
+        "daily" => Recurrence::Daily { interval: 1 },
+        "weekly" => Recurrence::Weekly {
+            interval: 1,
+            weekday: date.weekday(),
+        },
+        "biweekly" => Recurrence::Weekly {
+            interval: 2,
+            weekday: date.weekday(),
+        },
+        "monthly_day" => Recurrence::MonthlyByDay {
+            interval: 1,
+            day: date.day(),
+        },
+        "monthly_weekday" => {
+            let nth = ((date.day() - 1) / 7 + 1) as i32;
+            Recurrence::MonthlyByWeekday {
+                interval: 1,
+                nth: if nth > 4 { -1 } else { nth },
+                weekday: date.weekday(),
+            }
+        }
+        _ => return None,
+    };
+
+    let mut rule = RecurrenceRule::new(pattern, date);
+    rule.until = NaiveDate::parse_from_str(until, "%Y-%m-%d").ok();
+    Some(rule)
+}
+
 #[derive(Props, Clone, PartialEq)]
 pub struct TodoListProps {
-    day: String,
+    date: NaiveDate,
     todos: Vec<Todo>,
     on_todos_change: EventHandler<()>,
 }
@@ -16,13 +49,16 @@ pub struct TodoListProps {
 #[component]
 pub fn TodoList(props: TodoListProps) -> Element {
     let mut new_todo = use_signal(String::new);
+    let mut repeat = use_signal(|| "none".to_string());
+    let mut repeat_until = use_signal(String::new);
+    let mut show_done = use_signal(|| false);
     let mut dragged_todo = use_signal(|| None::<Todo>);
     let mut drop_index = use_signal(|| None::<usize>);
     let todo_manager = get_todo_manager();
 
     let add_todo = {
         let on_todos_change = props.on_todos_change.clone();
-        let day = props.day.clone();
+        let date = props.date;
         move |ev: FormEvent| {
             ev.prevent_default();
 
@@ -35,10 +71,14 @@ pub fn TodoList(props: TodoListProps) -> Element {
             };
 
             if !content.is_empty() {
-                let day = day.clone();
                 let on_todos_change = on_todos_change.clone();
+                let rule = build_rule(&repeat.read(), date, &repeat_until.read());
                 spawn(async move {
-                    if let Ok(()) = todo_manager.create_todo(content, day).await {
+                    let result = match rule {
+                        Some(rule) => todo_manager.create_recurring_todo(content, rule).await,
+                        None => todo_manager.create_todo(content, date).await,
+                    };
+                    if let Ok(()) = result {
                         on_todos_change.call(());
                     }
                 });
@@ -58,10 +98,30 @@ pub fn TodoList(props: TodoListProps) -> Element {
         }
     };
 
+    let handle_toggle = {
+        let on_todos_change = props.on_todos_change.clone();
+        move |todo: Todo| {
+            let on_todos_change = on_todos_change.clone();
+            spawn(async move {
+                let result = if todo.is_completed() {
+                    todo_manager.uncomplete_todo(todo.id, todo.date).await
+                } else {
+                    todo_manager.complete_todo(todo.id, todo.date).await
+                };
+                if let Ok(()) = result {
+                    on_todos_change.call(());
+                }
+            });
+        }
+    };
+
+    let (open_todos, done_todos): (Vec<Todo>, Vec<Todo>) =
+        props.todos.iter().cloned().partition(|t| !t.is_completed());
+
     let handle_drop = {
-        let day = props.day.clone();
+        let date = props.date;
         let on_todos_change = props.on_todos_change.clone();
-        let todos = props.todos.clone();
+        let todos = open_todos.clone();
         move |ev: DragEvent| {
             ev.prevent_default();
 
@@ -81,10 +141,9 @@ pub fn TodoList(props: TodoListProps) -> Element {
                             .map(|(i, t)| (t.id, i as i32))
                             .collect();
 
-                        let day = day.clone();
                         let on_todos_change = on_todos_change.clone();
                         spawn(async move {
-                            if let Ok(()) = todo_manager.update_positions(&day, updates).await {
+                            if let Ok(()) = todo_manager.update_positions(date, updates).await {
                                 on_todos_change.call(());
                             }
                         });
@@ -124,6 +183,26 @@ pub fn TodoList(props: TodoListProps) -> Element {
                     value: "{new_todo.read()}",
                     oninput: move |ev| new_todo.set(ev.value().clone())
                 }
+                select {
+                    class: "todo-repeat",
+                    value: "{repeat}",
+                    onchange: move |ev| repeat.set(ev.value()),
+                    option { value: "none", "Once" }
+                    option { value: "daily", "Daily" }
+                    option { value: "weekly", "Weekly" }
+                    option { value: "biweekly", "Every 2 weeks" }
+                    option { value: "monthly_day", "Monthly (same day)" }
+                    option { value: "monthly_weekday", "Monthly (same weekday)" }
+                }
+                if repeat() != "none" {
+                    input {
+                        r#type: "date",
+                        class: "todo-repeat-until",
+                        title: "Repeat until",
+                        value: "{repeat_until}",
+                        oninput: move |ev| repeat_until.set(ev.value())
+                    }
+                }
                 button {
                     r#type: "submit",
                     "Add"
@@ -131,7 +210,7 @@ pub fn TodoList(props: TodoListProps) -> Element {
             }
             ul {
                 class: "todos",
-                {props.todos.iter().enumerate().map(|(index, todo)| {
+                {open_todos.iter().enumerate().map(|(index, todo)| {
                     let show_placeholder = current_drop_index == Some(index)
                         && current_dragged.as_ref().map(|t| t.id) != Some(todo.id);
 
@@ -142,6 +221,7 @@ pub fn TodoList(props: TodoListProps) -> Element {
                             index: index,
                             show_placeholder: show_placeholder,
                             ondelete: handle_delete.clone(),
+                            ontoggle: handle_toggle.clone(),
                             ondragstart: handle_drag_start.clone(),
                             ondragover: handle_drag_over.clone(),
                             ondrop: handle_drop.clone(),
@@ -149,6 +229,35 @@ pub fn TodoList(props: TodoListProps) -> Element {
                     }
                 })}
             }
+            if !done_todos.is_empty() {
+                div {
+                    class: "done-section",
+                    button {
+                        class: "done-toggle",
+                        onclick: move |_| show_done.toggle(),
+                        if show_done() { "▾ " } else { "▸ " }
+                        "Done ({done_todos.len()})"
+                    }
+                    if show_done() {
+                        ul {
+                            class: "todos done",
+                            {done_todos.iter().enumerate().map(|(index, todo)| rsx! {
+                                TodoItem {
+                                    key: "{todo.id}",
+                                    todo: todo.clone(),
+                                    index: index,
+                                    show_placeholder: false,
+                                    ondelete: handle_delete.clone(),
+                                    ontoggle: handle_toggle.clone(),
+                                    ondragstart: move |_| {},
+                                    ondragover: move |_| {},
+                                    ondrop: move |_| {},
+                                }
+                            })}
+                        }
+                    }
+                }
+            }
         }
     }
 }
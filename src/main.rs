@@ -1,5 +1,7 @@
 use dioxus::prelude::*;
-use views::{HabitsPage, HomePage, TimelinePage, TimelinePageNoParam, TodosPage};
+use views::{
+    HabitsPage, HomePage, RoutinesPage, SettingsPage, TimelinePage, TimelinePageNoParam, TodosPage,
+};
 mod components;
 mod managers;
 mod models;
@@ -8,8 +10,11 @@ mod storage;
 mod utils;
 mod views;
 
+use crate::components::encryption::UnlockPrompt;
+use crate::components::storage_recovery::{StorageConflicts, StorageRecovery};
 use crate::components::window_manager::WindowSizeManager;
 use crate::models::timeline::SizeInfo;
+use crate::storage::{encryption_state, EncryptionState};
 
 use components::navbar::Navbar;
 
@@ -26,12 +31,18 @@ enum Route {
     #[route("/todos")]
     TodosPage {},
 
+    #[route("/routines")]
+    RoutinesPage {},
+
     #[route("/timeline?:y")]
     TimelinePage { y: String },
 
     #[route("/timeline")]
     TimelinePageNoParam,
 
+    #[route("/settings")]
+    SettingsPage {},
+
 }
 
 const FAVICON: Asset = asset!("/assets/favicon.ico");
@@ -51,11 +62,34 @@ fn App() -> Element {
 
     use_context_provider(|| size_info);
 
+    // Encrypted data can't be loaded until the passphrase is entered
+    let mut locked = use_signal(|| encryption_state() == EncryptionState::Locked);
+
+    // Debounced saves may still be queued when the window closes
+    #[cfg(not(target_arch = "wasm32"))]
+    use_drop(|| {
+        // Nothing was loaded, so nothing is pending
+        if encryption_state() == EncryptionState::Locked {
+            return;
+        }
+        managers::flush_all_blocking();
+    });
+
+    if locked() {
+        return rsx! {
+            document::Link { rel: "icon", href: FAVICON }
+            document::Link { rel: "stylesheet", href: MAIN_CSS }
+            UnlockPrompt { on_unlock: move |_| locked.set(false) }
+        };
+    }
+
     rsx! {
         div {
             document::Link { rel: "icon", href: FAVICON }
             document::Link { rel: "stylesheet", href: MAIN_CSS }
             WindowSizeManager {}
+            StorageRecovery {}
+            StorageConflicts {}
 
             Router::<Route> {}
         }
@@ -1,34 +1,383 @@
 // managers/habit_manager.rs
-use crate::models::habit::HabitData;
-use crate::storage::{get_path_manager, JsonStorage};
+use crate::models::habit::{HabitData, HabitMeasure, HabitNote, WeekStart};
+use crate::models::habit_stats::HabitStats;
+use crate::storage::{
+    from_json_column, get_path_manager, parse_column, row_changes, to_json_column, BackupInfo,
+    Conflict, ConflictResolution, Corruption, DiffEntry, JsonFormat, Mergeable, Merged, Merger,
+    Migration, SqliteModel, Storage, StorageEvent, Versioned,
+};
 use chrono::NaiveDate;
 use once_cell::sync::Lazy;
+use rusqlite::{params, Connection, Transaction};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::sync::broadcast;
 use tracing::debug;
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct HabitsStore {
     habits: HashMap<Uuid, HabitData>,
+    #[serde(default)]
+    collapsed_categories: Vec<String>,
+}
+
+impl Versioned for HabitsStore {
+    const SCHEMA_VERSION: u32 = 1;
+    const SCHEMA_MIGRATIONS: &'static [Migration] = &[];
+}
+
+fn merge_habit(
+    merger: &mut Merger,
+    path: &str,
+    base: &HabitData,
+    mine: &HabitData,
+    theirs: &HabitData,
+) -> HabitData {
+    let values = merger
+        .entities(
+            &format!("{}.values", path),
+            &base.values,
+            &mine.values,
+            &theirs.values,
+            |date, _| date.to_string(),
+            |merger, path, base, mine, theirs| merger.value(path, base, mine, theirs),
+        )
+        .into_iter()
+        .map(|(date, value)| (*date, value))
+        .collect();
+    let notes = merger
+        .entities(
+            &format!("{}.notes", path),
+            &base.notes,
+            &mine.notes,
+            &theirs.notes,
+            |date, _| date.to_string(),
+            |merger, path, base, mine, theirs| merger.value(path, base, mine, theirs),
+        )
+        .into_iter()
+        .map(|(date, note)| (*date, note))
+        .collect();
+
+    HabitData {
+        title: merger.field(path, "title", base, mine, theirs, |h| &h.title),
+        start_date: merger.field(path, "start_date", base, mine, theirs, |h| &h.start_date),
+        // Days ticked off on either device are kept
+        completed_days: merger.set(
+            &base.completed_days,
+            &mine.completed_days,
+            &theirs.completed_days,
+        ),
+        week_start: merger.field(path, "week_start", base, mine, theirs, |h| &h.week_start),
+        color: merger.field(path, "color", base, mine, theirs, |h| &h.color),
+        schedule: merger.field(path, "schedule", base, mine, theirs, |h| &h.schedule),
+        measure: merger.field(path, "measure", base, mine, theirs, |h| &h.measure),
+        values,
+        notes,
+        position: merger.field(path, "position", base, mine, theirs, |h| &h.position),
+        archived: merger.field(path, "archived", base, mine, theirs, |h| &h.archived),
+        category: merger.field(path, "category", base, mine, theirs, |h| &h.category),
+    }
+}
+
+impl Mergeable for HabitsStore {
+    fn merge(base: &Self, mine: &Self, theirs: &Self) -> Merged<Self> {
+        let mut merger = Merger::default();
+        let habits = merger
+            .entities(
+                "habits",
+                &base.habits,
+                &mine.habits,
+                &theirs.habits,
+                |_, habit| habit.title.clone(),
+                merge_habit,
+            )
+            .into_iter()
+            .map(|(id, habit)| (*id, habit))
+            .collect();
+        let collapsed_categories = merger.set(
+            &base.collapsed_categories,
+            &mine.collapsed_categories,
+            &theirs.collapsed_categories,
+        );
+        merger.finish(Self {
+            habits,
+            collapsed_categories,
+        })
+    }
+}
+
+// Everything but the per-day rows lives in the `habits` row
+fn same_habit_row(a: &HabitData, b: &HabitData) -> bool {
+    a.title == b.title
+        && a.start_date == b.start_date
+        && a.week_start == b.week_start
+        && a.color == b.color
+        && a.schedule == b.schedule
+        && a.measure == b.measure
+        && a.position == b.position
+        && a.archived == b.archived
+        && a.category == b.category
+}
+
+impl SqliteModel for HabitsStore {
+    const MIGRATIONS: &'static [&'static str] = &["
+        CREATE TABLE habits (
+            id TEXT PRIMARY KEY,
+            title TEXT NOT NULL,
+            start_date TEXT NOT NULL,
+            week_start TEXT NOT NULL,
+            color TEXT NOT NULL,
+            schedule TEXT NOT NULL,
+            measure TEXT,
+            position INTEGER NOT NULL DEFAULT 0,
+            archived INTEGER NOT NULL DEFAULT 0,
+            category TEXT
+        );
+        CREATE TABLE habit_completions (
+            habit_id TEXT NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+            date TEXT NOT NULL,
+            PRIMARY KEY (habit_id, date)
+        );
+        CREATE TABLE habit_values (
+            habit_id TEXT NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+            date TEXT NOT NULL,
+            value REAL NOT NULL,
+            PRIMARY KEY (habit_id, date)
+        );
+        CREATE TABLE habit_notes (
+            habit_id TEXT NOT NULL REFERENCES habits(id) ON DELETE CASCADE,
+            date TEXT NOT NULL,
+            text TEXT NOT NULL,
+            mood INTEGER,
+            PRIMARY KEY (habit_id, date)
+        );
+        CREATE TABLE collapsed_categories (name TEXT PRIMARY KEY);
+    "];
+
+    fn load(conn: &Connection) -> rusqlite::Result<Self> {
+        let mut habits = HashMap::new();
+        let mut statement = conn.prepare(
+            "SELECT id, title, start_date, week_start, color, schedule, measure, position,
+                    archived, category
+             FROM habits",
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: Uuid = parse_column(0, row.get(0)?)?;
+            let week_start: String = row.get(3)?;
+            let mut habit = HabitData::new(
+                row.get(1)?,
+                row.get(2)?,
+                row.get(4)?,
+                WeekStart::from_string(&week_start),
+            );
+            habit.schedule = from_json_column(5, row.get(5)?)?;
+            habit.measure = row
+                .get::<_, Option<String>>(6)?
+                .map(|measure| from_json_column(6, measure))
+                .transpose()?;
+            habit.position = row.get(7)?;
+            habit.archived = row.get(8)?;
+            habit.category = row.get(9)?;
+            habits.insert(id, habit);
+        }
+
+        let mut statement =
+            conn.prepare("SELECT habit_id, date FROM habit_completions ORDER BY date")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: Uuid = parse_column(0, row.get(0)?)?;
+            if let Some(habit) = habits.get_mut(&id) {
+                habit.completed_days.push(row.get(1)?);
+            }
+        }
+
+        let mut statement = conn.prepare("SELECT habit_id, date, value FROM habit_values")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: Uuid = parse_column(0, row.get(0)?)?;
+            if let Some(habit) = habits.get_mut(&id) {
+                habit.values.insert(row.get(1)?, row.get(2)?);
+            }
+        }
+
+        let mut statement = conn.prepare("SELECT habit_id, date, text, mood FROM habit_notes")?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let id: Uuid = parse_column(0, row.get(0)?)?;
+            if let Some(habit) = habits.get_mut(&id) {
+                let note = HabitNote {
+                    text: row.get(2)?,
+                    mood: row.get(3)?,
+                };
+                habit.notes.insert(row.get(1)?, note);
+            }
+        }
+
+        let mut statement = conn.prepare("SELECT name FROM collapsed_categories ORDER BY rowid")?;
+        let collapsed_categories = statement
+            .query_map([], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()?;
+
+        Ok(Self {
+            habits,
+            collapsed_categories,
+        })
+    }
+
+    fn persist(tx: &Transaction, before: &Self, after: &Self) -> rusqlite::Result<()> {
+        for (id, habit) in &after.habits {
+            let old = before.habits.get(id);
+            let habit_id = id.to_string();
+
+            // An upsert rather than a replace, which would cascade to the day rows
+            if !old.is_some_and(|old| same_habit_row(old, habit)) {
+                tx.execute(
+                    "INSERT INTO habits (id, title, start_date, week_start, color, schedule,
+                                         measure, position, archived, category)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                     ON CONFLICT(id) DO UPDATE SET
+                         title = excluded.title,
+                         start_date = excluded.start_date,
+                         week_start = excluded.week_start,
+                         color = excluded.color,
+                         schedule = excluded.schedule,
+                         measure = excluded.measure,
+                         position = excluded.position,
+                         archived = excluded.archived,
+                         category = excluded.category",
+                    params![
+                        habit_id,
+                        habit.title,
+                        habit.start_date,
+                        habit.week_start.to_string(),
+                        habit.color,
+                        to_json_column(&habit.schedule)?,
+                        habit.measure.as_ref().map(to_json_column).transpose()?,
+                        habit.position,
+                        habit.archived,
+                        habit.category,
+                    ],
+                )?;
+            }
+
+            let completions = row_changes(
+                old.into_iter()
+                    .flat_map(|old| old.completed_days.iter())
+                    .map(|date| (date, &())),
+                habit.completed_days.iter().map(|date| (date, &())),
+            );
+            for (date, _) in completions.upserted {
+                tx.execute(
+                    "INSERT OR IGNORE INTO habit_completions (habit_id, date) VALUES (?1, ?2)",
+                    params![habit_id, date],
+                )?;
+            }
+            for date in completions.removed {
+                tx.execute(
+                    "DELETE FROM habit_completions WHERE habit_id = ?1 AND date = ?2",
+                    params![habit_id, date],
+                )?;
+            }
+
+            let values = row_changes(old.into_iter().flat_map(|old| &old.values), &habit.values);
+            for (date, value) in values.upserted {
+                tx.execute(
+                    "INSERT OR REPLACE INTO habit_values (habit_id, date, value)
+                     VALUES (?1, ?2, ?3)",
+                    params![habit_id, date, value],
+                )?;
+            }
+            for date in values.removed {
+                tx.execute(
+                    "DELETE FROM habit_values WHERE habit_id = ?1 AND date = ?2",
+                    params![habit_id, date],
+                )?;
+            }
+
+            let notes = row_changes(old.into_iter().flat_map(|old| &old.notes), &habit.notes);
+            for (date, note) in notes.upserted {
+                tx.execute(
+                    "INSERT OR REPLACE INTO habit_notes (habit_id, date, text, mood)
+                     VALUES (?1, ?2, ?3, ?4)",
+                    params![habit_id, date, note.text, note.mood],
+                )?;
+            }
+            for date in notes.removed {
+                tx.execute(
+                    "DELETE FROM habit_notes WHERE habit_id = ?1 AND date = ?2",
+                    params![habit_id, date],
+                )?;
+            }
+        }
+
+        for id in before.habits.keys() {
+            if !after.habits.contains_key(id) {
+                tx.execute("DELETE FROM habits WHERE id = ?1", [id.to_string()])?;
+            }
+        }
+
+        let collapsed = row_changes(
+            before.collapsed_categories.iter().map(|name| (name, &())),
+            after.collapsed_categories.iter().map(|name| (name, &())),
+        );
+        for (name, _) in collapsed.upserted {
+            tx.execute(
+                "INSERT OR IGNORE INTO collapsed_categories (name) VALUES (?1)",
+                [name],
+            )?;
+        }
+        for name in collapsed.removed {
+            tx.execute("DELETE FROM collapsed_categories WHERE name = ?1", [name])?;
+        }
+        Ok(())
+    }
 }
 
 pub struct HabitManager {
-    storage: JsonStorage<HabitsStore>,
+    storage: Storage<HabitsStore, JsonFormat>,
 }
 
 impl HabitManager {
     pub fn new() -> Result<Self, String> {
         Ok(Self {
-            storage: JsonStorage::new(get_path_manager().habits_file())
-                .map_err(|e| e.to_string())?,
+            storage: Storage::new(get_path_manager().habits_file()).map_err(|e| e.to_string())?,
         })
     }
 
+    // Sorted by user-defined position, then title for habits that share one
     pub async fn get_all_habits(&self) -> Result<Vec<(Uuid, HabitData)>, String> {
         debug!("Getting all habits");
         self.storage
-            .read(|store| store.habits.iter().map(|(k, v)| (*k, v.clone())).collect())
+            .read(|store| {
+                let mut habits: Vec<_> =
+                    store.habits.iter().map(|(k, v)| (*k, v.clone())).collect();
+                habits.sort_by(|(_, a), (_, b)| {
+                    a.position
+                        .cmp(&b.position)
+                        .then_with(|| a.title.cmp(&b.title))
+                });
+                habits
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn get_categories(&self) -> Result<Vec<String>, String> {
+        debug!("Getting habit categories");
+        self.storage
+            .read(|store| {
+                let mut categories: Vec<String> = store
+                    .habits
+                    .values()
+                    .filter_map(|habit| habit.category.clone())
+                    .collect();
+                categories.sort();
+                categories.dedup();
+                categories
+            })
             .await
             .map_err(|e| e.to_string())
     }
@@ -41,10 +390,34 @@ impl HabitManager {
             .map_err(|e| e.to_string())
     }
 
-    pub async fn create_habit(&self, id: Uuid, data: HabitData) -> Result<(), String> {
+    pub async fn get_stats(
+        &self,
+        id: Uuid,
+        today: NaiveDate,
+    ) -> Result<Option<HabitStats>, String> {
+        debug!("Computing stats for habit: {}", id);
+        self.storage
+            .read(|store| {
+                store
+                    .habits
+                    .get(&id)
+                    .map(|habit| HabitStats::compute(habit, today))
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn create_habit(&self, id: Uuid, mut data: HabitData) -> Result<(), String> {
         debug!("Creating habit with id: {}", id);
         self.storage
             .write(|store| {
+                // New habits go to the end of the bar
+                data.position = store
+                    .habits
+                    .values()
+                    .map(|habit| habit.position + 1)
+                    .max()
+                    .unwrap_or(0);
                 store.habits.insert(id, data);
             })
             .await
@@ -78,9 +451,13 @@ impl HabitManager {
         self.storage
             .write(|store| {
                 if let Some(habit) = store.habits.get_mut(&id) {
-                    if !habit.completed_days.contains(&date) {
-                        habit.completed_days.push(date);
-                        habit.completed_days.sort();
+                    match habit.measure.as_ref().map(|m| m.target) {
+                        Some(target) => habit.set_value(date, target),
+                        None if !habit.completed_days.contains(&date) => {
+                            habit.completed_days.push(date);
+                            habit.completed_days.sort();
+                        }
+                        None => {}
                     }
                 }
             })
@@ -94,17 +471,208 @@ impl HabitManager {
             .write(|store| {
                 if let Some(habit) = store.habits.get_mut(&id) {
                     habit.completed_days.retain(|&d| d != date);
+                    habit.values.remove(&date);
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn set_value(&self, id: Uuid, date: NaiveDate, value: f64) -> Result<(), String> {
+        debug!("Setting value {} on {} for habit {}", value, date, id);
+        self.storage
+            .write(|store| {
+                if let Some(habit) = store.habits.get_mut(&id) {
+                    habit.set_value(date, value);
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn set_measure(&self, id: Uuid, measure: Option<HabitMeasure>) -> Result<(), String> {
+        debug!("Updating measure for habit {}", id);
+        self.storage
+            .write(|store| {
+                if let Some(habit) = store.habits.get_mut(&id) {
+                    habit.set_measure(measure);
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn update_positions(&self, updates: Vec<(Uuid, i32)>) -> Result<(), String> {
+        debug!("Updating habit positions");
+        self.storage
+            .write(|store| {
+                for (id, position) in updates {
+                    if let Some(habit) = store.habits.get_mut(&id) {
+                        habit.position = position;
+                    }
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn set_archived(&self, id: Uuid, archived: bool) -> Result<(), String> {
+        debug!("Setting archived={} for habit {}", archived, id);
+        self.storage
+            .write(|store| {
+                if let Some(habit) = store.habits.get_mut(&id) {
+                    habit.archived = archived;
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn set_category(&self, id: Uuid, category: Option<String>) -> Result<(), String> {
+        debug!("Setting category {:?} for habit {}", category, id);
+        let category = category
+            .map(|c| c.trim().to_string())
+            .filter(|c| !c.is_empty());
+        self.storage
+            .write(|store| {
+                if let Some(habit) = store.habits.get_mut(&id) {
+                    habit.category = category;
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn get_collapsed_categories(&self) -> Result<Vec<String>, String> {
+        self.storage
+            .read(|store| store.collapsed_categories.clone())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn set_category_collapsed(
+        &self,
+        category: String,
+        collapsed: bool,
+    ) -> Result<(), String> {
+        debug!("Setting collapsed={} for category {}", collapsed, category);
+        self.storage
+            .write(|store| {
+                store.collapsed_categories.retain(|c| *c != category);
+                if collapsed {
+                    store.collapsed_categories.push(category);
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn get_note(&self, id: Uuid, date: NaiveDate) -> Result<Option<HabitNote>, String> {
+        debug!("Getting note on {} for habit {}", date, id);
+        self.storage
+            .read(|store| {
+                store
+                    .habits
+                    .get(&id)
+                    .and_then(|habit| habit.note_on(date).cloned())
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    // An empty note removes the entry
+    pub async fn set_note(&self, id: Uuid, date: NaiveDate, note: HabitNote) -> Result<(), String> {
+        debug!("Setting note on {} for habit {}", date, id);
+        self.storage
+            .write(|store| {
+                if let Some(habit) = store.habits.get_mut(&id) {
+                    habit.set_note(date, note);
                 }
             })
             .await
             .map_err(|e| e.to_string())
     }
 
+    pub async fn get_notes_between(
+        &self,
+        id: Uuid,
+        from: NaiveDate,
+        to: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, HabitNote)>, String> {
+        debug!("Getting notes from {} to {} for habit {}", from, to, id);
+        self.storage
+            .read(|store| {
+                store
+                    .habits
+                    .get(&id)
+                    .map(|habit| habit.notes_between(from, to))
+                    .unwrap_or_default()
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
     // Additional helper methods could go here
     pub async fn force_save(&self) -> Result<(), String> {
         self.storage.force_save().await.map_err(|e| e.to_string())
     }
 
+    pub async fn flush(&self) -> Result<(), String> {
+        self.storage.flush().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn corruption(&self) -> Option<Corruption> {
+        self.storage.corruption().await
+    }
+
+    pub fn newer_version(&self) -> Option<u32> {
+        self.storage.newer_version()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        self.storage.subscribe()
+    }
+
+    pub async fn conflict(&self) -> Option<Conflict> {
+        self.storage.conflict().await
+    }
+
+    pub async fn resolve_conflict(&self, resolution: ConflictResolution) -> Result<(), String> {
+        self.storage
+            .resolve_conflict(resolution)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn restore_from_backup(&self) -> Result<PathBuf, String> {
+        self.storage
+            .restore_from_backup()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn start_fresh(&self) -> Result<(), String> {
+        self.storage.start_fresh().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn list_backups(&self) -> Result<Vec<BackupInfo>, String> {
+        self.storage.list_backups().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn diff_backup(&self, index: usize) -> Result<Vec<DiffEntry>, String> {
+        self.storage
+            .diff_backup(index)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn restore_backup(&self, index: usize) -> Result<PathBuf, String> {
+        self.storage
+            .restore_backup(index)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
     pub async fn reload(&self) -> Result<(), String> {
         self.storage.reload().await.map_err(|e| e.to_string())
     }
@@ -116,3 +684,156 @@ static HABIT_MANAGER: Lazy<HabitManager> =
 pub fn get_habit_manager() -> &'static HabitManager {
     &*HABIT_MANAGER
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::habit::HabitSchedule;
+    use crate::storage::decode;
+
+    fn round_trip(store: &HabitsStore) -> HabitsStore {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch(HabitsStore::MIGRATIONS[0]).unwrap();
+        let tx = conn.transaction().unwrap();
+        HabitsStore::persist(&tx, &HabitsStore::default(), store).unwrap();
+        tx.commit().unwrap();
+        HabitsStore::load(&conn).unwrap()
+    }
+
+    #[test]
+    fn habits_round_trip_through_sqlite() {
+        let date = |d| NaiveDate::from_ymd_opt(2024, 3, d).unwrap();
+        let mut habit = HabitData::new(
+            "Run".to_string(),
+            date(1),
+            "#ff0000".to_string(),
+            WeekStart::Monday,
+        );
+        habit.schedule = HabitSchedule::TimesPerWeek { times: 3 };
+        habit.completed_days = vec![date(2), date(4)];
+        habit.set_measure(Some(HabitMeasure {
+            unit: "km".to_string(),
+            target: 5.0,
+        }));
+        habit.set_value(date(5), 2.5);
+        habit.set_note(
+            date(4),
+            HabitNote {
+                text: "Windy".to_string(),
+                mood: Some(3),
+            },
+        );
+        habit.category = Some("Health".to_string());
+
+        let mut store = HabitsStore::default();
+        store.habits.insert(Uuid::new_v4(), habit);
+        store.collapsed_categories = vec!["Health".to_string()];
+
+        let loaded = round_trip(&store);
+        assert_eq!(
+            serde_json::to_value(&loaded).unwrap(),
+            serde_json::to_value(&store).unwrap()
+        );
+    }
+
+    #[test]
+    fn habits_from_version_1_get_defaults_for_newer_fields() {
+        let store: HabitsStore =
+            decode::<_, JsonFormat>(include_str!("../../tests/fixtures/habits/v1.json")).unwrap();
+        let habit = store.habits.values().next().unwrap();
+        assert_eq!(habit.title, "Read");
+        assert_eq!(habit.week_start, WeekStart::Monday);
+        assert_eq!(habit.completed_days.len(), 2);
+        assert_eq!(habit.schedule, HabitSchedule::Daily);
+        assert!(habit.measure.is_none() && habit.notes.is_empty() && !habit.archived);
+        assert!(store.collapsed_categories.is_empty());
+    }
+
+    fn manager_in(dir: &tempfile::TempDir) -> HabitManager {
+        HabitManager {
+            storage: Storage::new(dir.path().join("habits.json")).unwrap(),
+        }
+    }
+
+    fn new_habit(title: &str) -> HabitData {
+        HabitData::new(
+            title.to_string(),
+            NaiveDate::from_ymd_opt(2024, 3, 1).unwrap(),
+            "#00ff00".to_string(),
+            WeekStart::Monday,
+        )
+    }
+
+    async fn titles(manager: &HabitManager) -> Vec<String> {
+        manager
+            .get_all_habits()
+            .await
+            .unwrap()
+            .into_iter()
+            .map(|(_, habit)| habit.title)
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn positions_reorder_the_habits() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_in(&dir);
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        for (id, title) in ids.iter().zip(["Read", "Run", "Write"]) {
+            manager.create_habit(*id, new_habit(title)).await.unwrap();
+        }
+        assert_eq!(titles(&manager).await, ["Read", "Run", "Write"]);
+
+        manager
+            .update_positions(vec![(ids[2], 0), (ids[0], 1), (ids[1], 2)])
+            .await
+            .unwrap();
+        assert_eq!(titles(&manager).await, ["Write", "Read", "Run"]);
+    }
+
+    #[tokio::test]
+    async fn archived_habits_keep_their_history() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_in(&dir);
+        let id = Uuid::new_v4();
+        let day = NaiveDate::from_ymd_opt(2024, 3, 4).unwrap();
+        manager.create_habit(id, new_habit("Run")).await.unwrap();
+        manager.mark_day(id, day).await.unwrap();
+
+        manager.set_archived(id, true).await.unwrap();
+        let habit = manager.get_habit(id).await.unwrap().unwrap();
+        assert!(habit.archived);
+        assert_eq!(habit.completed_days, vec![day]);
+
+        manager.set_archived(id, false).await.unwrap();
+        let habit = manager.get_habit(id).await.unwrap().unwrap();
+        assert!(!habit.archived);
+        assert_eq!(habit.completed_days, vec![day]);
+    }
+
+    #[tokio::test]
+    async fn categories_are_trimmed_and_listed_once() {
+        let dir = tempfile::tempdir().unwrap();
+        let manager = manager_in(&dir);
+        let ids: Vec<Uuid> = (0..3).map(|_| Uuid::new_v4()).collect();
+        for (id, category) in ids.iter().zip([" Health ", "Work", "Health"]) {
+            manager.create_habit(*id, new_habit("Habit")).await.unwrap();
+            manager
+                .set_category(*id, Some(category.to_string()))
+                .await
+                .unwrap();
+        }
+        assert_eq!(manager.get_categories().await.unwrap(), ["Health", "Work"]);
+
+        // A blank category takes the habit out of its group
+        manager
+            .set_category(ids[1], Some("  ".to_string()))
+            .await
+            .unwrap();
+        assert_eq!(
+            manager.get_habit(ids[1]).await.unwrap().unwrap().category,
+            None
+        );
+        assert_eq!(manager.get_categories().await.unwrap(), ["Health"]);
+    }
+}
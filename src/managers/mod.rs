@@ -1,3 +1,184 @@
 pub mod habit_manager;
+pub mod routine_manager;
 pub mod timeline_manager;
 pub mod todo_manager;
+
+use crate::storage::{
+    block_on_writers, BackupInfo, Conflict, ConflictResolution, Corruption, DiffEntry, StorageEvent,
+};
+use habit_manager::get_habit_manager;
+use routine_manager::get_routine_manager;
+use std::path::PathBuf;
+use std::time::Duration;
+use timeline_manager::get_timeline_manager;
+use todo_manager::get_todo_manager;
+use tokio::sync::broadcast::{self, error::RecvError};
+use tracing::{error, warn};
+
+// The app's data files, for screens that operate on all of them at once
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DataStore {
+    Habits,
+    Todos,
+    Routines,
+    Timeline,
+}
+
+impl DataStore {
+    pub const ALL: [DataStore; 4] = [
+        DataStore::Habits,
+        DataStore::Todos,
+        DataStore::Routines,
+        DataStore::Timeline,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            DataStore::Habits => "Habits",
+            DataStore::Todos => "Todos",
+            DataStore::Routines => "Routines",
+            DataStore::Timeline => "Timeline",
+        }
+    }
+
+    pub async fn flush(&self) -> Result<(), String> {
+        match self {
+            DataStore::Habits => get_habit_manager().flush().await,
+            DataStore::Todos => get_todo_manager().flush().await,
+            DataStore::Routines => get_routine_manager().flush().await,
+            DataStore::Timeline => get_timeline_manager().flush().await,
+        }
+    }
+
+    pub async fn corruption(&self) -> Option<Corruption> {
+        match self {
+            DataStore::Habits => get_habit_manager().corruption().await,
+            DataStore::Todos => get_todo_manager().corruption().await,
+            DataStore::Routines => get_routine_manager().corruption().await,
+            DataStore::Timeline => get_timeline_manager().corruption().await,
+        }
+    }
+
+    // Set when a newer build wrote the store, which is then opened read-only
+    pub async fn newer_version(&self) -> Option<u32> {
+        match self {
+            DataStore::Habits => get_habit_manager().newer_version(),
+            DataStore::Todos => get_todo_manager().newer_version(),
+            DataStore::Routines => get_routine_manager().newer_version(),
+            DataStore::Timeline => get_timeline_manager().newer_version().await,
+        }
+    }
+
+    pub async fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        match self {
+            DataStore::Habits => get_habit_manager().subscribe(),
+            DataStore::Todos => get_todo_manager().subscribe(),
+            DataStore::Routines => get_routine_manager().subscribe(),
+            DataStore::Timeline => get_timeline_manager().subscribe().await,
+        }
+    }
+
+    // Waits for the next outside change. The receiver is replaced when its storage
+    // goes away, which happens when another timeline is opened.
+    pub async fn next_event(
+        &self,
+        receiver: &mut broadcast::Receiver<StorageEvent>,
+    ) -> StorageEvent {
+        loop {
+            match receiver.recv().await {
+                Ok(event) => return event,
+                // Reloading covers whatever was missed
+                Err(RecvError::Lagged(_)) => return StorageEvent::Reloaded,
+                Err(RecvError::Closed) => *receiver = self.subscribe().await,
+            }
+        }
+    }
+
+    pub async fn conflict(&self) -> Option<Conflict> {
+        match self {
+            DataStore::Habits => get_habit_manager().conflict().await,
+            DataStore::Todos => get_todo_manager().conflict().await,
+            DataStore::Routines => get_routine_manager().conflict().await,
+            DataStore::Timeline => get_timeline_manager().conflict().await,
+        }
+    }
+
+    pub async fn resolve_conflict(&self, resolution: ConflictResolution) -> Result<(), String> {
+        match self {
+            DataStore::Habits => get_habit_manager().resolve_conflict(resolution).await,
+            DataStore::Todos => get_todo_manager().resolve_conflict(resolution).await,
+            DataStore::Routines => get_routine_manager().resolve_conflict(resolution).await,
+            DataStore::Timeline => get_timeline_manager().resolve_conflict(resolution).await,
+        }
+    }
+
+    pub async fn restore_from_backup(&self) -> Result<PathBuf, String> {
+        match self {
+            DataStore::Habits => get_habit_manager().restore_from_backup().await,
+            DataStore::Todos => get_todo_manager().restore_from_backup().await,
+            DataStore::Routines => get_routine_manager().restore_from_backup().await,
+            DataStore::Timeline => get_timeline_manager().restore_from_backup().await,
+        }
+    }
+
+    pub async fn start_fresh(&self) -> Result<(), String> {
+        match self {
+            DataStore::Habits => get_habit_manager().start_fresh().await,
+            DataStore::Todos => get_todo_manager().start_fresh().await,
+            DataStore::Routines => get_routine_manager().start_fresh().await,
+            DataStore::Timeline => get_timeline_manager().start_fresh().await,
+        }
+    }
+
+    pub async fn list_backups(&self) -> Result<Vec<BackupInfo>, String> {
+        match self {
+            DataStore::Habits => get_habit_manager().list_backups().await,
+            DataStore::Todos => get_todo_manager().list_backups().await,
+            DataStore::Routines => get_routine_manager().list_backups().await,
+            DataStore::Timeline => get_timeline_manager().list_backups().await,
+        }
+    }
+
+    pub async fn diff_backup(&self, index: usize) -> Result<Vec<DiffEntry>, String> {
+        match self {
+            DataStore::Habits => get_habit_manager().diff_backup(index).await,
+            DataStore::Todos => get_todo_manager().diff_backup(index).await,
+            DataStore::Routines => get_routine_manager().diff_backup(index).await,
+            DataStore::Timeline => get_timeline_manager().diff_backup(index).await,
+        }
+    }
+
+    pub async fn restore_backup(&self, index: usize) -> Result<PathBuf, String> {
+        match self {
+            DataStore::Habits => get_habit_manager().restore_backup(index).await,
+            DataStore::Todos => get_todo_manager().restore_backup(index).await,
+            DataStore::Routines => get_routine_manager().restore_backup(index).await,
+            DataStore::Timeline => get_timeline_manager().restore_backup(index).await,
+        }
+    }
+}
+
+// Waits for every manager's pending writes to reach disk
+pub async fn flush_all() {
+    for store in DataStore::ALL {
+        if let Err(e) = store.flush().await {
+            error!("Failed to flush {} storage: {}", store.label(), e);
+        }
+    }
+}
+
+// How long shutdown waits for pending saves before giving up on them
+const SHUTDOWN_FLUSH_TIMEOUT: Duration = Duration::from_secs(5);
+
+// `flush_all` for synchronous code such as drop handlers. The writers run on a
+// runtime of their own, so this works even though the app's runtime is blocked.
+pub fn flush_all_blocking() {
+    match block_on_writers(tokio::time::timeout(SHUTDOWN_FLUSH_TIMEOUT, flush_all())) {
+        Ok(Ok(())) => {}
+        Ok(Err(_)) => warn!(
+            "Pending saves may be lost, flushing on shutdown timed out after {:?}",
+            SHUTDOWN_FLUSH_TIMEOUT
+        ),
+        Err(e) => error!("Failed to flush on shutdown: {}", e),
+    }
+}
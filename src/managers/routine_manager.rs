@@ -0,0 +1,400 @@
+// managers/routine_manager.rs
+use crate::models::routine::{RoutineData, TimeBlock};
+use crate::models::timeline::Routine as LegacyRoutine;
+use crate::storage::{
+    get_path_manager, BackupInfo, Conflict, ConflictResolution, Corruption, DiffEntry, JsonStorage,
+    Mergeable, Merged, Merger, Migration, StorageEvent, Versioned,
+};
+use chrono::Weekday;
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::PathBuf;
+use tokio::sync::broadcast;
+use tracing::debug;
+use uuid::Uuid;
+
+#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+pub struct RoutinesStore {
+    routines: HashMap<Uuid, RoutineData>,
+}
+
+impl Versioned for RoutinesStore {
+    const SCHEMA_VERSION: u32 = 1;
+    const SCHEMA_MIGRATIONS: &'static [Migration] = &[];
+}
+
+impl Mergeable for RoutinesStore {
+    fn merge(base: &Self, mine: &Self, theirs: &Self) -> Merged<Self> {
+        let mut merger = Merger::default();
+        let routines = merger
+            .entities(
+                "routines",
+                &base.routines,
+                &mine.routines,
+                &theirs.routines,
+                |_, routine| routine.name.clone(),
+                |merger, path, base, mine, theirs| RoutineData {
+                    name: merger.field(path, "name", base, mine, theirs, |r| &r.name),
+                    color: merger.field(path, "color", base, mine, theirs, |r| &r.color),
+                    // A day's blocks only make sense together
+                    blocks: merger.field(path, "blocks", base, mine, theirs, |r| &r.blocks),
+                },
+            )
+            .into_iter()
+            .map(|(id, routine)| (*id, routine))
+            .collect();
+        merger.finish(Self { routines })
+    }
+}
+
+// Color for routines that didn't come with one
+pub const DEFAULT_ROUTINE_COLOR: &str = "#6495ED";
+
+impl RoutinesStore {
+    // Timelines used to list routine names. Adds the ones not known yet, by id or
+    // by name, as routines without blocks. Returns how many were added.
+    fn adopt(&mut self, legacy: &[LegacyRoutine]) -> usize {
+        let mut added = 0;
+        for routine in legacy {
+            let known = routine.id.is_some_and(|id| self.routines.contains_key(&id))
+                || self.routines.values().any(|r| r.name == routine.name);
+            if known {
+                continue;
+            }
+            self.routines.insert(
+                routine.id.unwrap_or_else(Uuid::new_v4),
+                RoutineData {
+                    name: routine.name.clone(),
+                    color: DEFAULT_ROUTINE_COLOR.to_string(),
+                    blocks: Vec::new(),
+                },
+            );
+            added += 1;
+        }
+        added
+    }
+
+    // Blocks that run on `weekday`, led by the rest of the ones from the day before
+    // that ran past midnight
+    fn day_schedule(&self, weekday: Weekday) -> Vec<ScheduledBlock> {
+        let scheduled = |continued: bool, day: Weekday| {
+            self.routines.iter().flat_map(move |(id, routine)| {
+                routine
+                    .blocks_for_day(day)
+                    .into_iter()
+                    .filter(move |block| !continued || block.end_minute() > MINUTES_PER_DAY)
+                    .map(move |block| ScheduledBlock {
+                        routine_id: *id,
+                        routine_name: routine.name.clone(),
+                        block,
+                        continued,
+                    })
+            })
+        };
+        let mut carried: Vec<ScheduledBlock> = scheduled(true, weekday.pred()).collect();
+        carried.sort_by_key(|s| s.block.start);
+        let mut schedule: Vec<ScheduledBlock> = scheduled(false, weekday).collect();
+        schedule.sort_by_key(|s| s.block.start);
+        carried.extend(schedule);
+        carried
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct ScheduledBlock {
+    pub routine_id: Uuid,
+    pub routine_name: String,
+    pub block: TimeBlock,
+    // The block started the day before and this is the part after midnight
+    pub continued: bool,
+}
+
+pub const MINUTES_PER_DAY: u32 = 24 * 60;
+
+pub struct RoutineManager {
+    storage: JsonStorage<RoutinesStore>,
+}
+
+impl RoutineManager {
+    pub fn new() -> Result<Self, String> {
+        Ok(Self {
+            storage: JsonStorage::new(get_path_manager().routines_file())
+                .map_err(|e| e.to_string())?,
+        })
+    }
+
+    pub async fn get_all_routines(&self) -> Result<Vec<(Uuid, RoutineData)>, String> {
+        debug!("Getting all routines");
+        self.storage
+            .read(|store| {
+                let mut routines: Vec<_> = store
+                    .routines
+                    .iter()
+                    .map(|(k, v)| (*k, v.clone()))
+                    .collect();
+                routines.sort_by(|a, b| a.1.name.cmp(&b.1.name));
+                routines
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn get_routine(&self, id: Uuid) -> Result<Option<RoutineData>, String> {
+        debug!("Getting routine with id: {}", id);
+        self.storage
+            .read(|store| store.routines.get(&id).cloned())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn create_routine(&self, id: Uuid, mut data: RoutineData) -> Result<(), String> {
+        debug!("Creating routine with id: {}", id);
+        data.sort_blocks();
+        self.storage
+            .write(|store| {
+                store.routines.insert(id, data);
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn update_routine(&self, id: Uuid, mut data: RoutineData) -> Result<(), String> {
+        debug!("Updating routine with id: {}", id);
+        data.sort_blocks();
+        self.storage
+            .write(|store| {
+                if store.routines.contains_key(&id) {
+                    store.routines.insert(id, data);
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn delete_routine(&self, id: Uuid) -> Result<(), String> {
+        debug!("Deleting routine with id: {}", id);
+        self.storage
+            .write(|store| {
+                store.routines.remove(&id);
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    // Routine names taken out of an old timeline file
+    pub async fn adopt_legacy_routines(&self, legacy: &[LegacyRoutine]) -> Result<(), String> {
+        let added = self
+            .storage
+            .write(|store| store.adopt(legacy))
+            .await
+            .map_err(|e| e.to_string())?;
+        debug!("Moved {} routines out of the timeline", added);
+        Ok(())
+    }
+
+    pub async fn add_block(&self, routine_id: Uuid, block: TimeBlock) -> Result<(), String> {
+        debug!("Adding block {} to routine {}", block.id, routine_id);
+        self.storage
+            .write(|store| {
+                if let Some(routine) = store.routines.get_mut(&routine_id) {
+                    routine.blocks.push(block);
+                    routine.sort_blocks();
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn update_block(&self, routine_id: Uuid, block: TimeBlock) -> Result<(), String> {
+        debug!("Updating block {} in routine {}", block.id, routine_id);
+        self.storage
+            .write(|store| {
+                if let Some(routine) = store.routines.get_mut(&routine_id) {
+                    if let Some(existing) = routine.blocks.iter_mut().find(|b| b.id == block.id) {
+                        *existing = block;
+                        routine.sort_blocks();
+                    }
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn delete_block(&self, routine_id: Uuid, block_id: Uuid) -> Result<(), String> {
+        debug!("Deleting block {} from routine {}", block_id, routine_id);
+        self.storage
+            .write(|store| {
+                if let Some(routine) = store.routines.get_mut(&routine_id) {
+                    routine.blocks.retain(|b| b.id != block_id);
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    // All blocks from every routine that run on the given weekday, ordered by start time
+    pub async fn get_day_schedule(&self, weekday: Weekday) -> Result<Vec<ScheduledBlock>, String> {
+        debug!("Getting schedule for {:?}", weekday);
+        self.storage
+            .read(|store| store.day_schedule(weekday))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn force_save(&self) -> Result<(), String> {
+        self.storage.force_save().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn flush(&self) -> Result<(), String> {
+        self.storage.flush().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn corruption(&self) -> Option<Corruption> {
+        self.storage.corruption().await
+    }
+
+    pub fn newer_version(&self) -> Option<u32> {
+        self.storage.newer_version()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        self.storage.subscribe()
+    }
+
+    pub async fn conflict(&self) -> Option<Conflict> {
+        self.storage.conflict().await
+    }
+
+    pub async fn resolve_conflict(&self, resolution: ConflictResolution) -> Result<(), String> {
+        self.storage
+            .resolve_conflict(resolution)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn restore_from_backup(&self) -> Result<PathBuf, String> {
+        self.storage
+            .restore_from_backup()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn start_fresh(&self) -> Result<(), String> {
+        self.storage.start_fresh().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn list_backups(&self) -> Result<Vec<BackupInfo>, String> {
+        self.storage.list_backups().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn diff_backup(&self, index: usize) -> Result<Vec<DiffEntry>, String> {
+        self.storage
+            .diff_backup(index)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn restore_backup(&self, index: usize) -> Result<PathBuf, String> {
+        self.storage
+            .restore_backup(index)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn reload(&self) -> Result<(), String> {
+        self.storage.reload().await.map_err(|e| e.to_string())
+    }
+}
+
+static ROUTINE_MANAGER: Lazy<RoutineManager> =
+    Lazy::new(|| RoutineManager::new().expect("Failed to create routine manager"));
+
+pub fn get_routine_manager() -> &'static RoutineManager {
+    &*ROUTINE_MANAGER
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::routine::weekday_bit;
+    use crate::storage::{decode, JsonFormat};
+    use chrono::NaiveTime;
+
+    #[test]
+    fn routines_from_old_timelines_are_adopted_once() {
+        let mut store = RoutinesStore::default();
+        let id = Uuid::new_v4();
+        let legacy = vec![
+            LegacyRoutine {
+                name: "Morning".to_string(),
+                id: Some(id),
+            },
+            LegacyRoutine {
+                name: "Evening".to_string(),
+                id: None,
+            },
+        ];
+        assert_eq!(store.adopt(&legacy), 2);
+        assert_eq!(store.routines[&id].name, "Morning");
+        assert_eq!(store.adopt(&legacy), 0);
+        assert_eq!(store.routines.len(), 2);
+    }
+
+    #[test]
+    fn blocks_past_midnight_continue_the_next_day() {
+        let mut routine = RoutineData {
+            name: "Evening".to_string(),
+            color: DEFAULT_ROUTINE_COLOR.to_string(),
+            blocks: Vec::new(),
+        };
+        let at = |hour| NaiveTime::from_hms_opt(hour, 0, 0).unwrap();
+        let mut late = TimeBlock::new("Shift".to_string(), at(22), 180, "#000000".to_string());
+        late.weekdays = weekday_bit(Weekday::Mon);
+        routine.blocks.push(late.clone());
+        routine.blocks.push(TimeBlock::new(
+            "Read".to_string(),
+            at(21),
+            60,
+            "#000000".to_string(),
+        ));
+        // Longer than a day: still only carried into the one day after
+        let mut trip = TimeBlock::new("Trip".to_string(), at(20), 3000, "#000000".to_string());
+        trip.weekdays = weekday_bit(Weekday::Fri);
+        routine.blocks.push(trip);
+        let mut store = RoutinesStore::default();
+        store.routines.insert(Uuid::new_v4(), routine);
+
+        let titles = |weekday| {
+            store
+                .day_schedule(weekday)
+                .into_iter()
+                .map(|s| (s.block.title, s.continued))
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            titles(Weekday::Mon),
+            vec![("Read".to_string(), false), ("Shift".to_string(), false)]
+        );
+        assert_eq!(
+            titles(Weekday::Tue),
+            vec![("Shift".to_string(), true), ("Read".to_string(), false)]
+        );
+        assert_eq!(titles(Weekday::Wed), vec![("Read".to_string(), false)]);
+        assert_eq!(
+            titles(Weekday::Sat),
+            vec![("Trip".to_string(), true), ("Read".to_string(), false)]
+        );
+        assert_eq!(titles(Weekday::Sun), vec![("Read".to_string(), false)]);
+    }
+
+    #[test]
+    fn routines_from_version_1_load() {
+        let store: RoutinesStore =
+            decode::<_, JsonFormat>(include_str!("../../tests/fixtures/routines/v1.json")).unwrap();
+        let routine = store.routines.values().next().unwrap();
+        assert_eq!(routine.name, "Morning");
+        assert_eq!(routine.blocks[0].duration_minutes, 45);
+        assert!(!routine.blocks[0].is_active_on(Weekday::Sat));
+    }
+}
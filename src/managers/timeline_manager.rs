@@ -1,12 +1,21 @@
+use crate::managers::routine_manager::get_routine_manager;
 use crate::models::timeline::{LifePeriod, LifePeriodEvent, Yaml};
-use crate::storage::{get_path_manager, StorageConfig, StorageError, YamlStorage};
+use crate::storage::{
+    decode, from_json_column, get_path_manager, get_storage_backend, is_sealed, load_database,
+    parse_column, read_document, to_json_column, BackupInfo, Conflict, ConflictResolution,
+    Corruption, DiffEntry, Entity, Mergeable, Merged, Merger, Migration, SqliteModel, Storage,
+    StorageBackend, StorageConfig, StorageError, StorageEvent, Versioned, YamlFormat,
+};
+use crate::utils::validation::{parse_diagnostic, validate, Diagnostic, Severity};
 use once_cell::sync::Lazy;
 #[cfg(not(target_os = "android"))]
 use rfd::FileDialog;
+use rusqlite::{params, Connection, Transaction};
+use std::collections::BTreeMap;
+use std::path::PathBuf;
 use std::sync::Arc;
-use std::time::SystemTime;
-use tokio::sync::RwLock;
-use tracing::{debug, error};
+use tokio::sync::{broadcast, RwLock};
+use tracing::{debug, error, warn};
 use uuid::Uuid;
 
 const DEFAULT_TIMELINE: &str = r#"
@@ -34,7 +43,6 @@ life_periods:
   start: 2024-01
   color: '#FF9E00'
   events: []
-routines: []
 "#;
 
 impl From<StorageError> for String {
@@ -43,11 +51,309 @@ impl From<StorageError> for String {
     }
 }
 
+impl Versioned for Yaml {
+    const SCHEMA_VERSION: u32 = 1;
+    const SCHEMA_MIGRATIONS: &'static [Migration] = &[];
+}
+
+impl Entity for LifePeriod {
+    type Key = Uuid;
+
+    fn key(&self) -> Option<Uuid> {
+        self.id
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+impl Entity for LifePeriodEvent {
+    type Key = Uuid;
+
+    fn key(&self) -> Option<Uuid> {
+        self.id
+    }
+
+    fn name(&self) -> String {
+        self.name.clone()
+    }
+}
+
+fn merge_event(
+    merger: &mut Merger,
+    path: &str,
+    base: &LifePeriodEvent,
+    mine: &LifePeriodEvent,
+    theirs: &LifePeriodEvent,
+) -> LifePeriodEvent {
+    LifePeriodEvent {
+        name: merger.field(path, "name", base, mine, theirs, |e| &e.name),
+        color: merger.field(path, "color", base, mine, theirs, |e| &e.color),
+        start: merger.field(path, "start", base, mine, theirs, |e| &e.start),
+        id: mine.id,
+    }
+}
+
+fn merge_period(
+    merger: &mut Merger,
+    path: &str,
+    base: &LifePeriod,
+    mine: &LifePeriod,
+    theirs: &LifePeriod,
+) -> LifePeriod {
+    LifePeriod {
+        name: merger.field(path, "name", base, mine, theirs, |p| &p.name),
+        start: merger.field(path, "start", base, mine, theirs, |p| &p.start),
+        end: merger.field(path, "end", base, mine, theirs, |p| &p.end),
+        track: merger.field(path, "track", base, mine, theirs, |p| &p.track),
+        color: merger.field(path, "color", base, mine, theirs, |p| &p.color),
+        events: merger.list(
+            &format!("{}.events", path),
+            &base.events,
+            &mine.events,
+            &theirs.events,
+            merge_event,
+        ),
+        id: mine.id,
+    }
+}
+
+// Periods and events are matched up by the ids `assign_ids` gives them
+impl Mergeable for Yaml {
+    fn merge(base: &Self, mine: &Self, theirs: &Self) -> Merged<Self> {
+        let mut merger = Merger::default();
+        let data = Yaml {
+            name: merger.field("", "name", base, mine, theirs, |y| &y.name),
+            date_of_birth: merger.field("", "date_of_birth", base, mine, theirs, |y| {
+                &y.date_of_birth
+            }),
+            life_expectancy: merger.field("", "life_expectancy", base, mine, theirs, |y| {
+                &y.life_expectancy
+            }),
+            life_periods: merger.list(
+                "life_periods",
+                &base.life_periods,
+                &mine.life_periods,
+                &theirs.life_periods,
+                merge_period,
+            ),
+            routines: merger.field("", "routines", base, mine, theirs, |y| &y.routines),
+        };
+        merger.finish(data)
+    }
+}
+
+// Periods and events are keyed by their position, since ids are optional in
+// hand-written files. Inserting a period early rewrites the ones after it, which
+// is cheap at the size of a life timeline.
+impl SqliteModel for Yaml {
+    const MIGRATIONS: &'static [&'static str] = &[
+        "
+        CREATE TABLE timeline (
+            id INTEGER PRIMARY KEY CHECK (id = 1),
+            name TEXT NOT NULL,
+            date_of_birth TEXT NOT NULL,
+            life_expectancy INTEGER NOT NULL,
+            routines TEXT
+        );
+        CREATE TABLE life_periods (
+            position INTEGER PRIMARY KEY,
+            id TEXT,
+            name TEXT NOT NULL,
+            start TEXT NOT NULL,
+            color TEXT NOT NULL
+        );
+        CREATE TABLE life_period_events (
+            period_position INTEGER NOT NULL
+                REFERENCES life_periods(position) ON DELETE CASCADE,
+            position INTEGER NOT NULL,
+            id TEXT,
+            name TEXT NOT NULL,
+            color TEXT NOT NULL,
+            start TEXT NOT NULL,
+            PRIMARY KEY (period_position, position)
+        );
+    ",
+        "
+        ALTER TABLE life_periods ADD COLUMN end_month TEXT;
+        ALTER TABLE life_periods ADD COLUMN track TEXT;
+    ",
+    ];
+
+    fn load(conn: &Connection) -> rusqlite::Result<Self> {
+        let mut yaml = Yaml::default();
+        let header = conn.query_row(
+            "SELECT name, date_of_birth, life_expectancy, routines FROM timeline WHERE id = 1",
+            [],
+            |row| {
+                Ok((
+                    row.get(0)?,
+                    row.get(1)?,
+                    row.get(2)?,
+                    row.get::<_, Option<String>>(3)?,
+                ))
+            },
+        );
+        match header {
+            Ok((name, date_of_birth, life_expectancy, routines)) => {
+                yaml.name = name;
+                yaml.date_of_birth = date_of_birth;
+                yaml.life_expectancy = life_expectancy;
+                yaml.routines = routines
+                    .map(|routines| from_json_column(3, routines))
+                    .transpose()?;
+            }
+            Err(rusqlite::Error::QueryReturnedNoRows) => {}
+            Err(e) => return Err(e),
+        }
+
+        let mut periods = BTreeMap::new();
+        let mut statement = conn.prepare(
+            "SELECT position, id, name, start, color, end_month, track FROM life_periods
+             ORDER BY position",
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let position: i64 = row.get(0)?;
+            let period = LifePeriod {
+                id: row
+                    .get::<_, Option<String>>(1)?
+                    .map(|id| parse_column(1, id))
+                    .transpose()?,
+                name: row.get(2)?,
+                start: row.get(3)?,
+                color: row.get(4)?,
+                end: row.get(5)?,
+                track: row.get(6)?,
+                events: Vec::new(),
+            };
+            periods.insert(position, period);
+        }
+
+        let mut statement = conn.prepare(
+            "SELECT period_position, id, name, color, start FROM life_period_events
+             ORDER BY period_position, position",
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let position: i64 = row.get(0)?;
+            if let Some(period) = periods.get_mut(&position) {
+                period.events.push(LifePeriodEvent {
+                    id: row
+                        .get::<_, Option<String>>(1)?
+                        .map(|id| parse_column(1, id))
+                        .transpose()?,
+                    name: row.get(2)?,
+                    color: row.get(3)?,
+                    start: row.get(4)?,
+                });
+            }
+        }
+
+        yaml.life_periods = periods.into_values().collect();
+        Ok(yaml)
+    }
+
+    fn persist(tx: &Transaction, before: &Self, after: &Self) -> rusqlite::Result<()> {
+        if before.name != after.name
+            || before.date_of_birth != after.date_of_birth
+            || before.life_expectancy != after.life_expectancy
+            || before.routines != after.routines
+        {
+            tx.execute(
+                "INSERT OR REPLACE INTO timeline (id, name, date_of_birth, life_expectancy, routines)
+                 VALUES (1, ?1, ?2, ?3, ?4)",
+                params![
+                    after.name,
+                    after.date_of_birth,
+                    after.life_expectancy,
+                    after.routines.as_ref().map(to_json_column).transpose()?,
+                ],
+            )?;
+        }
+
+        let period_count = before.life_periods.len().max(after.life_periods.len());
+        for position in 0..period_count {
+            let old = before.life_periods.get(position);
+            let Some(period) = after.life_periods.get(position) else {
+                tx.execute(
+                    "DELETE FROM life_periods WHERE position = ?1",
+                    [position as i64],
+                )?;
+                continue;
+            };
+            if old == Some(period) {
+                continue;
+            }
+
+            let row_changed = old.map_or(true, |old| {
+                old.id != period.id
+                    || old.name != period.name
+                    || old.start != period.start
+                    || old.color != period.color
+                    || old.end != period.end
+                    || old.track != period.track
+            });
+            if row_changed {
+                tx.execute(
+                    "INSERT INTO life_periods (position, id, name, start, color, end_month, track)
+                     VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)
+                     ON CONFLICT(position) DO UPDATE SET
+                         id = excluded.id,
+                         name = excluded.name,
+                         start = excluded.start,
+                         color = excluded.color,
+                         end_month = excluded.end_month,
+                         track = excluded.track",
+                    params![
+                        position as i64,
+                        period.id.map(|id| id.to_string()),
+                        period.name,
+                        period.start,
+                        period.color,
+                        period.end,
+                        period.track,
+                    ],
+                )?;
+            }
+
+            let old_events = old.map(|old| old.events.as_slice()).unwrap_or_default();
+            for index in 0..old_events.len().max(period.events.len()) {
+                match (old_events.get(index), period.events.get(index)) {
+                    (old_event, Some(event)) if old_event != Some(event) => {
+                        tx.execute(
+                            "INSERT OR REPLACE INTO life_period_events
+                                 (period_position, position, id, name, color, start)
+                             VALUES (?1, ?2, ?3, ?4, ?5, ?6)",
+                            params![
+                                position as i64,
+                                index as i64,
+                                event.id.map(|id| id.to_string()),
+                                event.name,
+                                event.color,
+                                event.start,
+                            ],
+                        )?;
+                    }
+                    (Some(_), None) => {
+                        tx.execute(
+                            "DELETE FROM life_period_events
+                             WHERE period_position = ?1 AND position = ?2",
+                            [position as i64, index as i64],
+                        )?;
+                    }
+                    _ => {}
+                }
+            }
+        }
+        Ok(())
+    }
+}
+
 pub struct TimelineManager {
     current_name: Arc<RwLock<String>>,
-    storage: Arc<RwLock<YamlStorage<Yaml>>>,
-
-    last_modified: Arc<RwLock<SystemTime>>,
+    storage: Arc<RwLock<Storage<Yaml, YamlFormat>>>,
 }
 
 fn assign_ids(yaml: &mut Yaml) {
@@ -76,38 +382,34 @@ impl TimelineManager {
 
         let current_name = "default".to_string();
         let path = get_path_manager().timeline_file(&current_name);
-
-        // Get the initial last modified time
-        let last_modified = path
-            .metadata()
-            .ok()
-            .and_then(|m| m.modified().ok())
-            .unwrap_or_else(SystemTime::now);
-
-        let storage = YamlStorage::with_config_and_default(path, config, Some(default_yaml))?;
+        let storage = Storage::with_config_and_default(path, config, Some(default_yaml))?;
 
         Ok(Self {
             current_name: Arc::new(RwLock::new(current_name)),
             storage: Arc::new(RwLock::new(storage)),
-            last_modified: Arc::new(RwLock::new(last_modified)),
         })
     }
 
     pub async fn get_available_timelines(&self) -> Vec<String> {
         #[cfg(not(target_arch = "wasm32"))]
         {
+            // With SQLite, timelines that were never opened are still plain YAML files
+            let sqlite = get_storage_backend() == StorageBackend::Sqlite;
             if let Ok(entries) = std::fs::read_dir(get_path_manager().timelines_dir()) {
-                entries
+                let mut names: Vec<String> = entries
                     .filter_map(|entry| {
                         let entry = entry.ok()?;
                         let path = entry.path();
-                        if path.extension()?.to_str()? == "yaml" {
-                            path.file_stem()?.to_str().map(String::from)
-                        } else {
-                            None
+                        match path.extension()?.to_str()? {
+                            "yaml" => path.file_stem()?.to_str().map(String::from),
+                            "db" if sqlite => path.file_stem()?.to_str().map(String::from),
+                            _ => None,
                         }
                     })
-                    .collect()
+                    .collect();
+                names.sort();
+                names.dedup();
+                names
             } else {
                 vec!["default".to_string()]
             }
@@ -120,6 +422,14 @@ impl TimelineManager {
     }
     pub async fn get_timeline_by_name(&self, name: &str) -> Result<Yaml, String> {
         let path = get_path_manager().timeline_file(name);
+        let db_path = StorageBackend::Sqlite.data_path(&path);
+        if get_storage_backend() == StorageBackend::Sqlite && db_path.exists() {
+            debug!("Loading timeline '{}' from: {:?}", name, db_path);
+            return load_database::<Yaml>(&db_path).map_err(|e| {
+                error!("Failed to load timeline database '{}': {}", name, e);
+                format!("Failed to load timeline '{}': {}", name, e)
+            });
+        }
         debug!("Loading timeline '{}' from: {:?}", name, path);
 
         if !path.exists() {
@@ -134,7 +444,7 @@ impl TimelineManager {
                     name,
                     content.len()
                 );
-                match serde_yaml::from_str::<Yaml>(&content) {
+                match read_document::<Yaml, YamlFormat>(&content) {
                     Ok(mut yaml) => {
                         debug!("Successfully parsed YAML for timeline '{}'", name);
                         // Assign IDs to periods that don't have them
@@ -180,16 +490,12 @@ impl TimelineManager {
             }
         };
 
-        // Update last modified time
-        if let Ok(metadata) = path.metadata() {
-            if let Ok(modified) = metadata.modified() {
-                let mut last_modified = self.last_modified.write().await;
-                *last_modified = modified;
-            }
+        for diagnostic in validate(&yaml) {
+            warn!("Timeline '{}': {}", name, diagnostic);
         }
 
         // Create new storage
-        let new_storage = YamlStorage::with_config_and_default(path, config, Some(yaml.clone()))?;
+        let new_storage = Storage::with_config_and_default(path, config, Some(yaml.clone()))?;
 
         // Update the manager state
         {
@@ -201,41 +507,32 @@ impl TimelineManager {
             *storage = new_storage;
         }
 
-        Ok(yaml)
+        self.get_timeline().await
     }
 
-    pub async fn check_for_file_changes(&self) -> Result<Option<Yaml>, String> {
-        let current_name = self.current_name.read().await;
-        let path = get_path_manager().timeline_file(&current_name);
-
-        let file_modified = path.metadata().ok().and_then(|m| m.modified().ok());
-
-        if let Some(file_time) = file_modified {
-            let last_check = *self.last_modified.read().await;
-
-            if file_time > last_check {
-                debug!("File change detected for timeline: {}", current_name);
-
-                // Load the new content
-                let new_yaml = self.get_timeline_by_name(&current_name).await?;
-
-                // Update the storage and last modified time
-                {
-                    let storage = self.storage.read().await;
-                    storage.write(|store| *store = new_yaml.clone()).await?;
-                }
-                {
-                    let mut last_modified = self.last_modified.write().await;
-                    *last_modified = file_time;
-                }
-
-                return Ok(Some(new_yaml));
-            }
+    // Hands routine names that old timelines listed to the routine manager, then
+    // saves so the timeline is rewritten without them
+    async fn adopt_legacy_routines(&self) {
+        let storage = self.storage.read().await;
+        let Ok(Some(legacy)) = storage.read(|yaml| yaml.routines.clone()).await else {
+            return;
+        };
+        debug!("Moving {} routines out of the timeline", legacy.len());
+        if let Err(e) = get_routine_manager().adopt_legacy_routines(&legacy).await {
+            error!("Failed to move routines out of the timeline: {}", e);
+            return;
+        }
+        if let Err(e) = storage.write(|yaml| yaml.routines = None).await {
+            error!("Failed to drop routines from the timeline: {}", e);
+            return;
+        }
+        if let Err(e) = storage.force_save().await {
+            error!("Failed to save timeline after moving its routines: {}", e);
         }
-
-        Ok(None)
     }
+
     pub async fn get_timeline(&self) -> Result<Yaml, String> {
+        self.adopt_legacy_routines().await;
         let storage = self.storage.read().await;
         storage.get_data().await.map_err(|e| e.to_string())
     }
@@ -383,6 +680,89 @@ impl TimelineManager {
         storage.force_save().await.map_err(|e| e.to_string())
     }
 
+    pub async fn flush(&self) -> Result<(), String> {
+        let storage = self.storage.read().await;
+        storage.flush().await.map_err(|e| e.to_string())
+    }
+
+    // Why the current timeline's file didn't load, if it was set aside. Problems in
+    // a timeline that did load come from `validate`.
+    pub async fn load_problems(&self) -> Vec<Diagnostic> {
+        let Some(corruption) = self.corruption().await else {
+            return Vec::new();
+        };
+        let content = std::fs::read_to_string(&corruption.quarantined_path).ok();
+        let parsed = content
+            .filter(|content| !is_sealed(content))
+            .and_then(|content| parse_diagnostic(&content));
+        vec![parsed.unwrap_or_else(|| Diagnostic {
+            severity: Severity::Error,
+            path: "timeline".to_string(),
+            message: corruption.reason,
+            period: None,
+        })]
+    }
+
+    pub async fn corruption(&self) -> Option<Corruption> {
+        let storage = self.storage.read().await;
+        storage.corruption().await
+    }
+
+    pub async fn newer_version(&self) -> Option<u32> {
+        let storage = self.storage.read().await;
+        storage.newer_version()
+    }
+
+    // Follows the open timeline only; switching timelines closes the receiver
+    pub async fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        let storage = self.storage.read().await;
+        storage.subscribe()
+    }
+
+    pub async fn conflict(&self) -> Option<Conflict> {
+        let storage = self.storage.read().await;
+        storage.conflict().await
+    }
+
+    pub async fn resolve_conflict(&self, resolution: ConflictResolution) -> Result<(), String> {
+        let storage = self.storage.read().await;
+        storage
+            .resolve_conflict(resolution)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn restore_from_backup(&self) -> Result<PathBuf, String> {
+        let storage = self.storage.read().await;
+        storage
+            .restore_from_backup()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn start_fresh(&self) -> Result<(), String> {
+        let storage = self.storage.read().await;
+        storage.start_fresh().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn list_backups(&self) -> Result<Vec<BackupInfo>, String> {
+        let storage = self.storage.read().await;
+        storage.list_backups().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn diff_backup(&self, index: usize) -> Result<Vec<DiffEntry>, String> {
+        let storage = self.storage.read().await;
+        storage.diff_backup(index).await.map_err(|e| e.to_string())
+    }
+
+    pub async fn restore_backup(&self, index: usize) -> Result<PathBuf, String> {
+        let storage = self.storage.read().await;
+        storage
+            .restore_backup(index)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
     pub async fn reload(&self) -> Result<(), String> {
         let storage = self.storage.read().await;
         storage.reload().await.map_err(|e| e.to_string())
@@ -394,7 +774,7 @@ impl TimelineManager {
             .pick_file()
         {
             let content = std::fs::read_to_string(&file_path).ok()?;
-            let yaml: Yaml = serde_yaml::from_str(&content).ok()?;
+            let yaml: Yaml = decode::<_, YamlFormat>(&content).ok()?;
             let name = file_path.file_stem()?.to_str()?.to_string();
             Some((name, yaml))
         } else {
@@ -415,6 +795,8 @@ impl TimelineManager {
             .add_filter("YAML", &["yaml", "yml"])
             .save_file()
         {
+            // Exports are for reading and sharing, so they leave out the version
+            // envelope; imports take unversioned files as they are
             let content = serde_yaml::to_string(yaml).map_err(|e| e.to_string())?;
             std::fs::write(file_path, content).map_err(|e| e.to_string())
         } else {
@@ -435,3 +817,109 @@ static TIMELINE_MANAGER: Lazy<TimelineManager> =
 pub fn get_timeline_manager() -> &'static TimelineManager {
     &*TIMELINE_MANAGER
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::timeline::Routine;
+    use crate::storage::encode;
+
+    fn save(conn: &mut Connection, before: &Yaml, after: &Yaml) -> Yaml {
+        let tx = conn.transaction().unwrap();
+        Yaml::persist(&tx, before, after).unwrap();
+        tx.commit().unwrap();
+        Yaml::load(conn).unwrap()
+    }
+
+    #[test]
+    fn timeline_round_trips_through_sqlite_as_periods_shift() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "foreign_keys", true).unwrap();
+        for step in Yaml::MIGRATIONS {
+            conn.execute_batch(step).unwrap();
+        }
+
+        let mut yaml: Yaml = serde_yaml::from_str(DEFAULT_TIMELINE).unwrap();
+        yaml.life_periods[1].events = vec![
+            LifePeriodEvent {
+                name: "Exam".to_string(),
+                color: "#ffffff".to_string(),
+                start: "2015-06-01".to_string(),
+                id: None,
+            },
+            LifePeriodEvent {
+                name: "Trip".to_string(),
+                color: "#000000".to_string(),
+                start: "2016-07-01".to_string(),
+                id: Some(Uuid::new_v4()),
+            },
+        ];
+        let loaded = save(&mut conn, &Yaml::default(), &yaml);
+        assert_eq!(loaded.life_periods, yaml.life_periods);
+        assert_eq!(loaded.name, yaml.name);
+
+        let mut updated = yaml.clone();
+        updated.life_periods.insert(
+            0,
+            LifePeriod {
+                name: "Baby".to_string(),
+                start: "1999-01".to_string(),
+                end: Some("2001-06".to_string()),
+                track: Some("Home".to_string()),
+                color: "#123456".to_string(),
+                events: Vec::new(),
+                id: None,
+            },
+        );
+        updated.life_periods[2].events.remove(0);
+        updated.life_periods.pop();
+        updated.life_periods[3].end = Some("2023-12".to_string());
+
+        let loaded = save(&mut conn, &yaml, &updated);
+        assert_eq!(loaded.life_periods, updated.life_periods);
+    }
+
+    #[test]
+    fn routine_names_stay_in_sqlite_until_moved_out() {
+        let mut conn = Connection::open_in_memory().unwrap();
+        conn.pragma_update(None, "foreign_keys", true).unwrap();
+        for step in Yaml::MIGRATIONS {
+            conn.execute_batch(step).unwrap();
+        }
+
+        let mut yaml: Yaml = serde_yaml::from_str(DEFAULT_TIMELINE).unwrap();
+        yaml.routines = Some(vec![Routine {
+            name: "Morning".to_string(),
+            id: Some(Uuid::new_v4()),
+        }]);
+        let loaded = save(&mut conn, &Yaml::default(), &yaml);
+        assert_eq!(loaded.routines, yaml.routines);
+
+        let mut moved = loaded.clone();
+        moved.routines = None;
+        assert_eq!(save(&mut conn, &loaded, &moved).routines, None);
+        let column: Option<String> = conn
+            .query_row("SELECT routines FROM timeline", [], |row| row.get(0))
+            .unwrap();
+        assert_eq!(column, None);
+    }
+
+    #[test]
+    fn timeline_from_version_1_loads() {
+        let yaml: Yaml =
+            decode::<_, YamlFormat>(include_str!("../../tests/fixtures/timeline/v1.yaml")).unwrap();
+        assert_eq!(yaml.name, "Jane Doe");
+        assert_eq!(yaml.life_expectancy, 85);
+        assert_eq!(yaml.life_periods[0].events[0].name, "First bike");
+        assert_eq!(yaml.life_periods[1].name, "2008");
+        assert!(yaml.life_periods[1].events.is_empty());
+        // Routine names are only data here; the manager moves them when it loads
+        assert_eq!(yaml.routines.as_ref().unwrap()[0].name, "Morning");
+
+        let content = encode::<_, YamlFormat>(&yaml).unwrap();
+        assert!(content.starts_with("version: 1\n"));
+        let reloaded: Yaml = decode::<_, YamlFormat>(&content).unwrap();
+        assert_eq!(reloaded.life_periods, yaml.life_periods);
+        assert_eq!(reloaded.routines, yaml.routines);
+    }
+}
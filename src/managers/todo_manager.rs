@@ -1,10 +1,20 @@
 // managers/todo_manager.rs
-use crate::models::todo::Todo;
-use crate::storage::{get_path_manager, JsonStorage};
+use crate::models::recurrence::RecurrenceRule;
+use crate::models::todo::{CarryOverPolicy, Todo};
+use crate::storage::{
+    from_json_column, get_path_manager, parse_column, row_changes, to_json_column, BackupInfo,
+    Conflict, ConflictResolution, Corruption, DiffEntry, Entity, JsonFormat, Mergeable, Merged,
+    Merger, Migration, SqliteModel, Storage, StorageEvent, Versioned,
+};
+use crate::utils::date_utils::{date_for_day_name, week_start_monday};
+use chrono::{Duration, Local, NaiveDate, NaiveDateTime};
 use once_cell::sync::Lazy;
+use rusqlite::{params, Connection, Transaction};
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
-use tracing::debug;
+use std::collections::{BTreeMap, BTreeSet, HashMap};
+use std::path::PathBuf;
+use tokio::sync::broadcast;
+use tracing::{debug, info};
 use uuid::Uuid;
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
@@ -14,83 +24,561 @@ pub struct DayTodos {
 
 #[derive(Debug, Clone, Serialize, Deserialize, Default)]
 pub struct TodoStore {
-    monday: DayTodos,
-    tuesday: DayTodos,
-    wednesday: DayTodos,
-    thursday: DayTodos,
-    friday: DayTodos,
-    saturday: DayTodos,
-    sunday: DayTodos,
+    #[serde(default)]
+    days: BTreeMap<NaiveDate, DayTodos>,
+    #[serde(default)]
+    recurring: Vec<Todo>,
+    #[serde(default)]
+    carry_over: CarryOverPolicy,
 }
 
 impl TodoStore {
-    fn get_day_mut(&mut self, day: &str) -> &mut DayTodos {
-        match day.to_lowercase().as_str() {
-            "monday" => &mut self.monday,
-            "tuesday" => &mut self.tuesday,
-            "wednesday" => &mut self.wednesday,
-            "thursday" => &mut self.thursday,
-            "friday" => &mut self.friday,
-            "saturday" => &mut self.saturday,
-            "sunday" => &mut self.sunday,
-            _ => panic!("Invalid day: {}", day),
+    fn get_day_mut(&mut self, date: NaiveDate) -> &mut DayTodos {
+        self.days.entry(date).or_default()
+    }
+
+    fn get_day(&self, date: NaiveDate) -> Option<&DayTodos> {
+        self.days.get(&date)
+    }
+
+    // Remove a one-off todo wherever it is stored, renumbering the day it came from
+    fn take_todo(&mut self, id: Uuid) -> Option<Todo> {
+        for day_todos in self.days.values_mut() {
+            if let Some(pos) = day_todos.todos.iter().position(|t| t.id == id) {
+                let todo = day_todos.todos.remove(pos);
+                for (i, todo) in day_todos.todos.iter_mut().enumerate() {
+                    todo.position = (i + 1) as i32;
+                }
+                return Some(todo);
+            }
+        }
+        None
+    }
+
+    // Move unfinished todos from days before `today` according to the carry-over policy
+    fn carry_over_unfinished(&mut self, today: NaiveDate) -> usize {
+        if self.carry_over == CarryOverPolicy::Leave {
+            return 0;
+        }
+
+        let past_dates: Vec<NaiveDate> = self.days.range(..today).map(|(d, _)| *d).collect();
+        let mut carried = 0;
+        for date in past_dates {
+            let Some(day_todos) = self.days.get_mut(&date) else {
+                continue;
+            };
+            let (unfinished, finished): (Vec<Todo>, Vec<Todo>) =
+                day_todos.todos.drain(..).partition(|t| !t.is_completed());
+            day_todos.todos = finished;
+
+            let target = match self.carry_over {
+                CarryOverPolicy::CarryToSameWeekday => {
+                    let weeks_behind = ((today - date).num_days() + 6) / 7;
+                    date + Duration::days(weeks_behind * 7)
+                }
+                _ => today,
+            };
+            for mut todo in unfinished {
+                let target_todos = self.get_day_mut(target);
+                todo.date = target;
+                todo.carried_count += 1;
+                todo.position = target_todos.todos.len() as i32 + 1;
+                target_todos.todos.push(todo);
+                carried += 1;
+            }
+        }
+        carried
+    }
+
+    // A completed occurrence of a series goes back to being a plain occurrence: the
+    // one-off copy is dropped and the date is no longer skipped by the series
+    fn uncomplete(&mut self, id: Uuid, date: NaiveDate) {
+        let Some(todo) = self
+            .get_day(date)
+            .and_then(|day_todos| day_todos.todos.iter().find(|t| t.id == id))
+        else {
+            return;
+        };
+        let series_rule = todo.series_id.and_then(|series_id| {
+            self.recurring
+                .iter_mut()
+                .find(|series| series.id == series_id)
+                .and_then(|series| series.recurrence.as_mut())
+        });
+        match series_rule {
+            Some(rule) if rule.exceptions.contains(&date) => {
+                rule.exceptions.retain(|exception| *exception != date);
+                self.take_todo(id);
+            }
+            _ => {
+                if let Some(todo) = self.get_day_mut(date).todos.iter_mut().find(|t| t.id == id) {
+                    todo.completed_at = None;
+                }
+            }
+        }
+        self.prune_empty_days();
+    }
+
+    fn prune_empty_days(&mut self) {
+        self.days.retain(|_, day_todos| !day_todos.todos.is_empty());
+    }
+}
+
+// Version 0 of the todo store, written before todos were tied to dates, kept one
+// bucket per weekday. Those are moved onto the current week when migrated.
+#[derive(Deserialize)]
+struct LegacyTodo {
+    id: Uuid,
+    content: String,
+    day: String,
+    created_at: NaiveDateTime,
+    position: i32,
+    #[serde(default)]
+    recurrence: Option<RecurrenceRule>,
+}
+
+#[derive(Deserialize)]
+struct LegacyDayTodos {
+    todos: Vec<LegacyTodo>,
+}
+
+#[derive(Deserialize)]
+struct LegacyTodoStore {
+    monday: LegacyDayTodos,
+    tuesday: LegacyDayTodos,
+    wednesday: LegacyDayTodos,
+    thursday: LegacyDayTodos,
+    friday: LegacyDayTodos,
+    saturday: LegacyDayTodos,
+    sunday: LegacyDayTodos,
+    #[serde(default)]
+    recurring: Vec<LegacyTodo>,
+}
+
+impl LegacyTodo {
+    fn into_todo(self, week_start: NaiveDate) -> Todo {
+        let date = match &self.recurrence {
+            Some(rule) => rule.start,
+            None => date_for_day_name(week_start, &self.day).unwrap_or(week_start),
+        };
+        Todo {
+            id: self.id,
+            content: self.content,
+            date,
+            created_at: self.created_at,
+            position: self.position,
+            recurrence: self.recurrence,
+            completed_at: None,
+            series_id: None,
+            carried_count: 0,
+        }
+    }
+}
+
+impl From<LegacyTodoStore> for TodoStore {
+    fn from(legacy: LegacyTodoStore) -> Self {
+        let week_start = week_start_monday(Local::now().date_naive());
+        info!("Migrating weekday todo store onto week of {}", week_start);
+
+        let mut store = TodoStore::default();
+        for bucket in [
+            legacy.monday,
+            legacy.tuesday,
+            legacy.wednesday,
+            legacy.thursday,
+            legacy.friday,
+            legacy.saturday,
+            legacy.sunday,
+        ] {
+            for todo in bucket.todos {
+                let todo = todo.into_todo(week_start);
+                store.get_day_mut(todo.date).todos.push(todo);
+            }
         }
+        store.recurring = legacy
+            .recurring
+            .into_iter()
+            .map(|todo| todo.into_todo(week_start))
+            .collect();
+        store.prune_empty_days();
+        store
+    }
+}
+
+impl Versioned for TodoStore {
+    const SCHEMA_VERSION: u32 = 1;
+    const SCHEMA_MIGRATIONS: &'static [Migration] = &[Migration {
+        from: 0,
+        description: "Move weekday buckets onto dates in the current week",
+        apply: |data| {
+            let legacy: LegacyTodoStore =
+                serde_json::from_value(data).map_err(|e| e.to_string())?;
+            serde_json::to_value(TodoStore::from(legacy)).map_err(|e| e.to_string())
+        },
+    }];
+
+    fn unversioned_version(data: &serde_json::Value) -> u32 {
+        if data.get("monday").is_some() {
+            0
+        } else {
+            1
+        }
+    }
+}
+
+impl Entity for Todo {
+    type Key = Uuid;
+
+    fn key(&self) -> Option<Uuid> {
+        Some(self.id)
     }
 
-    fn get_day(&self, day: &str) -> &DayTodos {
-        match day.to_lowercase().as_str() {
-            "monday" => &self.monday,
-            "tuesday" => &self.tuesday,
-            "wednesday" => &self.wednesday,
-            "thursday" => &self.thursday,
-            "friday" => &self.friday,
-            "saturday" => &self.saturday,
-            "sunday" => &self.sunday,
-            _ => panic!("Invalid day: {}", day),
+    fn name(&self) -> String {
+        self.content.clone()
+    }
+}
+
+fn merge_todo(merger: &mut Merger, path: &str, base: &Todo, mine: &Todo, theirs: &Todo) -> Todo {
+    Todo {
+        id: mine.id,
+        content: merger.field(path, "content", base, mine, theirs, |t| &t.content),
+        date: merger.field(path, "date", base, mine, theirs, |t| &t.date),
+        created_at: merger.field(path, "created_at", base, mine, theirs, |t| &t.created_at),
+        position: merger.field(path, "position", base, mine, theirs, |t| &t.position),
+        recurrence: merger.field(path, "recurrence", base, mine, theirs, |t| &t.recurrence),
+        completed_at: merger.field(path, "completed_at", base, mine, theirs, |t| {
+            &t.completed_at
+        }),
+        series_id: merger.field(path, "series_id", base, mine, theirs, |t| &t.series_id),
+        carried_count: merger.field(path, "carried_count", base, mine, theirs, |t| {
+            &t.carried_count
+        }),
+    }
+}
+
+fn dated_todos(store: &TodoStore) -> Vec<Todo> {
+    store
+        .days
+        .values()
+        .flat_map(|day_todos| day_todos.todos.iter().cloned())
+        .collect()
+}
+
+// Dated todos are merged by id rather than per day, so a todo moved to another
+// day on one side and edited on the other keeps both changes
+impl Mergeable for TodoStore {
+    fn merge(base: &Self, mine: &Self, theirs: &Self) -> Merged<Self> {
+        let mut merger = Merger::default();
+        let todos = merger.list(
+            "todos",
+            &dated_todos(base),
+            &dated_todos(mine),
+            &dated_todos(theirs),
+            merge_todo,
+        );
+        let mut days: BTreeMap<NaiveDate, DayTodos> = BTreeMap::new();
+        for todo in todos {
+            days.entry(todo.date).or_default().todos.push(todo);
+        }
+        for day_todos in days.values_mut() {
+            day_todos.todos.sort_by_key(|todo| todo.position);
         }
+
+        let data = TodoStore {
+            days,
+            recurring: merger.list(
+                "recurring",
+                &base.recurring,
+                &mine.recurring,
+                &theirs.recurring,
+                merge_todo,
+            ),
+            carry_over: merger.field("", "carry_over", base, mine, theirs, |s| &s.carry_over),
+        };
+        merger.finish(data)
+    }
+}
+
+// A recurring todo as it appears on one concrete date
+fn occurrence(todo: &Todo, date: NaiveDate) -> Todo {
+    Todo {
+        date,
+        ..todo.clone()
+    }
+}
+
+// Dated and recurring todos share one table, keyed by id
+fn todo_rows(store: &TodoStore) -> BTreeMap<Uuid, (bool, &Todo)> {
+    store
+        .days
+        .values()
+        .flat_map(|day_todos| day_todos.todos.iter())
+        .map(|todo| (todo.id, (false, todo)))
+        .chain(store.recurring.iter().map(|todo| (todo.id, (true, todo))))
+        .collect()
+}
+
+impl SqliteModel for TodoStore {
+    const MIGRATIONS: &'static [&'static str] = &["
+        CREATE TABLE todos (
+            id TEXT PRIMARY KEY,
+            recurring INTEGER NOT NULL DEFAULT 0,
+            content TEXT NOT NULL,
+            date TEXT NOT NULL,
+            created_at TEXT NOT NULL,
+            position INTEGER NOT NULL,
+            recurrence TEXT,
+            completed_at TEXT,
+            series_id TEXT,
+            carried_count INTEGER NOT NULL DEFAULT 0
+        );
+        CREATE INDEX todos_by_date ON todos (date);
+        CREATE TABLE todo_settings (key TEXT PRIMARY KEY, value TEXT NOT NULL);
+    "];
+
+    fn load(conn: &Connection) -> rusqlite::Result<Self> {
+        let mut store = TodoStore::default();
+        let mut statement = conn.prepare(
+            "SELECT id, recurring, content, date, created_at, position, recurrence,
+                    completed_at, series_id, carried_count
+             FROM todos
+             ORDER BY date, position, rowid",
+        )?;
+        let mut rows = statement.query([])?;
+        while let Some(row) = rows.next()? {
+            let recurring: bool = row.get(1)?;
+            let todo = Todo {
+                id: parse_column(0, row.get(0)?)?,
+                content: row.get(2)?,
+                date: row.get(3)?,
+                created_at: row.get(4)?,
+                position: row.get(5)?,
+                recurrence: row
+                    .get::<_, Option<String>>(6)?
+                    .map(|rule| from_json_column(6, rule))
+                    .transpose()?,
+                completed_at: row.get(7)?,
+                series_id: row
+                    .get::<_, Option<String>>(8)?
+                    .map(|id| parse_column(8, id))
+                    .transpose()?,
+                carried_count: row.get(9)?,
+            };
+            if recurring {
+                store.recurring.push(todo);
+            } else {
+                store.get_day_mut(todo.date).todos.push(todo);
+            }
+        }
+
+        let carry_over: Option<String> = conn
+            .query_row(
+                "SELECT value FROM todo_settings WHERE key = 'carry_over'",
+                [],
+                |row| row.get(0),
+            )
+            .ok();
+        store.carry_over = carry_over
+            .map(|policy| CarryOverPolicy::from_string(&policy))
+            .unwrap_or_default();
+        Ok(store)
+    }
+
+    fn persist(tx: &Transaction, before: &Self, after: &Self) -> rusqlite::Result<()> {
+        let old_rows = todo_rows(before);
+        let new_rows = todo_rows(after);
+        let changes = row_changes(&old_rows, &new_rows);
+        for (id, (recurring, todo)) in changes.upserted {
+            tx.execute(
+                "INSERT INTO todos (id, recurring, content, date, created_at, position,
+                                    recurrence, completed_at, series_id, carried_count)
+                 VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)
+                 ON CONFLICT(id) DO UPDATE SET
+                     recurring = excluded.recurring,
+                     content = excluded.content,
+                     date = excluded.date,
+                     created_at = excluded.created_at,
+                     position = excluded.position,
+                     recurrence = excluded.recurrence,
+                     completed_at = excluded.completed_at,
+                     series_id = excluded.series_id,
+                     carried_count = excluded.carried_count",
+                params![
+                    id.to_string(),
+                    recurring,
+                    todo.content,
+                    todo.date,
+                    todo.created_at,
+                    todo.position,
+                    todo.recurrence.as_ref().map(to_json_column).transpose()?,
+                    todo.completed_at,
+                    todo.series_id.map(|id| id.to_string()),
+                    todo.carried_count,
+                ],
+            )?;
+        }
+        for id in changes.removed {
+            tx.execute("DELETE FROM todos WHERE id = ?1", [id.to_string()])?;
+        }
+
+        if before.carry_over != after.carry_over {
+            tx.execute(
+                "INSERT OR REPLACE INTO todo_settings (key, value) VALUES ('carry_over', ?1)",
+                [after.carry_over.to_string()],
+            )?;
+        }
+        Ok(())
     }
 }
 
 pub struct TodoManager {
-    storage: JsonStorage<TodoStore>,
+    storage: Storage<TodoStore, JsonFormat>,
 }
 
 impl TodoManager {
     pub fn new() -> Result<Self, String> {
         Ok(Self {
-            storage: JsonStorage::new(get_path_manager().todos_file())
-                .map_err(|e| e.to_string())?,
+            storage: Storage::new(get_path_manager().todos_file()).map_err(|e| e.to_string())?,
         })
     }
 
-    pub async fn get_todos_by_day(&self, day: &str) -> Result<Vec<Todo>, String> {
-        debug!("Getting todos for day: {}", day);
+    pub async fn get_todos_by_date(&self, date: NaiveDate) -> Result<Vec<Todo>, String> {
+        debug!("Getting todos for date: {}", date);
         self.storage
             .read(|store| {
-                let day_todos = store.get_day(day);
-                let mut todos = day_todos.todos.clone();
+                let mut todos = store
+                    .get_day(date)
+                    .map(|day_todos| day_todos.todos.clone())
+                    .unwrap_or_default();
                 todos.sort_by_key(|t| t.position);
+
+                // Recurring todos are listed after the one-off todos of that day
+                todos.extend(
+                    store
+                        .recurring
+                        .iter()
+                        .filter(|t| t.recurrence.as_ref().map_or(false, |r| r.occurs_on(date)))
+                        .map(|t| occurrence(t, date)),
+                );
                 todos
             })
             .await
             .map_err(|e| e.to_string())
     }
 
-    pub async fn create_todo(&self, content: String, day: String) -> Result<(), String> {
-        debug!("Creating new todo for day: {}", day);
-        let day_clone = day.clone();
-        let created_at = chrono::Local::now().naive_local();
+    // Start dates of every earlier week that still has todos recorded, newest first
+    pub async fn get_history_weeks(&self, before: NaiveDate) -> Result<Vec<NaiveDate>, String> {
+        debug!("Getting todo history before {}", before);
+        let current_week = week_start_monday(before);
+        self.storage
+            .read(|store| {
+                let weeks: BTreeSet<NaiveDate> = store
+                    .days
+                    .iter()
+                    .filter(|(_, day_todos)| !day_todos.todos.is_empty())
+                    .map(|(date, _)| week_start_monday(*date))
+                    .filter(|week| *week < current_week)
+                    .collect();
+                weeks.into_iter().rev().collect()
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn get_recurring_todos(&self) -> Result<Vec<Todo>, String> {
+        debug!("Getting recurring todos");
+        self.storage
+            .read(|store| store.recurring.clone())
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    // Expand every recurring todo into its concrete dates for the week starting on `week_start`
+    pub async fn expand_week(
+        &self,
+        week_start: NaiveDate,
+    ) -> Result<Vec<(NaiveDate, Todo)>, String> {
+        debug!("Expanding recurring todos for week of {}", week_start);
+        let week_end = week_start + Duration::days(7);
+        self.storage
+            .read(|store| {
+                let mut occurrences: Vec<(NaiveDate, Todo)> = store
+                    .recurring
+                    .iter()
+                    .flat_map(|todo| {
+                        todo.recurrence
+                            .as_ref()
+                            .map(|rule| rule.occurrences_between(week_start, week_end))
+                            .unwrap_or_default()
+                            .into_iter()
+                            .map(move |date| (date, occurrence(todo, date)))
+                    })
+                    .collect();
+                occurrences.sort_by(|a, b| a.0.cmp(&b.0).then(a.1.position.cmp(&b.1.position)));
+                occurrences
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn create_recurring_todo(
+        &self,
+        content: String,
+        rule: RecurrenceRule,
+    ) -> Result<(), String> {
+        debug!("Creating recurring todo: {}", rule.describe());
+        let created_at = Local::now().naive_local();
+
+        self.storage
+            .write(|store| {
+                let position = store.recurring.len() as i32 + 1;
+                store.recurring.push(Todo {
+                    id: Uuid::new_v4(),
+                    content,
+                    date: rule.start,
+                    created_at,
+                    position,
+                    recurrence: Some(rule),
+                    completed_at: None,
+                    series_id: None,
+                    carried_count: 0,
+                });
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
 
+    pub async fn update_recurrence(&self, id: Uuid, rule: RecurrenceRule) -> Result<(), String> {
+        debug!("Updating recurrence of todo {}: {}", id, rule.describe());
         self.storage
             .write(|store| {
-                let day_todos = store.get_day_mut(&day_clone);
+                if let Some(todo) = store.recurring.iter_mut().find(|t| t.id == id) {
+                    todo.date = rule.start;
+                    todo.recurrence = Some(rule);
+                }
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn create_todo(&self, content: String, date: NaiveDate) -> Result<(), String> {
+        debug!("Creating new todo for date: {}", date);
+        let created_at = Local::now().naive_local();
+
+        self.storage
+            .write(|store| {
+                let day_todos = store.get_day_mut(date);
                 let position = day_todos.todos.len() as i32 + 1;
                 let todo = Todo {
                     id: Uuid::new_v4(),
                     content,
-                    day: day_clone,
+                    date,
                     created_at,
                     position,
+                    recurrence: None,
+                    completed_at: None,
+                    series_id: None,
+                    carried_count: 0,
                 };
                 day_todos.todos.push(todo);
             })
@@ -98,31 +586,117 @@ impl TodoManager {
             .map_err(|e| e.to_string())
     }
 
+    // Completing one occurrence of a recurring todo records it as a one-off todo on that date,
+    // so the series keeps going and the finished occurrence stays in the day's history.
+    pub async fn complete_todo(&self, id: Uuid, date: NaiveDate) -> Result<(), String> {
+        debug!("Completing todo {} on {}", id, date);
+        let completed_at = Local::now().naive_local();
+        self.storage
+            .write(|store| {
+                if let Some(series) = store.recurring.iter_mut().find(|t| t.id == id) {
+                    let Some(rule) = series.recurrence.as_mut() else {
+                        return;
+                    };
+                    if !rule.occurs_on(date) {
+                        return;
+                    }
+                    rule.exceptions.push(date);
+                    rule.exceptions.sort();
+
+                    let instance = Todo {
+                        id: Uuid::new_v4(),
+                        date,
+                        recurrence: None,
+                        completed_at: Some(completed_at),
+                        series_id: Some(series.id),
+                        ..series.clone()
+                    };
+                    let day_todos = store.get_day_mut(date);
+                    day_todos.todos.push(Todo {
+                        position: day_todos.todos.len() as i32 + 1,
+                        ..instance
+                    });
+                    return;
+                }
+
+                if let Some(todo) = store
+                    .get_day_mut(date)
+                    .todos
+                    .iter_mut()
+                    .find(|t| t.id == id)
+                {
+                    todo.completed_at = Some(completed_at);
+                }
+                store.prune_empty_days();
+            })
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn uncomplete_todo(&self, id: Uuid, date: NaiveDate) -> Result<(), String> {
+        debug!("Uncompleting todo {} on {}", id, date);
+        self.storage
+            .write(|store| store.uncomplete(id, date))
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn get_carry_over_policy(&self) -> Result<CarryOverPolicy, String> {
+        self.storage
+            .read(|store| store.carry_over)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn set_carry_over_policy(&self, policy: CarryOverPolicy) -> Result<(), String> {
+        debug!("Setting carry-over policy to {:?}", policy);
+        self.storage
+            .write(|store| store.carry_over = policy)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    // Returns how many todos were moved forward
+    pub async fn carry_over(&self, today: NaiveDate) -> Result<usize, String> {
+        let needs_carry = self
+            .storage
+            .read(|store| {
+                store.carry_over != CarryOverPolicy::Leave
+                    && store
+                        .days
+                        .range(..today)
+                        .any(|(_, day)| day.todos.iter().any(|t| !t.is_completed()))
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        if !needs_carry {
+            return Ok(0);
+        }
+
+        let carried = self
+            .storage
+            .write(|store| {
+                let carried = store.carry_over_unfinished(today);
+                store.prune_empty_days();
+                carried
+            })
+            .await
+            .map_err(|e| e.to_string())?;
+        info!("Carried over {} unfinished todos to {}", carried, today);
+        Ok(carried)
+    }
+
     pub async fn delete_todo(&self, id: Uuid) -> Result<(), String> {
         debug!("Deleting todo with id: {}", id);
         self.storage
             .write(|store| {
-                let days = vec![
-                    "monday",
-                    "tuesday",
-                    "wednesday",
-                    "thursday",
-                    "friday",
-                    "saturday",
-                    "sunday",
-                ];
-
-                for day in days {
-                    let day_todos = store.get_day_mut(day);
-                    if let Some(pos) = day_todos.todos.iter().position(|t| t.id == id) {
-                        day_todos.todos.remove(pos);
-                        // Reorder remaining todos
-                        for (i, todo) in day_todos.todos.iter_mut().enumerate() {
-                            todo.position = (i + 1) as i32;
-                        }
-                        break;
-                    }
+                if let Some(pos) = store.recurring.iter().position(|t| t.id == id) {
+                    store.recurring.remove(pos);
+                    return;
                 }
+
+                store.take_todo(id);
+                store.prune_empty_days();
             })
             .await
             .map_err(|e| e.to_string())
@@ -130,14 +704,13 @@ impl TodoManager {
 
     pub async fn update_positions(
         &self,
-        day: &str,
+        date: NaiveDate,
         updates: Vec<(Uuid, i32)>,
     ) -> Result<(), String> {
-        debug!("Updating positions for day: {}", day);
-        let day_clone = day.to_string();
+        debug!("Updating positions for date: {}", date);
         self.storage
             .write(|store| {
-                let day_todos = store.get_day_mut(&day_clone);
+                let day_todos = store.get_day_mut(date);
                 let position_map: HashMap<_, _> = updates.into_iter().collect();
 
                 for todo in day_todos.todos.iter_mut() {
@@ -145,48 +718,23 @@ impl TodoManager {
                         todo.position = new_position;
                     }
                 }
+                store.prune_empty_days();
             })
             .await
             .map_err(|e| e.to_string())
     }
 
-    pub async fn move_todo(&self, id: Uuid, new_day: String) -> Result<(), String> {
-        debug!("Moving todo {} to day: {}", id, new_day);
-        let new_day_clone = new_day.clone();
+    pub async fn move_todo(&self, id: Uuid, new_date: NaiveDate) -> Result<(), String> {
+        debug!("Moving todo {} to date: {}", id, new_date);
         self.storage
             .write(|store| {
-                let days = vec![
-                    "monday",
-                    "tuesday",
-                    "wednesday",
-                    "thursday",
-                    "friday",
-                    "saturday",
-                    "sunday",
-                ];
-
-                // Find and remove todo from current day
-                let mut todo_to_move = None;
-                for day in &days {
-                    let day_todos = store.get_day_mut(day);
-                    if let Some(pos) = day_todos.todos.iter().position(|t| t.id == id) {
-                        let mut todo = day_todos.todos.remove(pos);
-                        todo.day = new_day_clone.clone(); // Update the day field
-                        todo_to_move = Some(todo);
-                        // Reorder remaining todos
-                        for (i, todo) in day_todos.todos.iter_mut().enumerate() {
-                            todo.position = (i + 1) as i32;
-                        }
-                        break;
-                    }
-                }
-
-                // Add todo to new day
-                if let Some(mut todo) = todo_to_move {
-                    let new_day_todos = store.get_day_mut(&new_day_clone);
+                if let Some(mut todo) = store.take_todo(id) {
+                    let new_day_todos = store.get_day_mut(new_date);
+                    todo.date = new_date;
                     todo.position = new_day_todos.todos.len() as i32 + 1;
                     new_day_todos.todos.push(todo);
                 }
+                store.prune_empty_days();
             })
             .await
             .map_err(|e| e.to_string())
@@ -197,6 +745,62 @@ impl TodoManager {
         self.storage.force_save().await.map_err(|e| e.to_string())
     }
 
+    pub async fn flush(&self) -> Result<(), String> {
+        self.storage.flush().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn corruption(&self) -> Option<Corruption> {
+        self.storage.corruption().await
+    }
+
+    pub fn newer_version(&self) -> Option<u32> {
+        self.storage.newer_version()
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        self.storage.subscribe()
+    }
+
+    pub async fn conflict(&self) -> Option<Conflict> {
+        self.storage.conflict().await
+    }
+
+    pub async fn resolve_conflict(&self, resolution: ConflictResolution) -> Result<(), String> {
+        self.storage
+            .resolve_conflict(resolution)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn restore_from_backup(&self) -> Result<PathBuf, String> {
+        self.storage
+            .restore_from_backup()
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn start_fresh(&self) -> Result<(), String> {
+        self.storage.start_fresh().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn list_backups(&self) -> Result<Vec<BackupInfo>, String> {
+        self.storage.list_backups().await.map_err(|e| e.to_string())
+    }
+
+    pub async fn diff_backup(&self, index: usize) -> Result<Vec<DiffEntry>, String> {
+        self.storage
+            .diff_backup(index)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
+    pub async fn restore_backup(&self, index: usize) -> Result<PathBuf, String> {
+        self.storage
+            .restore_backup(index)
+            .await
+            .map_err(|e| e.to_string())
+    }
+
     pub async fn reload(&self) -> Result<(), String> {
         self.storage.reload().await.map_err(|e| e.to_string())
     }
@@ -208,3 +812,102 @@ static TODO_MANAGER: Lazy<TodoManager> =
 pub fn get_todo_manager() -> &'static TodoManager {
     &*TODO_MANAGER
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::recurrence::Recurrence;
+    use crate::storage::{decode, encode};
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_carry_over_policies() {
+        let mut store = TodoStore::default();
+        // Nothing moves until carrying over is switched on
+        assert_eq!(store.carry_over, CarryOverPolicy::Leave);
+        assert_eq!(CarryOverPolicy::from_string(""), CarryOverPolicy::Leave);
+        let monday = date(2024, 3, 4);
+        store
+            .get_day_mut(monday)
+            .todos
+            .push(Todo::new("Call bank".to_string(), monday));
+        let mut done = Todo::new("Laundry".to_string(), monday);
+        done.completed_at = Some(monday.and_hms_opt(10, 0, 0).unwrap());
+        store.get_day_mut(monday).todos.push(done);
+
+        // Thursday of the following week: same-weekday moves to the next Monday on or after it
+        let today = date(2024, 3, 14);
+        store.carry_over = CarryOverPolicy::CarryToSameWeekday;
+        assert_eq!(store.carry_over_unfinished(today), 1);
+        let next_monday = date(2024, 3, 18);
+        let carried = &store.get_day(next_monday).unwrap().todos[0];
+        assert_eq!(carried.content, "Call bank");
+        assert_eq!(carried.carried_count, 1);
+        assert_eq!(store.get_day(monday).unwrap().todos.len(), 1);
+
+        // Later, carrying to today bumps the counter again
+        store.carry_over = CarryOverPolicy::CarryToToday;
+        let later = date(2024, 3, 20);
+        assert_eq!(store.carry_over_unfinished(later), 1);
+        let carried = &store.get_day(later).unwrap().todos[0];
+        assert_eq!(carried.date, later);
+        assert_eq!(carried.carried_count, 2);
+
+        store.carry_over = CarryOverPolicy::Leave;
+        assert_eq!(store.carry_over_unfinished(date(2024, 4, 1)), 0);
+    }
+
+    #[test]
+    fn test_uncompleting_an_occurrence_resumes_the_series() {
+        let mut store = TodoStore::default();
+        let monday = date(2024, 3, 4);
+        let mut series = Todo::new("Water plants".to_string(), monday);
+        let mut rule = RecurrenceRule::new(Recurrence::Daily { interval: 1 }, monday);
+        rule.exceptions.push(monday);
+        series.recurrence = Some(rule);
+        let mut instance = Todo::new("Water plants".to_string(), monday);
+        instance.series_id = Some(series.id);
+        instance.completed_at = Some(monday.and_hms_opt(9, 0, 0).unwrap());
+        let instance_id = instance.id;
+        store.recurring.push(series);
+        store.get_day_mut(monday).todos.push(instance);
+
+        store.uncomplete(instance_id, monday);
+        assert!(store.get_day(monday).is_none());
+        let rule = store.recurring[0].recurrence.as_ref().unwrap();
+        assert!(rule.exceptions.is_empty());
+        assert!(rule.occurs_on(monday));
+    }
+
+    #[test]
+    fn test_legacy_store_migrates_onto_current_week() {
+        let store: TodoStore =
+            decode::<_, JsonFormat>(include_str!("../../tests/fixtures/todos/v0.json")).unwrap();
+        let tuesday = week_start_monday(Local::now().date_naive()) + Duration::days(1);
+        let todos = &store.get_day(tuesday).unwrap().todos;
+        assert_eq!(todos.len(), 1);
+        assert_eq!(todos[0].content, "Water plants");
+        assert_eq!(todos[0].date, tuesday);
+        assert_eq!(store.days.len(), 1);
+
+        // A migrated store is written in the current versioned layout
+        let content = encode::<_, JsonFormat>(&store).unwrap();
+        assert!(content.contains("\"version\": 1"));
+        let reloaded: TodoStore = decode::<_, JsonFormat>(&content).unwrap();
+        assert_eq!(reloaded.days.len(), 1);
+    }
+
+    #[test]
+    fn test_unversioned_dated_store_loads_as_version_1() {
+        let store: TodoStore =
+            decode::<_, JsonFormat>(include_str!("../../tests/fixtures/todos/v1.json")).unwrap();
+        let todos = &store.get_day(date(2024, 3, 4)).unwrap().todos;
+        assert!(todos[0].is_completed());
+        assert_eq!(store.recurring.len(), 1);
+        assert!(store.recurring[0].is_recurring());
+        assert_eq!(store.carry_over, CarryOverPolicy::CarryToSameWeekday);
+    }
+}
@@ -1,5 +1,6 @@
-use chrono::{Datelike, NaiveDate, Weekday};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
 use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 use uuid::Uuid;
 
 #[derive(Debug, Serialize, Deserialize, Clone)]
@@ -9,6 +10,10 @@ pub struct Habit {
     pub start_date: NaiveDate,
     pub color: String,
     pub week_start: String,
+    #[serde(default)]
+    pub category: Option<String>,
+    #[serde(default)]
+    pub archived: bool,
 }
 
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
@@ -51,6 +56,11 @@ impl WeekStart {
         }
     }
 
+    // First day of the week containing the given date
+    pub fn week_start_date(&self, date: NaiveDate) -> NaiveDate {
+        date - Duration::days(self.get_days_from_start(date))
+    }
+
     // String conversion methods
     pub fn to_string(&self) -> String {
         match self {
@@ -78,11 +88,398 @@ impl WeekStart {
     }
 }
 
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum HabitSchedule {
+    #[default]
+    Daily,
+    Weekdays {
+        days: Vec<Weekday>,
+    },
+    TimesPerWeek {
+        times: u32,
+    },
+    TimesPerMonth {
+        times: u32,
+    },
+}
+
+impl HabitSchedule {
+    pub fn to_string(&self) -> String {
+        match self {
+            HabitSchedule::Daily => "daily",
+            HabitSchedule::Weekdays { .. } => "weekdays",
+            HabitSchedule::TimesPerWeek { .. } => "per_week",
+            HabitSchedule::TimesPerMonth { .. } => "per_month",
+        }
+        .to_string()
+    }
+}
+
+// How a single calendar day counts for a habit
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DayStatus {
+    Completed,
+    Missed,
+    // Not scheduled, or the period's target was already reached
+    Neutral,
+    // Scheduled, but the day or its period is still in progress
+    Pending,
+    BeforeStart,
+    Future,
+}
+
+// Unit and daily target for habits tracked as an amount rather than done / not done
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct HabitMeasure {
+    pub unit: String,
+    pub target: f64,
+}
+
+// Free-form reflection for a single day, with an optional 1–5 mood rating
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct HabitNote {
+    #[serde(default)]
+    pub text: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mood: Option<u8>,
+}
+
+impl HabitNote {
+    pub fn is_empty(&self) -> bool {
+        self.text.trim().is_empty() && self.mood.is_none()
+    }
+}
+
 #[derive(Debug, Serialize, Clone, PartialEq, Deserialize)]
 pub struct HabitData {
     pub title: String,
     pub start_date: NaiveDate,
+    // For measured habits this holds the days whose value reached the target
     pub completed_days: Vec<NaiveDate>,
     pub week_start: WeekStart,
     pub color: String,
+    #[serde(default)]
+    pub schedule: HabitSchedule,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub measure: Option<HabitMeasure>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub values: BTreeMap<NaiveDate, f64>,
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub notes: BTreeMap<NaiveDate, HabitNote>,
+    #[serde(default)]
+    pub position: i32,
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub archived: bool,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub category: Option<String>,
+}
+
+impl HabitData {
+    pub fn new(title: String, start_date: NaiveDate, color: String, week_start: WeekStart) -> Self {
+        Self {
+            title,
+            start_date,
+            completed_days: Vec::new(),
+            week_start,
+            color,
+            schedule: HabitSchedule::default(),
+            measure: None,
+            values: BTreeMap::new(),
+            notes: BTreeMap::new(),
+            position: 0,
+            archived: false,
+            category: None,
+        }
+    }
+
+    pub fn value_on(&self, date: NaiveDate) -> f64 {
+        match self.measure {
+            Some(_) => self.values.get(&date).copied().unwrap_or(0.0),
+            None if self.is_completed(date) => 1.0,
+            None => 0.0,
+        }
+    }
+
+    // Fraction of the daily target reached, between 0 and 1
+    pub fn progress(&self, date: NaiveDate) -> f64 {
+        match &self.measure {
+            Some(measure) if measure.target > 0.0 => {
+                (self.value_on(date) / measure.target).clamp(0.0, 1.0)
+            }
+            _ => self.value_on(date).min(1.0),
+        }
+    }
+
+    pub fn set_value(&mut self, date: NaiveDate, value: f64) {
+        if value > 0.0 {
+            self.values.insert(date, value);
+        } else {
+            self.values.remove(&date);
+        }
+        self.sync_completed_day(date);
+    }
+
+    // Switching a boolean habit to a measured one keeps its history by
+    // recording every completed day as meeting the target
+    pub fn set_measure(&mut self, measure: Option<HabitMeasure>) {
+        match &measure {
+            Some(new) => {
+                if self.measure.is_none() {
+                    self.values = self
+                        .completed_days
+                        .iter()
+                        .map(|date| (*date, new.target))
+                        .collect();
+                }
+            }
+            None => self.values.clear(),
+        }
+        self.measure = measure;
+
+        if self.measure.is_some() {
+            let target = self.measure.as_ref().map(|m| m.target).unwrap_or(0.0);
+            self.completed_days = self
+                .values
+                .iter()
+                .filter(|(_, value)| **value >= target)
+                .map(|(date, _)| *date)
+                .collect();
+        }
+    }
+
+    pub fn note_on(&self, date: NaiveDate) -> Option<&HabitNote> {
+        self.notes.get(&date)
+    }
+
+    pub fn set_note(&mut self, date: NaiveDate, note: HabitNote) {
+        if note.is_empty() {
+            self.notes.remove(&date);
+        } else {
+            self.notes.insert(
+                date,
+                HabitNote {
+                    text: note.text.trim().to_string(),
+                    mood: note.mood.map(|mood| mood.clamp(1, 5)),
+                },
+            );
+        }
+    }
+
+    // Notes in an inclusive date range, oldest first. A reversed range has none.
+    pub fn notes_between(&self, from: NaiveDate, to: NaiveDate) -> Vec<(NaiveDate, HabitNote)> {
+        if from > to {
+            return Vec::new();
+        }
+        self.notes
+            .range(from..=to)
+            .map(|(date, note)| (*date, note.clone()))
+            .collect()
+    }
+
+    fn sync_completed_day(&mut self, date: NaiveDate) {
+        let reached = match &self.measure {
+            Some(measure) => self.value_on(date) >= measure.target,
+            None => self.values.get(&date).is_some_and(|value| *value > 0.0),
+        };
+        self.completed_days.retain(|d| *d != date);
+        if reached {
+            self.completed_days.push(date);
+            self.completed_days.sort();
+        }
+    }
+
+    pub fn is_completed(&self, date: NaiveDate) -> bool {
+        self.completed_days.contains(&date)
+    }
+
+    // Inclusive date range of the week or month a day belongs to, for quota-based schedules
+    pub fn period_bounds(&self, date: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        match self.schedule {
+            HabitSchedule::TimesPerWeek { .. } => {
+                let start = self.week_start.week_start_date(date);
+                Some((start, start + Duration::days(6)))
+            }
+            HabitSchedule::TimesPerMonth { .. } => {
+                let start = date.with_day(1)?;
+                let next_month = if start.month() == 12 {
+                    NaiveDate::from_ymd_opt(start.year() + 1, 1, 1)?
+                } else {
+                    NaiveDate::from_ymd_opt(start.year(), start.month() + 1, 1)?
+                };
+                Some((start, next_month - Duration::days(1)))
+            }
+            _ => None,
+        }
+    }
+
+    pub fn completions_between(&self, from: NaiveDate, to: NaiveDate) -> usize {
+        self.completed_days
+            .iter()
+            .filter(|d| **d >= from && **d <= to && **d >= self.start_date)
+            .count()
+    }
+
+    pub fn day_status(&self, date: NaiveDate, today: NaiveDate) -> DayStatus {
+        if date > today {
+            return DayStatus::Future;
+        }
+        if date < self.start_date {
+            return DayStatus::BeforeStart;
+        }
+        if self.is_completed(date) {
+            return DayStatus::Completed;
+        }
+
+        let target = match &self.schedule {
+            HabitSchedule::Daily => None,
+            HabitSchedule::Weekdays { days } => {
+                if !days.contains(&date.weekday()) {
+                    return DayStatus::Neutral;
+                }
+                None
+            }
+            HabitSchedule::TimesPerWeek { times } | HabitSchedule::TimesPerMonth { times } => {
+                Some(*times as usize)
+            }
+        };
+
+        match (target, self.period_bounds(date)) {
+            (Some(target), Some((start, end))) => {
+                if self.completions_between(start, end) >= target {
+                    DayStatus::Neutral
+                } else if end >= today {
+                    DayStatus::Pending
+                } else {
+                    DayStatus::Missed
+                }
+            }
+            _ if date == today => DayStatus::Pending,
+            _ => DayStatus::Missed,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn habit(schedule: HabitSchedule, completed: &[u32]) -> HabitData {
+        HabitData {
+            completed_days: completed
+                .iter()
+                .map(|d| NaiveDate::from_ymd_opt(2024, 1, *d).unwrap())
+                .collect(),
+            schedule,
+            ..HabitData::new(
+                "Gym".to_string(),
+                NaiveDate::from_ymd_opt(2024, 1, 1).unwrap(),
+                "#800080".to_string(),
+                WeekStart::Monday,
+            )
+        }
+    }
+
+    #[test]
+    fn unscheduled_and_quota_days_are_neutral() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let today = day(20);
+
+        let weekdays = habit(
+            HabitSchedule::Weekdays {
+                days: vec![Weekday::Mon, Weekday::Wed, Weekday::Fri],
+            },
+            &[1],
+        );
+        assert_eq!(weekdays.day_status(day(1), today), DayStatus::Completed);
+        assert_eq!(weekdays.day_status(day(2), today), DayStatus::Neutral);
+        assert_eq!(weekdays.day_status(day(3), today), DayStatus::Missed);
+
+        // Week of Jan 1 meets the target, week of Jan 8 doesn't, week of Jan 15 is ongoing
+        let per_week = habit(HabitSchedule::TimesPerWeek { times: 2 }, &[1, 3, 9]);
+        assert_eq!(per_week.day_status(day(2), today), DayStatus::Neutral);
+        assert_eq!(per_week.day_status(day(10), today), DayStatus::Missed);
+        assert_eq!(per_week.day_status(day(16), today), DayStatus::Pending);
+        assert_eq!(per_week.day_status(day(21), today), DayStatus::Future);
+    }
+
+    #[test]
+    fn measured_values_drive_completion() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let mut water = habit(HabitSchedule::Daily, &[1, 2]);
+
+        // Existing completions carry over as meeting the target
+        water.set_measure(Some(HabitMeasure {
+            unit: "glasses".to_string(),
+            target: 8.0,
+        }));
+        assert_eq!(water.value_on(day(1)), 8.0);
+        assert!(water.is_completed(day(2)));
+
+        water.set_value(day(3), 4.0);
+        assert_eq!(water.progress(day(3)), 0.5);
+        assert!(!water.is_completed(day(3)));
+
+        water.set_value(day(3), 9.0);
+        assert!(water.is_completed(day(3)));
+        assert_eq!(water.progress(day(3)), 1.0);
+
+        water.set_value(day(1), 0.0);
+        assert!(!water.is_completed(day(1)));
+        assert!(!water.values.contains_key(&day(1)));
+    }
+
+    #[test]
+    fn boolean_habits_deserialize_without_measure() {
+        let json = r##"{"title":"Read","start_date":"2024-01-01","completed_days":["2024-01-02"],"week_start":"Monday","color":"#800080"}"##;
+        let habit: HabitData = serde_json::from_str(json).unwrap();
+        assert!(habit.measure.is_none());
+        assert_eq!(
+            habit.value_on(NaiveDate::from_ymd_opt(2024, 1, 2).unwrap()),
+            1.0
+        );
+    }
+
+    #[test]
+    fn empty_notes_are_removed() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let mut h = habit(HabitSchedule::Daily, &[]);
+
+        h.set_note(
+            day(2),
+            HabitNote {
+                text: " Too tired ".to_string(),
+                mood: Some(9),
+            },
+        );
+        h.set_note(
+            day(5),
+            HabitNote {
+                text: String::new(),
+                mood: Some(4),
+            },
+        );
+        assert_eq!(h.note_on(day(2)).unwrap().text, "Too tired");
+        assert_eq!(h.note_on(day(2)).unwrap().mood, Some(5));
+        assert_eq!(h.notes_between(day(1), day(4)).len(), 1);
+
+        h.set_note(day(2), HabitNote::default());
+        assert!(h.note_on(day(2)).is_none());
+    }
+
+    #[test]
+    fn reversed_note_ranges_are_empty() {
+        let day = |d| NaiveDate::from_ymd_opt(2024, 1, d).unwrap();
+        let mut h = habit(HabitSchedule::Daily, &[]);
+        h.set_note(
+            day(2),
+            HabitNote {
+                text: "Rest day".to_string(),
+                mood: None,
+            },
+        );
+
+        assert!(h.notes_between(day(4), day(1)).is_empty());
+        assert_eq!(h.notes_between(day(2), day(2)).len(), 1);
+    }
 }
@@ -0,0 +1,198 @@
+use crate::models::habit::{DayStatus, HabitData, HabitSchedule};
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use std::collections::HashMap;
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct HabitStats {
+    pub current_streak: u32,
+    pub longest_streak: u32,
+    pub rate_7: f64,
+    pub rate_30: f64,
+    pub rate_365: f64,
+    // Completions per weekday, ordered from the habit's week start
+    pub weekday_counts: Vec<(Weekday, u32)>,
+}
+
+impl HabitStats {
+    pub fn compute(habit: &HabitData, today: NaiveDate) -> Self {
+        let (current_streak, longest_streak) = streaks(habit, today);
+        Self {
+            current_streak,
+            longest_streak,
+            rate_7: completion_rate(habit, today, 7),
+            rate_30: completion_rate(habit, today, 30),
+            rate_365: completion_rate(habit, today, 365),
+            weekday_counts: weekday_distribution(habit, today),
+        }
+    }
+}
+
+// Streaks count completed days; neutral and still-pending days neither extend nor break them
+fn streaks(habit: &HabitData, today: NaiveDate) -> (u32, u32) {
+    let mut current = 0;
+    let mut longest = 0;
+    let mut date = habit.start_date;
+
+    while date <= today {
+        match habit.day_status(date, today) {
+            DayStatus::Completed => {
+                current += 1;
+                longest = longest.max(current);
+            }
+            DayStatus::Missed => current = 0,
+            _ => {}
+        }
+        date += Duration::days(1);
+    }
+
+    (current, longest)
+}
+
+// Share of scheduled days completed in the last `days` days, clipped to the habit's start.
+// For quota schedules a failed period only counts its shortfall as missed, not every empty day.
+pub fn completion_rate(habit: &HabitData, today: NaiveDate, days: i64) -> f64 {
+    let from = (today - Duration::days(days - 1)).max(habit.start_date);
+    let target = match habit.schedule {
+        HabitSchedule::TimesPerWeek { times } | HabitSchedule::TimesPerMonth { times } => {
+            Some(times as usize)
+        }
+        _ => None,
+    };
+
+    let mut completed = 0;
+    let mut missed = 0;
+    let mut period_missed: HashMap<NaiveDate, (usize, usize)> = HashMap::new();
+    let mut date = from;
+
+    while date <= today {
+        match habit.day_status(date, today) {
+            DayStatus::Completed => completed += 1,
+            DayStatus::Missed => match (target, habit.period_bounds(date)) {
+                (Some(target), Some((start, end))) => {
+                    let entry = period_missed.entry(start).or_insert_with(|| {
+                        let done = habit.completions_between(start, end);
+                        (0, target.saturating_sub(done))
+                    });
+                    entry.0 += 1;
+                }
+                _ => missed += 1,
+            },
+            _ => {}
+        }
+        date += Duration::days(1);
+    }
+
+    missed += period_missed
+        .values()
+        .map(|(days, shortfall)| (*days).min(*shortfall))
+        .sum::<usize>();
+
+    if completed + missed == 0 {
+        0.0
+    } else {
+        completed as f64 / (completed + missed) as f64
+    }
+}
+
+pub fn weekday_distribution(habit: &HabitData, today: NaiveDate) -> Vec<(Weekday, u32)> {
+    let mut counts = [0u32; 7];
+    for date in &habit.completed_days {
+        if *date >= habit.start_date && *date <= today {
+            counts[date.weekday().num_days_from_monday() as usize] += 1;
+        }
+    }
+
+    let mut weekday = habit.week_start.to_weekday();
+    (0..7)
+        .map(|_| {
+            let entry = (weekday, counts[weekday.num_days_from_monday() as usize]);
+            weekday = weekday.succ();
+            entry
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::habit::WeekStart;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    fn habit(start: NaiveDate, completed: Vec<NaiveDate>, week_start: WeekStart) -> HabitData {
+        HabitData {
+            completed_days: completed,
+            ..HabitData::new("Read".to_string(), start, "#800080".to_string(), week_start)
+        }
+    }
+
+    #[test]
+    fn streak_runs_through_leap_day() {
+        let completed = vec![
+            date(2024, 2, 27),
+            date(2024, 2, 28),
+            date(2024, 2, 29),
+            date(2024, 3, 1),
+        ];
+        let h = habit(date(2024, 2, 26), completed, WeekStart::Monday);
+
+        let stats = HabitStats::compute(&h, date(2024, 3, 1));
+        assert_eq!(stats.current_streak, 4);
+        assert_eq!(stats.longest_streak, 4);
+
+        // Today not done yet keeps the streak alive, a missed day ends it
+        assert_eq!(HabitStats::compute(&h, date(2024, 3, 2)).current_streak, 4);
+        assert_eq!(HabitStats::compute(&h, date(2024, 3, 3)).current_streak, 0);
+    }
+
+    #[test]
+    fn yearly_rate_covers_leap_year() {
+        let start = date(2024, 1, 1);
+        let completed: Vec<NaiveDate> = (0..366).map(|i| start + Duration::days(i)).collect();
+        let h = habit(start, completed, WeekStart::Monday);
+
+        // 366 days in 2024, so a 365 day window ending Dec 31 starts on Jan 2
+        let today = date(2024, 12, 31);
+        assert_eq!(completion_rate(&h, today, 365), 1.0);
+
+        let mut gap = h.clone();
+        gap.completed_days.retain(|d| *d != date(2024, 1, 1));
+        assert_eq!(completion_rate(&gap, today, 365), 1.0);
+        assert!(completion_rate(&gap, today, 366) < 1.0);
+    }
+
+    #[test]
+    fn mid_week_start_clips_windows_and_orders_weekdays() {
+        // Wednesday start with Sunday-based weeks
+        let start = date(2024, 5, 15);
+        let completed = vec![date(2024, 5, 15), date(2024, 5, 17), date(2024, 5, 19)];
+        let mut h = habit(start, completed, WeekStart::Sunday);
+
+        let today = date(2024, 5, 19);
+        // Five days since the start: Wed, Fri and Sun done
+        assert_eq!(completion_rate(&h, today, 30), 3.0 / 5.0);
+
+        let distribution = weekday_distribution(&h, today);
+        assert_eq!(distribution[0], (Weekday::Sun, 1));
+        assert_eq!(distribution[3], (Weekday::Wed, 1));
+        assert_eq!(distribution[4], (Weekday::Thu, 0));
+
+        // Two per week: the partial first week (Wed–Sat) met its target
+        h.schedule = HabitSchedule::TimesPerWeek { times: 2 };
+        let stats = HabitStats::compute(&h, today);
+        assert_eq!(stats.current_streak, 3);
+        assert_eq!(stats.rate_30, 1.0);
+    }
+
+    #[test]
+    fn failed_quota_counts_only_shortfall() {
+        let start = date(2024, 1, 1);
+        let mut h = habit(start, vec![date(2024, 1, 2)], WeekStart::Monday);
+        h.schedule = HabitSchedule::TimesPerWeek { times: 3 };
+
+        // One of three done in a closed week
+        assert_eq!(completion_rate(&h, date(2024, 1, 8), 8), 1.0 / 3.0);
+    }
+}
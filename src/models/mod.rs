@@ -1,3 +1,6 @@
 pub mod habit;
+pub mod habit_stats;
+pub mod recurrence;
+pub mod routine;
 pub mod timeline;
 pub mod todo;
@@ -0,0 +1,250 @@
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "snake_case")]
+pub enum Recurrence {
+    Daily {
+        interval: u32,
+    },
+    Weekly {
+        interval: u32,
+        weekday: Weekday,
+    },
+    MonthlyByDay {
+        interval: u32,
+        day: u32,
+    },
+    // nth counts from 1; -1 means the last matching weekday of the month
+    MonthlyByWeekday {
+        interval: u32,
+        nth: i32,
+        weekday: Weekday,
+    },
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RecurrenceRule {
+    pub pattern: Recurrence,
+    pub start: NaiveDate,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub until: Option<NaiveDate>,
+    // Dates skipped by the rule, e.g. because that occurrence was completed separately
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exceptions: Vec<NaiveDate>,
+}
+
+impl RecurrenceRule {
+    pub fn new(pattern: Recurrence, start: NaiveDate) -> Self {
+        Self {
+            pattern,
+            start,
+            until: None,
+            exceptions: Vec::new(),
+        }
+    }
+
+    pub fn occurs_on(&self, date: NaiveDate) -> bool {
+        if date < self.start
+            || self.until.map_or(false, |until| date > until)
+            || self.exceptions.contains(&date)
+        {
+            return false;
+        }
+
+        match self.pattern {
+            Recurrence::Daily { interval } => {
+                (date - self.start).num_days() % interval.max(1) as i64 == 0
+            }
+            Recurrence::Weekly { interval, weekday } => {
+                date.weekday() == weekday
+                    && weeks_between(self.start, date) % interval.max(1) as i64 == 0
+            }
+            Recurrence::MonthlyByDay { interval, day } => {
+                months_between(self.start, date) % interval.max(1) as i64 == 0
+                    && date.day() == day.min(days_in_month(date))
+            }
+            Recurrence::MonthlyByWeekday {
+                interval,
+                nth,
+                weekday,
+            } => {
+                months_between(self.start, date) % interval.max(1) as i64 == 0
+                    && date.weekday() == weekday
+                    && is_nth_weekday(date, nth)
+            }
+        }
+    }
+
+    // Occurrences in the half-open range [from, to)
+    pub fn occurrences_between(&self, from: NaiveDate, to: NaiveDate) -> Vec<NaiveDate> {
+        let from = from.max(self.start);
+        let mut dates = Vec::new();
+        let mut date = from;
+        while date < to {
+            if self.occurs_on(date) {
+                dates.push(date);
+            }
+            date += Duration::days(1);
+        }
+        dates
+    }
+
+    pub fn describe(&self) -> String {
+        let base = match self.pattern {
+            Recurrence::Daily { interval } if interval <= 1 => "Daily".to_string(),
+            Recurrence::Daily { interval } => format!("Every {} days", interval),
+            Recurrence::Weekly { interval, weekday } if interval <= 1 => {
+                format!("Every {}", weekday)
+            }
+            Recurrence::Weekly { interval, weekday } => {
+                format!("Every {} weeks on {}", interval, weekday)
+            }
+            Recurrence::MonthlyByDay { interval, day } if interval <= 1 => {
+                format!("Monthly on day {}", day)
+            }
+            Recurrence::MonthlyByDay { interval, day } => {
+                format!("Every {} months on day {}", interval, day)
+            }
+            Recurrence::MonthlyByWeekday {
+                interval,
+                nth,
+                weekday,
+            } => {
+                // Matches `is_nth_weekday`: only negative values count from the end
+                let ordinal = match nth {
+                    1 => "First".to_string(),
+                    2 => "Second".to_string(),
+                    3 => "Third".to_string(),
+                    4 => "Fourth".to_string(),
+                    5 => "Fifth".to_string(),
+                    n if n < 0 => "Last".to_string(),
+                    n => format!("{}th", n),
+                };
+                if interval <= 1 {
+                    format!("{} {} of the month", ordinal, weekday)
+                } else {
+                    format!("{} {} every {} months", ordinal, weekday, interval)
+                }
+            }
+        };
+
+        match self.until {
+            Some(until) => format!("{} until {}", base, until),
+            None => base,
+        }
+    }
+}
+
+fn weeks_between(start: NaiveDate, date: NaiveDate) -> i64 {
+    let start_monday = start - Duration::days(start.weekday().num_days_from_monday() as i64);
+    (date - start_monday).num_days() / 7
+}
+
+fn months_between(start: NaiveDate, date: NaiveDate) -> i64 {
+    (date.year() - start.year()) as i64 * 12 + date.month() as i64 - start.month() as i64
+}
+
+pub fn days_in_month(date: NaiveDate) -> u32 {
+    let (year, month) = if date.month() == 12 {
+        (date.year() + 1, 1)
+    } else {
+        (date.year(), date.month() + 1)
+    };
+    NaiveDate::from_ymd_opt(year, month, 1)
+        .and_then(|first_of_next| first_of_next.pred_opt())
+        .map(|last| last.day())
+        .unwrap_or(31)
+}
+
+fn is_nth_weekday(date: NaiveDate, nth: i32) -> bool {
+    if nth < 0 {
+        date.day() + 7 > days_in_month(date)
+    } else {
+        ((date.day() - 1) / 7 + 1) as i32 == nth
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(y: i32, m: u32, d: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(y, m, d).unwrap()
+    }
+
+    #[test]
+    fn test_every_other_tuesday() {
+        let rule = RecurrenceRule::new(
+            Recurrence::Weekly {
+                interval: 2,
+                weekday: Weekday::Tue,
+            },
+            date(2024, 1, 2),
+        );
+        let dates = rule.occurrences_between(date(2024, 1, 1), date(2024, 2, 1));
+        assert_eq!(
+            dates,
+            vec![date(2024, 1, 2), date(2024, 1, 16), date(2024, 1, 30)]
+        );
+    }
+
+    #[test]
+    fn test_first_monday_of_month() {
+        let rule = RecurrenceRule::new(
+            Recurrence::MonthlyByWeekday {
+                interval: 1,
+                nth: 1,
+                weekday: Weekday::Mon,
+            },
+            date(2024, 1, 1),
+        );
+        let dates = rule.occurrences_between(date(2024, 1, 1), date(2024, 4, 1));
+        assert_eq!(
+            dates,
+            vec![date(2024, 1, 1), date(2024, 2, 5), date(2024, 3, 4)]
+        );
+    }
+
+    #[test]
+    fn test_monthly_by_day_clamps_and_ends() {
+        let mut rule = RecurrenceRule::new(
+            Recurrence::MonthlyByDay {
+                interval: 1,
+                day: 31,
+            },
+            date(2024, 1, 31),
+        );
+        rule.until = Some(date(2024, 3, 1));
+        let dates = rule.occurrences_between(date(2024, 1, 1), date(2024, 5, 1));
+        assert_eq!(dates, vec![date(2024, 1, 31), date(2024, 2, 29)]);
+    }
+
+    #[test]
+    fn test_fifth_weekday_with_exceptions_and_until() {
+        let mut rule = RecurrenceRule::new(
+            Recurrence::MonthlyByWeekday {
+                interval: 1,
+                nth: 5,
+                weekday: Weekday::Fri,
+            },
+            date(2024, 1, 1),
+        );
+        rule.until = Some(date(2024, 10, 1));
+        rule.exceptions = vec![date(2024, 5, 31)];
+
+        // Only months with five Fridays; November's is past `until`
+        let dates = rule.occurrences_between(date(2024, 1, 1), date(2025, 1, 1));
+        assert_eq!(dates, vec![date(2024, 3, 29), date(2024, 8, 30)]);
+        assert_eq!(rule.describe(), "Fifth Fri of the month until 2024-10-01");
+
+        rule.pattern = Recurrence::MonthlyByWeekday {
+            interval: 1,
+            nth: -1,
+            weekday: Weekday::Fri,
+        };
+        let dates = rule.occurrences_between(date(2024, 1, 1), date(2024, 3, 1));
+        assert_eq!(dates, vec![date(2024, 1, 26), date(2024, 2, 23)]);
+        assert_eq!(rule.describe(), "Last Fri of the month until 2024-10-01");
+    }
+}
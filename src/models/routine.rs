@@ -0,0 +1,120 @@
+use chrono::{Duration, NaiveTime, Weekday};
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+pub const ALL_WEEKDAYS: u8 = 0b0111_1111;
+
+// A block lasts at most a day, so it never reaches past the day after it starts
+pub const MAX_DURATION_MINUTES: u32 = 24 * 60;
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TimeBlock {
+    pub id: Uuid,
+    pub title: String,
+    pub start: NaiveTime,
+    pub duration_minutes: u32,
+    pub color: String,
+    // Bit 0 is Monday, bit 6 is Sunday
+    pub weekdays: u8,
+}
+
+impl TimeBlock {
+    pub fn new(title: String, start: NaiveTime, duration_minutes: u32, color: String) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            title,
+            start,
+            duration_minutes,
+            color,
+            weekdays: ALL_WEEKDAYS,
+        }
+    }
+
+    pub fn end(&self) -> NaiveTime {
+        self.start + Duration::minutes(self.duration_minutes as i64)
+    }
+
+    pub fn is_active_on(&self, weekday: Weekday) -> bool {
+        self.weekdays & weekday_bit(weekday) != 0
+    }
+
+    pub fn set_active_on(&mut self, weekday: Weekday, active: bool) {
+        if active {
+            self.weekdays |= weekday_bit(weekday);
+        } else {
+            self.weekdays &= !weekday_bit(weekday);
+        }
+    }
+
+    // Minutes since midnight, used to lay blocks out on the day schedule
+    pub fn start_minute(&self) -> u32 {
+        self.start
+            .signed_duration_since(NaiveTime::MIN)
+            .num_minutes() as u32
+    }
+
+    // Minutes since midnight of the start day, past 24 * 60 when the block runs
+    // into the next day. Longer durations from a hand-edited file are cut to a day.
+    pub fn end_minute(&self) -> u32 {
+        self.start_minute() + self.duration_minutes.min(MAX_DURATION_MINUTES)
+    }
+}
+
+pub fn weekday_bit(weekday: Weekday) -> u8 {
+    1 << weekday.num_days_from_monday()
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct RoutineData {
+    pub name: String,
+    pub color: String,
+    #[serde(default)]
+    pub blocks: Vec<TimeBlock>,
+}
+
+impl RoutineData {
+    pub fn sort_blocks(&mut self) {
+        self.blocks.sort_by_key(|b| b.start);
+    }
+
+    pub fn blocks_for_day(&self, weekday: Weekday) -> Vec<TimeBlock> {
+        self.blocks
+            .iter()
+            .filter(|b| b.is_active_on(weekday))
+            .cloned()
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_weekday_mask() {
+        let start = NaiveTime::from_hms_opt(7, 0, 0).unwrap();
+        let mut block = TimeBlock::new("Gym".to_string(), start, 60, "#ff0000".to_string());
+        assert!(block.is_active_on(Weekday::Sun));
+
+        block.set_active_on(Weekday::Sun, false);
+        assert!(!block.is_active_on(Weekday::Sun));
+        assert!(block.is_active_on(Weekday::Mon));
+        assert_eq!(block.weekdays, 0b0011_1111);
+    }
+
+    #[test]
+    fn test_block_end_wraps_midnight() {
+        let start = NaiveTime::from_hms_opt(23, 30, 0).unwrap();
+        let block = TimeBlock::new("Sleep".to_string(), start, 480, "#000000".to_string());
+        assert_eq!(block.end(), NaiveTime::from_hms_opt(7, 30, 0).unwrap());
+        assert_eq!(block.start_minute(), 23 * 60 + 30);
+        assert_eq!(block.end_minute(), 23 * 60 + 30 + 480);
+    }
+
+    #[test]
+    fn test_block_end_stays_within_the_next_day() {
+        let start = NaiveTime::from_hms_opt(23, 0, 0).unwrap();
+        let block = TimeBlock::new("Trip".to_string(), start, 3000, "#000000".to_string());
+        assert_eq!(block.end_minute(), 47 * 60);
+    }
+}
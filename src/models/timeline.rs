@@ -1,3 +1,4 @@
+use crate::utils::calendar::{self, Span};
 use chrono::NaiveDate;
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
@@ -25,13 +26,153 @@ pub struct MyLifeApp {
     pub temp_start_date: String,
     pub data_folder: String,
     pub screenshot_data: Option<Vec<u8>>,
+    // Cells zoomed into from the whole-life grid, outermost first
+    #[serde(skip)]
+    pub zoom: Vec<ZoomFrame>,
+    // The grid to go back to when leaving the event view
+    #[serde(skip)]
+    pub return_view: Option<String>,
+}
+
+// How much time one cell of the timeline grid covers, coarsest first
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ZoomLevel {
+    Decades,
+    Years,
+    Months,
+    Weeks,
+    Days,
+}
+
+impl ZoomLevel {
+    // The levels the whole life can be shown at
+    pub const ROOTS: [ZoomLevel; 4] = [
+        ZoomLevel::Decades,
+        ZoomLevel::Years,
+        ZoomLevel::Months,
+        ZoomLevel::Weeks,
+    ];
+
+    // The level a cell of this one opens into
+    pub fn finer(self) -> Option<ZoomLevel> {
+        match self {
+            ZoomLevel::Decades => Some(ZoomLevel::Years),
+            ZoomLevel::Years => Some(ZoomLevel::Months),
+            ZoomLevel::Months => Some(ZoomLevel::Weeks),
+            ZoomLevel::Weeks => Some(ZoomLevel::Days),
+            ZoomLevel::Days => None,
+        }
+    }
+
+    pub fn label(self) -> &'static str {
+        match self {
+            ZoomLevel::Decades => "Decades",
+            ZoomLevel::Years => "Years",
+            ZoomLevel::Months => "Months",
+            ZoomLevel::Weeks => "Weeks",
+            ZoomLevel::Days => "Days",
+        }
+    }
+
+    // The `MyLifeApp::view` showing the whole life at this level. The month grid
+    // keeps its original "Lifetime" name.
+    pub fn view(self) -> &'static str {
+        match self {
+            ZoomLevel::Months => "Lifetime",
+            level => level.label(),
+        }
+    }
+
+    pub fn for_view(view: &str) -> Option<ZoomLevel> {
+        Self::ROOTS.into_iter().find(|level| level.view() == view)
+    }
+}
+
+// A cell that was zoomed into, shown as a grid of `level` cells
+#[derive(Debug, Clone, PartialEq)]
+pub struct ZoomFrame {
+    pub level: ZoomLevel,
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub label: String,
+}
+
+impl MyLifeApp {
+    // The whole life at one of `ZoomLevel::ROOTS`, possibly zoomed in
+    pub fn is_grid_view(&self) -> bool {
+        ZoomLevel::for_view(&self.view).is_some()
+    }
+
+    // The grid, or the periods stacked by track
+    pub fn shows_periods(&self) -> bool {
+        self.is_grid_view() || self.view == "Tracks"
+    }
+
+    pub fn show_tracks(&mut self) {
+        self.zoom.clear();
+        self.return_view = None;
+        self.view = "Tracks".to_string();
+    }
+
+    // The level of the cells on screen, `None` outside the grid
+    pub fn zoom_level(&self) -> Option<ZoomLevel> {
+        let root = ZoomLevel::for_view(&self.view)?;
+        Some(self.zoom.last().map_or(root, |frame| frame.level))
+    }
+
+    pub fn set_root_level(&mut self, level: ZoomLevel) {
+        self.zoom.clear();
+        self.return_view = None;
+        self.view = level.view().to_string();
+    }
+
+    pub fn zoom_in(&mut self, frame: ZoomFrame) {
+        self.zoom.push(frame);
+    }
+
+    // Keeps the first `depth` frames; 0 goes back to the whole life
+    pub fn zoom_to(&mut self, depth: usize) {
+        self.close_period();
+        self.zoom.truncate(depth);
+    }
+
+    // One step back: out of the event view, or out of the innermost frame
+    pub fn zoom_out(&mut self) {
+        if self.view == "EventView" {
+            self.close_period();
+        } else {
+            self.zoom.pop();
+        }
+    }
+
+    pub fn open_period(&mut self, period_id: Uuid) {
+        if self.shows_periods() {
+            self.return_view = Some(self.view.clone());
+        }
+        self.view = "EventView".to_string();
+        self.selected_life_period = Some(period_id);
+    }
+
+    pub fn close_period(&mut self) {
+        if self.view == "EventView" {
+            self.view = self
+                .return_view
+                .take()
+                .unwrap_or_else(|| ZoomLevel::Months.view().to_string());
+        }
+    }
 }
 
 #[derive(PartialEq, Clone)]
 pub struct CellData {
     pub color: String,
-    pub period: Option<LifePeriod>,
+    // One per track the cell falls in a period on, the main track first
+    pub periods: Vec<LifePeriod>,
     pub date: NaiveDate,
+    pub end: NaiveDate,
+    pub label: String,
+    // Set in the week and day grids when an event starts in the cell
+    pub event_color: Option<String>,
 }
 
 #[derive(Serialize, Deserialize, Clone, Default, Debug)]
@@ -40,6 +181,9 @@ pub struct Yaml {
     pub date_of_birth: String,
     pub life_expectancy: u32,
     pub life_periods: Vec<LifePeriod>,
+    // Routine names from before routines had their own store. The timeline manager
+    // moves them there once this is the timeline in use, then saves without them.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
     pub routines: Option<Vec<Routine>>,
 }
 
@@ -54,6 +198,13 @@ pub struct Routine {
 pub struct LifePeriod {
     pub name: String,
     pub start: String,
+    // The last month of the period, `YYYY-MM`. Without it the period runs until the
+    // next one on its track starts.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+    // Periods on different tracks can overlap, like a job during university
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub track: Option<String>,
     pub color: String,
     #[serde(default)]
     pub events: Vec<LifePeriodEvent>,
@@ -61,6 +212,69 @@ pub struct LifePeriod {
     pub id: Option<Uuid>,
 }
 
+// The track of periods that don't name one
+pub const MAIN_TRACK: &str = "Main";
+
+impl LifePeriod {
+    pub fn track_name(&self) -> &str {
+        self.track
+            .as_deref()
+            .map(str::trim)
+            .filter(|track| !track.is_empty())
+            .unwrap_or(MAIN_TRACK)
+    }
+
+    // The day after the last month, for periods that set an `end`
+    fn end_date(&self) -> Option<NaiveDate> {
+        self.end
+            .as_deref()
+            .and_then(calendar::month_start)
+            .map(|last_month| calendar::nth_month(last_month, 1))
+    }
+}
+
+impl Yaml {
+    // Every track in use, the main one first and the rest as they first appear
+    pub fn tracks(&self) -> Vec<&str> {
+        let mut tracks = vec![MAIN_TRACK];
+        for period in &self.life_periods {
+            if !tracks.contains(&period.track_name()) {
+                tracks.push(period.track_name());
+            }
+        }
+        tracks
+    }
+
+    // The periods of each track, laid out with `calendar::bounded_spans`
+    pub fn period_tracks(&self, last_end: NaiveDate) -> Vec<(&str, Vec<Span<'_, LifePeriod>>)> {
+        self.tracks()
+            .into_iter()
+            .map(|track| {
+                let spans = calendar::bounded_spans(
+                    &self.life_periods,
+                    |period| {
+                        (period.track_name() == track)
+                            .then(|| calendar::month_start(&period.start))
+                            .flatten()
+                    },
+                    LifePeriod::end_date,
+                    last_end,
+                );
+                (track, spans)
+            })
+            .collect()
+    }
+
+    // When the period with `id` starts and ends, on whichever track it's on
+    pub fn period_span(&self, id: Uuid, last_end: NaiveDate) -> Option<(NaiveDate, NaiveDate)> {
+        self.period_tracks(last_end)
+            .into_iter()
+            .flat_map(|(_, spans)| spans)
+            .find(|span| span.item.id == Some(id))
+            .map(|span| (span.start, span.end))
+    }
+}
+
 #[derive(Serialize, Deserialize, PartialEq, Clone, Debug)]
 pub struct LifePeriodEvent {
     pub name: String,
@@ -76,4 +290,99 @@ pub struct LegendItem {
     pub start: String,
     pub color: String,
     pub is_event: bool,
+    // Only used for periods
+    #[serde(default)]
+    pub end: Option<String>,
+    #[serde(default)]
+    pub track: Option<String>,
+}
+
+impl LegendItem {
+    // The start, or the start and end for periods that have one
+    pub fn dates(&self) -> String {
+        match &self.end {
+            Some(end) => format!("{} – {}", self.start, end),
+            None => self.start.clone(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn frame(level: ZoomLevel, label: &str) -> ZoomFrame {
+        let date = NaiveDate::from_ymd_opt(2000, 1, 1).unwrap();
+        ZoomFrame {
+            level,
+            start: date,
+            end: date,
+            label: label.to_string(),
+        }
+    }
+
+    #[test]
+    fn zooming_out_retraces_the_way_in() {
+        let mut app = MyLifeApp {
+            view: "Lifetime".to_string(),
+            ..Default::default()
+        };
+        app.set_root_level(ZoomLevel::Decades);
+        assert_eq!(app.view, "Decades");
+        app.zoom_in(frame(ZoomLevel::Years, "Ages 10–19"));
+        app.zoom_in(frame(ZoomLevel::Months, "Age 12"));
+        assert_eq!(app.zoom_level(), Some(ZoomLevel::Months));
+
+        let period = Uuid::new_v4();
+        app.open_period(period);
+        assert_eq!(app.zoom_level(), None);
+        app.zoom_out();
+        assert_eq!(app.view, "Decades");
+        assert_eq!(app.zoom.len(), 2);
+
+        app.zoom_out();
+        assert_eq!(app.zoom_level(), Some(ZoomLevel::Years));
+        app.open_period(period);
+        app.zoom_to(0);
+        assert_eq!(app.view, "Decades");
+        assert!(app.zoom.is_empty());
+    }
+
+    #[test]
+    fn periods_end_early_only_on_their_own_track() {
+        let yaml: Yaml = serde_yaml::from_str(
+            "
+name: Test
+date_of_birth: 2000-01
+life_expectancy: 80
+life_periods:
+- { name: School, start: 2006-09, end: 2018-06, color: '#111111' }
+- { name: Work, start: 2019-01, color: '#222222' }
+- { name: Berlin, start: 2016-03, end: 2030-12, track: Home, color: '#333333' }
+- { name: Munich, start: 2021-01, track: Home, color: '#444444' }
+",
+        )
+        .unwrap();
+        let today = calendar::day("2025-01-01").unwrap();
+        let tracks = yaml.period_tracks(today);
+        let names: Vec<&str> = tracks.iter().map(|(track, _)| *track).collect();
+        assert_eq!(names, vec!["Main", "Home"]);
+
+        let (_, main) = &tracks[0];
+        // A gap between school and work
+        assert_eq!(main[0].end, calendar::day("2018-07-01").unwrap());
+        assert_eq!(main[1].end, today);
+        // Berlin ends when Munich starts, before its own end
+        let (_, home) = &tracks[1];
+        assert_eq!(home[0].end, calendar::day("2021-01-01").unwrap());
+        assert_eq!(
+            calendar::dominant_span(
+                home,
+                calendar::day("2017-05-01").unwrap(),
+                calendar::day("2017-06-01").unwrap()
+            )
+            .map(|span| span.item.name.as_str()),
+            Some("Berlin")
+        );
+    }
 }
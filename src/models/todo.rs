@@ -1,4 +1,5 @@
-use chrono::NaiveDateTime;
+use crate::models::recurrence::RecurrenceRule;
+use chrono::{NaiveDate, NaiveDateTime};
 use serde::{Deserialize, Serialize};
 use uuid::Uuid;
 
@@ -6,19 +7,73 @@ use uuid::Uuid;
 pub struct Todo {
     pub id: Uuid,
     pub content: String,
-    pub day: String,
+    pub date: NaiveDate,
     pub created_at: NaiveDateTime,
     pub position: i32,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub recurrence: Option<RecurrenceRule>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub completed_at: Option<NaiveDateTime>,
+    // Set on todos created by completing one occurrence of a recurring todo
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub series_id: Option<Uuid>,
+    // How many times the todo was moved forward because it was left unfinished
+    #[serde(default, skip_serializing_if = "is_zero")]
+    pub carried_count: u32,
+}
+
+fn is_zero(value: &u32) -> bool {
+    *value == 0
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum CarryOverPolicy {
+    CarryToToday,
+    CarryToSameWeekday,
+    #[default]
+    Leave,
+}
+
+impl CarryOverPolicy {
+    pub fn to_string(&self) -> String {
+        match self {
+            CarryOverPolicy::CarryToToday => "today",
+            CarryOverPolicy::CarryToSameWeekday => "same_weekday",
+            CarryOverPolicy::Leave => "leave",
+        }
+        .to_string()
+    }
+
+    pub fn from_string(s: &str) -> Self {
+        match s {
+            "today" => CarryOverPolicy::CarryToToday,
+            "same_weekday" => CarryOverPolicy::CarryToSameWeekday,
+            _ => CarryOverPolicy::Leave,
+        }
+    }
 }
 
 impl Todo {
-    pub fn new(content: String, day: String) -> Self {
+    pub fn new(content: String, date: NaiveDate) -> Self {
         Self {
             id: Uuid::new_v4(),
             content,
-            day,
+            date,
             created_at: chrono::Local::now().naive_local(),
             position: 0,
+            recurrence: None,
+            completed_at: None,
+            series_id: None,
+            carried_count: 0,
         }
     }
+
+    pub fn is_recurring(&self) -> bool {
+        self.recurrence.is_some()
+    }
+
+    pub fn is_completed(&self) -> bool {
+        self.completed_at.is_some()
+    }
 }
@@ -129,6 +129,8 @@ fn initialize_app_state() -> MyLifeApp {
         temp_start_date: String::new(),
         data_folder: "data".to_string(),
         screenshot_data: None,
+        zoom: Vec::new(),
+        return_view: None,
     }
 }
 
@@ -0,0 +1,239 @@
+// storage/backend.rs
+use super::crypto::read_document;
+use super::merge::Mergeable;
+use super::schema::Versioned;
+use super::sqlite::{SqliteModel, SqliteStorage};
+use super::storage_manager::StorageResult;
+use super::{
+    BackupInfo, Conflict, ConflictResolution, Corruption, DataFormat, DiffEntry, StorageConfig,
+    StorageEvent, StorageManager,
+};
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Serialize};
+use std::path::{Path, PathBuf};
+use tokio::sync::broadcast;
+use tracing::{info, warn};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageBackend {
+    // One JSON/YAML document per store, rewritten on every save
+    Files,
+    // One SQLite database per store, updated row by row
+    Sqlite,
+}
+
+impl StorageBackend {
+    // `habits.json` -> `habits.db` when the data lives in SQLite
+    pub fn data_path(&self, file_path: &Path) -> PathBuf {
+        match self {
+            StorageBackend::Files => file_path.to_path_buf(),
+            StorageBackend::Sqlite => file_path.with_extension("db"),
+        }
+    }
+}
+
+// Chosen once per run with `MYQUEST_STORAGE=sqlite`; files are the default
+static STORAGE_BACKEND: Lazy<StorageBackend> =
+    Lazy::new(|| match std::env::var("MYQUEST_STORAGE") {
+        Ok(value) if value.eq_ignore_ascii_case("sqlite") => StorageBackend::Sqlite,
+        _ => StorageBackend::Files,
+    });
+
+pub fn get_storage_backend() -> StorageBackend {
+    *STORAGE_BACKEND
+}
+
+// A store kept in whichever backend is active. Both expose the same API, so
+// managers don't need to know which one they are talking to.
+pub enum Storage<T, F: DataFormat> {
+    File(StorageManager<T, F>),
+    Sqlite(SqliteStorage<T>),
+}
+
+impl<T, F> Storage<T, F>
+where
+    T: SqliteModel
+        + Versioned
+        + Mergeable
+        + Default
+        + Serialize
+        + DeserializeOwned
+        + Clone
+        + Send
+        + Sync
+        + 'static,
+    F: DataFormat + 'static,
+{
+    pub fn new(file_path: PathBuf) -> StorageResult<Self> {
+        Self::with_config_and_default(file_path, StorageConfig::default(), None)
+    }
+
+    // `file_path` is the document path; with SQLite the database sits next to it and
+    // imports the document the first time it is created
+    pub fn with_config_and_default(
+        file_path: PathBuf,
+        config: StorageConfig,
+        default_data: Option<T>,
+    ) -> StorageResult<Self> {
+        match get_storage_backend() {
+            StorageBackend::Files => Ok(Self::File(StorageManager::with_config_and_default(
+                file_path,
+                config,
+                default_data,
+            )?)),
+            StorageBackend::Sqlite => {
+                let db_path = StorageBackend::Sqlite.data_path(&file_path);
+                let import = if !db_path.exists() && file_path.exists() {
+                    match Self::read_document(&file_path) {
+                        Ok(data) => {
+                            info!("Importing {:?} into {:?}", file_path, db_path);
+                            Some(data)
+                        }
+                        Err(e) => {
+                            warn!("Not importing unreadable {:?}: {}", file_path, e);
+                            None
+                        }
+                    }
+                } else {
+                    None
+                };
+                Ok(Self::Sqlite(SqliteStorage::open(
+                    db_path,
+                    config,
+                    default_data,
+                    import,
+                )?))
+            }
+        }
+    }
+
+    fn read_document(file_path: &Path) -> Result<T, String> {
+        let content = std::fs::read_to_string(file_path).map_err(|e| e.to_string())?;
+        read_document::<T, F>(&content)
+    }
+
+    pub fn file_path(&self) -> &PathBuf {
+        match self {
+            Storage::File(storage) => storage.file_path(),
+            Storage::Sqlite(storage) => storage.file_path(),
+        }
+    }
+
+    pub async fn read<R, Func>(&self, f: Func) -> StorageResult<R>
+    where
+        Func: FnOnce(&T) -> R,
+    {
+        match self {
+            Storage::File(storage) => storage.read(f).await,
+            Storage::Sqlite(storage) => storage.read(f).await,
+        }
+    }
+
+    pub async fn write<R, Func>(&self, f: Func) -> StorageResult<R>
+    where
+        Func: FnOnce(&mut T) -> R,
+    {
+        match self {
+            Storage::File(storage) => storage.write(f).await,
+            Storage::Sqlite(storage) => storage.write(f).await,
+        }
+    }
+
+    pub async fn get_data(&self) -> StorageResult<T> {
+        match self {
+            Storage::File(storage) => storage.get_data().await,
+            Storage::Sqlite(storage) => storage.get_data().await,
+        }
+    }
+
+    pub async fn flush(&self) -> StorageResult<()> {
+        match self {
+            Storage::File(storage) => storage.flush().await,
+            Storage::Sqlite(storage) => storage.flush().await,
+        }
+    }
+
+    pub async fn force_save(&self) -> StorageResult<()> {
+        match self {
+            Storage::File(storage) => storage.force_save().await,
+            Storage::Sqlite(storage) => storage.force_save().await,
+        }
+    }
+
+    pub async fn reload(&self) -> StorageResult<()> {
+        match self {
+            Storage::File(storage) => storage.reload().await,
+            Storage::Sqlite(storage) => storage.reload().await,
+        }
+    }
+
+    pub async fn corruption(&self) -> Option<Corruption> {
+        match self {
+            Storage::File(storage) => storage.corruption().await,
+            Storage::Sqlite(storage) => storage.corruption().await,
+        }
+    }
+
+    pub fn newer_version(&self) -> Option<u32> {
+        match self {
+            Storage::File(storage) => storage.newer_version(),
+            Storage::Sqlite(storage) => storage.newer_version(),
+        }
+    }
+
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        match self {
+            Storage::File(storage) => storage.subscribe(),
+            Storage::Sqlite(storage) => storage.subscribe(),
+        }
+    }
+
+    pub async fn conflict(&self) -> Option<Conflict> {
+        match self {
+            Storage::File(storage) => storage.conflict().await,
+            Storage::Sqlite(storage) => storage.conflict().await,
+        }
+    }
+
+    pub async fn resolve_conflict(&self, resolution: ConflictResolution) -> StorageResult<()> {
+        match self {
+            Storage::File(storage) => storage.resolve_conflict(resolution).await,
+            Storage::Sqlite(storage) => storage.resolve_conflict(resolution).await,
+        }
+    }
+
+    pub async fn restore_from_backup(&self) -> StorageResult<PathBuf> {
+        match self {
+            Storage::File(storage) => storage.restore_from_backup().await,
+            Storage::Sqlite(storage) => storage.restore_from_backup().await,
+        }
+    }
+
+    pub async fn start_fresh(&self) -> StorageResult<()> {
+        match self {
+            Storage::File(storage) => storage.start_fresh().await,
+            Storage::Sqlite(storage) => storage.start_fresh().await,
+        }
+    }
+
+    pub async fn list_backups(&self) -> StorageResult<Vec<BackupInfo>> {
+        match self {
+            Storage::File(storage) => storage.list_backups().await,
+            Storage::Sqlite(storage) => storage.list_backups().await,
+        }
+    }
+
+    pub async fn diff_backup(&self, index: usize) -> StorageResult<Vec<DiffEntry>> {
+        match self {
+            Storage::File(storage) => storage.diff_backup(index).await,
+            Storage::Sqlite(storage) => storage.diff_backup(index).await,
+        }
+    }
+
+    pub async fn restore_backup(&self, index: usize) -> StorageResult<PathBuf> {
+        match self {
+            Storage::File(storage) => storage.restore_backup(index).await,
+            Storage::Sqlite(storage) => storage.restore_backup(index).await,
+        }
+    }
+}
@@ -0,0 +1,128 @@
+// storage/backups.rs
+use chrono::{DateTime, Datelike, Local, NaiveDate};
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum BackupKind {
+    // `.backupN`, rotated on every save; 1 is the newest
+    Rotated(usize),
+    // First state of a day, kept by daily/weekly retention
+    Snapshot(NaiveDate),
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct BackupInfo {
+    pub path: PathBuf,
+    pub kind: BackupKind,
+    pub modified: DateTime<Local>,
+    pub size: u64,
+    // Parsed with the storage's own type, then flattened to JSON for display and diffing
+    pub preview: Result<serde_json::Value, String>,
+}
+
+impl BackupInfo {
+    pub fn label(&self) -> String {
+        match &self.kind {
+            BackupKind::Rotated(n) => format!("Recent save #{}", n),
+            BackupKind::Snapshot(date) => format!("Snapshot {}", date.format("%Y-%m-%d")),
+        }
+    }
+
+    // One line overview such as "habits: 12, collapsed_categories: 0"
+    pub fn summary(&self) -> String {
+        match &self.preview {
+            Ok(serde_json::Value::Object(map)) => map
+                .iter()
+                .map(|(key, value)| match value {
+                    serde_json::Value::Array(items) => format!("{}: {}", key, items.len()),
+                    serde_json::Value::Object(items) => format!("{}: {}", key, items.len()),
+                    _ => key.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(", "),
+            Ok(_) => String::new(),
+            Err(e) => format!("Unreadable: {}", e),
+        }
+    }
+}
+
+// `habits.json` -> `habits.backup3`
+pub fn backup_path(file_path: &Path, index: usize) -> PathBuf {
+    file_path.with_extension(format!("backup{}", index))
+}
+
+// `habits.json` -> `habits.json.snapshot-2024-03-01`
+pub fn snapshot_path(file_path: &Path, date: NaiveDate) -> PathBuf {
+    PathBuf::from(format!(
+        "{}.snapshot-{}",
+        file_path.display(),
+        date.format("%Y-%m-%d")
+    ))
+}
+
+pub fn list_snapshots(file_path: &Path) -> Vec<(NaiveDate, PathBuf)> {
+    let (Some(dir), Some(name)) = (file_path.parent(), file_path.file_name()) else {
+        return Vec::new();
+    };
+    let prefix = format!("{}.snapshot-", name.to_string_lossy());
+    let Ok(entries) = std::fs::read_dir(dir) else {
+        return Vec::new();
+    };
+
+    let mut snapshots: Vec<(NaiveDate, PathBuf)> = entries
+        .flatten()
+        .filter_map(|entry| {
+            let entry_name = entry.file_name().to_string_lossy().to_string();
+            let date = entry_name.strip_prefix(&prefix)?;
+            let date = NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()?;
+            Some((date, entry.path()))
+        })
+        .collect();
+    snapshots.sort_by(|a, b| b.0.cmp(&a.0));
+    snapshots
+}
+
+// The newest `daily` dates are kept as-is; older ones keep only the newest
+// date of each ISO week, for `weekly` weeks
+pub fn snapshots_to_keep(dates: &[NaiveDate], daily: usize, weekly: usize) -> HashSet<NaiveDate> {
+    let mut sorted = dates.to_vec();
+    sorted.sort_by(|a, b| b.cmp(a));
+    sorted.dedup();
+
+    let mut keep: HashSet<NaiveDate> = sorted.iter().take(daily).copied().collect();
+    let mut weeks = HashSet::new();
+    for date in sorted.iter().skip(daily) {
+        let week = (date.iso_week().year(), date.iso_week().week());
+        if weeks.len() >= weekly && !weeks.contains(&week) {
+            break;
+        }
+        if weeks.insert(week) {
+            keep.insert(*date);
+        }
+    }
+    keep
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+
+    #[test]
+    fn retention_keeps_recent_days_then_one_per_week() {
+        let today = NaiveDate::from_ymd_opt(2024, 3, 31).unwrap(); // Sunday
+        let dates: Vec<NaiveDate> = (0..60).map(|i| today - Duration::days(i)).collect();
+
+        let keep = snapshots_to_keep(&dates, 7, 3);
+
+        // Mar 25–31 as dailies, then the Sundays of the three weeks before
+        assert_eq!(keep.len(), 10);
+        assert!(keep.contains(&NaiveDate::from_ymd_opt(2024, 3, 25).unwrap()));
+        assert!(keep.contains(&NaiveDate::from_ymd_opt(2024, 3, 24).unwrap()));
+        assert!(keep.contains(&NaiveDate::from_ymd_opt(2024, 3, 17).unwrap()));
+        assert!(keep.contains(&NaiveDate::from_ymd_opt(2024, 3, 10).unwrap()));
+        assert!(!keep.contains(&NaiveDate::from_ymd_opt(2024, 3, 23).unwrap()));
+        assert!(!keep.contains(&NaiveDate::from_ymd_opt(2024, 3, 3).unwrap()));
+    }
+}
@@ -1,10 +1,18 @@
 // storage/config.rs
+use std::time::Duration;
+
 #[derive(Debug, Clone)]
 pub struct StorageConfig {
     pub create_dirs: bool,
     pub backup_on_save: bool,
     pub max_backups: usize,
     pub extension: String,
+    // Writes within this window after the first pending change are saved together
+    pub save_debounce: Duration,
+    // Start-of-day snapshots kept for this many recent days...
+    pub daily_snapshots: usize,
+    // ...and after that, one per week for this many weeks
+    pub weekly_snapshots: usize,
 }
 
 impl Default for StorageConfig {
@@ -14,6 +22,9 @@ impl Default for StorageConfig {
             backup_on_save: true,
             max_backups: 5,
             extension: String::from("json"),
+            save_debounce: Duration::from_millis(250),
+            daily_snapshots: 7,
+            weekly_snapshots: 8,
         }
     }
 }
@@ -0,0 +1,460 @@
+// storage/crypto.rs
+use super::backend::{get_storage_backend, StorageBackend};
+use super::backups::{backup_path, list_snapshots};
+use super::paths::get_path_manager;
+use super::schema::{decode, encode, Versioned};
+use super::storage_manager::write_atomic_sync;
+use super::DataFormat;
+use argon2::{Algorithm, Argon2, Params, Version};
+use base64::{engine::general_purpose::STANDARD, Engine};
+use chacha20poly1305::aead::rand_core::RngCore;
+use chacha20poly1305::aead::{Aead, AeadCore, KeyInit, OsRng};
+use chacha20poly1305::{Key, XChaCha20Poly1305, XNonce};
+use once_cell::sync::Lazy;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::sync::{PoisonError, RwLock};
+use tracing::{error, info, warn};
+
+// First line of every encrypted data file. Files without it are read as plain text,
+// so data written before encryption was turned on stays readable.
+const SEALED_HEADER: &str = "myquest-encrypted v1";
+const KEY_FILE_NAME: &str = "encryption.json";
+const NONCE_LEN: usize = 24;
+const SALT_LEN: usize = 16;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum EncryptionState {
+    Off,
+    // Encryption is on, but the passphrase hasn't been entered yet
+    Locked,
+    Unlocked,
+}
+
+// The key data files are encrypted with. It is random and never changes, so a new
+// passphrase only re-wraps it and the files themselves are left alone.
+#[derive(Clone)]
+struct DataKey(Key);
+
+impl DataKey {
+    fn generate() -> Self {
+        Self(XChaCha20Poly1305::generate_key(&mut OsRng))
+    }
+
+    // Base64 of the nonce followed by the ciphertext
+    fn encrypt(&self, plaintext: &[u8]) -> Result<String, String> {
+        let nonce = XChaCha20Poly1305::generate_nonce(&mut OsRng);
+        let ciphertext = XChaCha20Poly1305::new(&self.0)
+            .encrypt(&nonce, plaintext)
+            .map_err(|e| e.to_string())?;
+        let mut sealed = nonce.to_vec();
+        sealed.extend(ciphertext);
+        Ok(STANDARD.encode(sealed))
+    }
+
+    fn decrypt(&self, encoded: &str) -> Result<Vec<u8>, String> {
+        let bytes = STANDARD.decode(encoded.trim()).map_err(|e| e.to_string())?;
+        if bytes.len() < NONCE_LEN {
+            return Err("Encrypted data is truncated".to_string());
+        }
+        let (nonce, ciphertext) = bytes.split_at(NONCE_LEN);
+        XChaCha20Poly1305::new(&self.0)
+            .decrypt(XNonce::from_slice(nonce), ciphertext)
+            .map_err(|_| "Encrypted data could not be decrypted".to_string())
+    }
+
+    fn seal(&self, content: &str) -> Result<String, String> {
+        Ok(format!(
+            "{}\n{}\n",
+            SEALED_HEADER,
+            self.encrypt(content.as_bytes())?
+        ))
+    }
+
+    fn open(&self, content: &str) -> Result<String, String> {
+        let body = content
+            .strip_prefix(SEALED_HEADER)
+            .ok_or_else(|| "Not an encrypted file".to_string())?;
+        String::from_utf8(self.decrypt(body)?).map_err(|e| e.to_string())
+    }
+}
+
+pub fn is_sealed(content: &str) -> bool {
+    content.starts_with(SEALED_HEADER)
+}
+
+// Kept in the data folder, so it travels with the data when that is synced. Holds
+// the data key, encrypted with a key derived from the passphrase.
+#[derive(Serialize, Deserialize)]
+struct KeyFile {
+    salt: String,
+    memory_kib: u32,
+    iterations: u32,
+    lanes: u32,
+    wrapped_key: String,
+}
+
+impl KeyFile {
+    fn wrap(key: &DataKey, passphrase: &str, params: &Params) -> Result<Self, String> {
+        let mut salt = [0u8; SALT_LEN];
+        OsRng.fill_bytes(&mut salt);
+        let key_file = KeyFile {
+            salt: STANDARD.encode(salt),
+            memory_kib: params.m_cost(),
+            iterations: params.t_cost(),
+            lanes: params.p_cost(),
+            wrapped_key: String::new(),
+        };
+        let wrapped_key = key_file.derive(passphrase)?.encrypt(&key.0)?;
+        Ok(KeyFile {
+            wrapped_key,
+            ..key_file
+        })
+    }
+
+    fn derive(&self, passphrase: &str) -> Result<DataKey, String> {
+        let salt = STANDARD.decode(&self.salt).map_err(|e| e.to_string())?;
+        let params = Params::new(self.memory_kib, self.iterations, self.lanes, None)
+            .map_err(|e| e.to_string())?;
+        let mut key = Key::default();
+        Argon2::new(Algorithm::Argon2id, Version::V0x13, params)
+            .hash_password_into(passphrase.as_bytes(), &salt, key.as_mut_slice())
+            .map_err(|e| e.to_string())?;
+        Ok(DataKey(key))
+    }
+
+    fn unwrap_key(&self, passphrase: &str) -> Result<DataKey, String> {
+        let bytes = self
+            .derive(passphrase)?
+            .decrypt(&self.wrapped_key)
+            .map_err(|_| "Wrong passphrase".to_string())?;
+        if bytes.len() != Key::default().len() {
+            return Err("The encryption key file is damaged".to_string());
+        }
+        Ok(DataKey(*Key::from_slice(&bytes)))
+    }
+
+    fn load(path: &Path) -> Result<Self, String> {
+        let content = std::fs::read_to_string(path).map_err(|e| e.to_string())?;
+        serde_json::from_str(&content).map_err(|e| e.to_string())
+    }
+
+    fn save(&self, path: &Path) -> Result<(), String> {
+        let content = serde_json::to_string_pretty(self).map_err(|e| e.to_string())?;
+        write_atomic_sync(path, &content).map_err(|e| e.to_string())
+    }
+}
+
+// `key` opens encrypted files and is kept while encryption is being turned off;
+// `sealing` decides whether saves are encrypted
+#[derive(Default)]
+struct Vault {
+    key: Option<DataKey>,
+    sealing: bool,
+}
+
+static VAULT: Lazy<RwLock<Vault>> = Lazy::new(|| RwLock::new(Vault::default()));
+
+fn with_vault<R>(f: impl FnOnce(&mut Vault) -> R) -> R {
+    f(&mut VAULT.write().unwrap_or_else(PoisonError::into_inner))
+}
+
+fn key_file_path() -> PathBuf {
+    get_path_manager().root_dir().join(KEY_FILE_NAME)
+}
+
+pub fn encryption_state() -> EncryptionState {
+    if with_vault(|vault| vault.sealing) {
+        EncryptionState::Unlocked
+    } else if key_file_path().exists() {
+        EncryptionState::Locked
+    } else {
+        EncryptionState::Off
+    }
+}
+
+// Has to happen before any store is loaded, or its encrypted file can't be read
+pub fn unlock(passphrase: &str) -> Result<(), String> {
+    let key = KeyFile::load(&key_file_path())?.unwrap_key(passphrase)?;
+    with_vault(|vault| {
+        vault.key = Some(key);
+        vault.sealing = true;
+    });
+    info!("Data unlocked");
+    Ok(())
+}
+
+// Encrypts every data file, backups included. Files are written one by one, and
+// plain files keep working, so an interruption leaves nothing unreadable.
+// Returns the number of files encrypted.
+pub fn enable_encryption(passphrase: &str) -> Result<usize, String> {
+    ensure_backend_encrypts(get_storage_backend())?;
+    if encryption_state() != EncryptionState::Off {
+        return Err("Encryption is already turned on".to_string());
+    }
+    let key = DataKey::generate();
+    KeyFile::wrap(&key, passphrase, &Params::default())?.save(&key_file_path())?;
+    with_vault(|vault| {
+        vault.key = Some(key.clone());
+        vault.sealing = true;
+    });
+
+    let count = rewrite_files(&all_data_files(), &|content| {
+        (!is_sealed(content)).then(|| key.seal(content)).transpose()
+    })?;
+    info!("Encryption turned on, {} files encrypted", count);
+    Ok(count)
+}
+
+// Only documents are sealed. SQLite databases would stay plain text while the
+// settings say the data is encrypted, so turning it on is refused there.
+fn ensure_backend_encrypts(backend: StorageBackend) -> Result<(), String> {
+    match backend {
+        StorageBackend::Files => Ok(()),
+        StorageBackend::Sqlite => Err(
+            "Encryption isn't available with SQLite storage, the databases would stay unencrypted. Switch back to file storage to turn it on."
+                .to_string(),
+        ),
+    }
+}
+
+// Only the key file changes; the data key stays the same. `replace_key` swaps
+// the data key too.
+pub fn change_passphrase(current: &str, new: &str) -> Result<(), String> {
+    let path = key_file_path();
+    let key = KeyFile::load(&path)?.unwrap_key(current)?;
+    KeyFile::wrap(&key, new, &Params::default())?.save(&path)?;
+    info!("Passphrase changed");
+    Ok(())
+}
+
+// Encrypts every data file with a new data key, for when the old one may have
+// leaked. The passphrase stays the same. If a file can't be rewritten, the ones
+// done so far are put back under the old key. Returns the number of files
+// re-encrypted.
+pub fn replace_key(passphrase: &str) -> Result<usize, String> {
+    let path = key_file_path();
+    let old = KeyFile::load(&path)?.unwrap_key(passphrase)?;
+    let new = DataKey::generate();
+    let key_file = KeyFile::wrap(&new, passphrase, &Params::default())?;
+    // Saves made while the files are rewritten already use the new key
+    with_vault(|vault| vault.key = Some(new.clone()));
+
+    let files = all_data_files();
+    let rekeyed = rewrite_files(&files, &|content| reseal(&old, &new, content))
+        .and_then(|count| key_file.save(&path).map(|()| count));
+    match rekeyed {
+        Ok(count) => {
+            info!("Encryption key replaced, {} files re-encrypted", count);
+            Ok(count)
+        }
+        Err(e) => {
+            with_vault(|vault| vault.key = Some(old.clone()));
+            if let Err(undo) = rewrite_files(&files, &|content| reseal(&new, &old, content)) {
+                error!("Failed to put files back under the old key: {}", undo);
+            }
+            Err(e)
+        }
+    }
+}
+
+// Content encrypted with `to`, for files that are plain or open with `from`. Files
+// that `from` doesn't open are left alone.
+fn reseal(from: &DataKey, to: &DataKey, content: &str) -> Result<Option<String>, String> {
+    if !is_sealed(content) {
+        return to.seal(content).map(Some);
+    }
+    match from.open(content) {
+        Ok(plain) => to.seal(&plain).map(Some),
+        Err(_) => Ok(None),
+    }
+}
+
+// Decrypts every data file, then removes the key file. Returns the number of
+// files decrypted.
+pub fn disable_encryption(passphrase: &str) -> Result<usize, String> {
+    let path = key_file_path();
+    let key = KeyFile::load(&path)?.unwrap_key(passphrase)?;
+    with_vault(|vault| vault.sealing = false);
+
+    let count = rewrite_files(&all_data_files(), &|content| {
+        is_sealed(content).then(|| key.open(content)).transpose()
+    });
+    let count = match count {
+        Ok(count) => count,
+        Err(e) => {
+            with_vault(|vault| vault.sealing = true);
+            return Err(e);
+        }
+    };
+    std::fs::remove_file(&path).map_err(|e| e.to_string())?;
+    with_vault(|vault| vault.key = None);
+    info!("Encryption turned off, {} files decrypted", count);
+    Ok(count)
+}
+
+// The store files with their rotated backups (`habits.backup1`) and snapshots
+// (`habits.json.snapshot-2024-03-01`). Quarantined files are left as they are.
+fn data_files(store_files: &[PathBuf]) -> Vec<PathBuf> {
+    let mut files = Vec::new();
+    for file in store_files {
+        files.push(file.clone());
+        files.extend(
+            (1..)
+                .map(|index| backup_path(file, index))
+                .take_while(|backup| backup.exists()),
+        );
+        files.extend(list_snapshots(file).into_iter().map(|(_, path)| path));
+    }
+    files
+}
+
+// Runs `rewrite` over the given files, saving the ones it returns new content for.
+// Missing files and symlinks are skipped. Returns how many were rewritten.
+fn rewrite_files(
+    files: &[PathBuf],
+    rewrite: &dyn Fn(&str) -> Result<Option<String>, String>,
+) -> Result<usize, String> {
+    let mut count = 0;
+    for path in files {
+        let is_file = std::fs::symlink_metadata(path).is_ok_and(|meta| meta.is_file());
+        if !is_file {
+            continue;
+        }
+        let Ok(content) = std::fs::read_to_string(path) else {
+            warn!("Skipping unreadable {:?}", path);
+            continue;
+        };
+        if let Some(new_content) =
+            rewrite(&content).map_err(|e| format!("{}: {}", path.display(), e))?
+        {
+            write_atomic_sync(path, &new_content).map_err(|e| e.to_string())?;
+            count += 1;
+        }
+    }
+    Ok(count)
+}
+
+fn all_data_files() -> Vec<PathBuf> {
+    data_files(&get_path_manager().store_files())
+}
+
+// What gets written to a data file: the encoded document, encrypted when
+// encryption is on. Exports go through `encode` and stay readable.
+pub fn write_document<T: Versioned + Serialize, F: DataFormat>(data: &T) -> Result<String, String> {
+    let content = encode::<T, F>(data)?;
+    with_vault(|vault| match (&vault.key, vault.sealing) {
+        (Some(key), true) => key.seal(&content),
+        _ => Ok(content),
+    })
+}
+
+// The document's text, decrypted if it's sealed
+pub(super) fn open_document(content: &str) -> Result<String, String> {
+    if !is_sealed(content) {
+        return Ok(content.to_string());
+    }
+    with_vault(|vault| match &vault.key {
+        Some(key) => key.open(content),
+        None => Err("The file is encrypted and the data hasn't been unlocked".to_string()),
+    })
+}
+
+pub fn read_document<T: Versioned + DeserializeOwned, F: DataFormat>(
+    content: &str,
+) -> Result<T, String> {
+    decode::<T, F>(&open_document(content)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn encryption_is_refused_for_sqlite_storage() {
+        assert!(ensure_backend_encrypts(StorageBackend::Files).is_ok());
+        let error = ensure_backend_encrypts(StorageBackend::Sqlite).unwrap_err();
+        assert!(error.contains("SQLite"));
+    }
+
+    #[test]
+    fn key_file_unlocks_only_with_its_passphrase() {
+        // Far below the real cost, to keep the test quick
+        let params = Params::new(64, 1, 1, None).unwrap();
+        let key = DataKey::generate();
+        let key_file = KeyFile::wrap(&key, "correct horse", &params).unwrap();
+
+        let sealed = key.seal("{\"a\": 1}").unwrap();
+        assert!(is_sealed(&sealed));
+        assert!(!sealed.contains("\"a\""));
+        let unlocked = key_file.unwrap_key("correct horse").unwrap();
+        assert_eq!(unlocked.open(&sealed).unwrap(), "{\"a\": 1}");
+        assert_eq!(
+            key_file.unwrap_key("wrong horse").err().as_deref(),
+            Some("Wrong passphrase")
+        );
+
+        // A new passphrase wraps the same key, so existing files still open
+        let rewrapped = KeyFile::wrap(&unlocked, "battery staple", &params).unwrap();
+        let unlocked = rewrapped.unwrap_key("battery staple").unwrap();
+        assert_eq!(unlocked.open(&sealed).unwrap(), "{\"a\": 1}");
+    }
+
+    #[test]
+    fn data_files_and_backups_are_rewritten() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("habits")).unwrap();
+        let files = [
+            "todos.json",
+            "todos.backup1",
+            "todos.backup2",
+            "todos.json.snapshot-2024-03-01",
+            "habits/habits.json",
+        ];
+        for name in files {
+            std::fs::write(dir.path().join(name), "{}").unwrap();
+        }
+        std::fs::write(dir.path().join("todos.json.corrupt-20240301T120000"), "{").unwrap();
+        std::fs::write(dir.path().join("notes.json"), "{}").unwrap();
+        let elsewhere = tempfile::tempdir().unwrap();
+        let outside = elsewhere.path().join("routines.json");
+        std::fs::write(&outside, "{}").unwrap();
+        #[cfg(unix)]
+        std::os::unix::fs::symlink(&outside, dir.path().join("routines.json")).unwrap();
+        let store_files = [
+            dir.path().join("todos.json"),
+            dir.path().join("habits/habits.json"),
+            dir.path().join("routines.json"),
+        ];
+
+        let key = DataKey::generate();
+        let seal = |content: &str| (!is_sealed(content)).then(|| key.seal(content)).transpose();
+        let data_files = data_files(&store_files);
+        assert_eq!(rewrite_files(&data_files, &seal).unwrap(), files.len());
+        for name in files {
+            let content = std::fs::read_to_string(dir.path().join(name)).unwrap();
+            assert_eq!(key.open(&content).unwrap(), "{}");
+        }
+        // Files the stores don't know about are left alone
+        let corrupt = dir.path().join("todos.json.corrupt-20240301T120000");
+        assert_eq!(std::fs::read_to_string(corrupt).unwrap(), "{");
+        assert_eq!(
+            std::fs::read_to_string(dir.path().join("notes.json")).unwrap(),
+            "{}"
+        );
+        assert_eq!(std::fs::read_to_string(&outside).unwrap(), "{}");
+        // Already encrypted files are left alone
+        assert_eq!(rewrite_files(&data_files, &seal).unwrap(), 0);
+
+        let new_key = DataKey::generate();
+        let rekey = |content: &str| reseal(&key, &new_key, content);
+        assert_eq!(rewrite_files(&data_files, &rekey).unwrap(), files.len());
+        for name in files {
+            let content = std::fs::read_to_string(dir.path().join(name)).unwrap();
+            assert!(key.open(&content).is_err());
+            assert_eq!(new_key.open(&content).unwrap(), "{}");
+        }
+        // Files the old key doesn't open are already done
+        assert_eq!(rewrite_files(&data_files, &rekey).unwrap(), 0);
+    }
+}
@@ -0,0 +1,131 @@
+// storage/diff.rs
+use serde_json::Value;
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum ChangeKind {
+    Added,
+    Removed,
+    Changed,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct DiffEntry {
+    // Dotted path such as `habits.<id>.title` or `life_periods[2].color`
+    pub path: String,
+    pub kind: ChangeKind,
+    pub before: Option<String>,
+    pub after: Option<String>,
+}
+
+pub(super) fn render(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        Value::Array(items) => format!("[{} items]", items.len()),
+        Value::Object(map) => format!("{{{} fields}}", map.len()),
+        other => other.to_string(),
+    }
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+// Changes needed to go from `before` to `after`. Objects are compared key by key
+// and arrays index by index; anything else is a leaf.
+pub fn diff_values(before: &Value, after: &Value) -> Vec<DiffEntry> {
+    let mut entries = Vec::new();
+    diff_into(&mut entries, "", before, after);
+    entries
+}
+
+fn diff_into(entries: &mut Vec<DiffEntry>, path: &str, before: &Value, after: &Value) {
+    match (before, after) {
+        (Value::Object(old), Value::Object(new)) => {
+            for (key, old_value) in old {
+                let child = join(path, key);
+                match new.get(key) {
+                    Some(new_value) => diff_into(entries, &child, old_value, new_value),
+                    None => entries.push(DiffEntry {
+                        path: child,
+                        kind: ChangeKind::Removed,
+                        before: Some(render(old_value)),
+                        after: None,
+                    }),
+                }
+            }
+            for (key, new_value) in new {
+                if !old.contains_key(key) {
+                    entries.push(DiffEntry {
+                        path: join(path, key),
+                        kind: ChangeKind::Added,
+                        before: None,
+                        after: Some(render(new_value)),
+                    });
+                }
+            }
+        }
+        (Value::Array(old), Value::Array(new)) => {
+            for index in 0..old.len().max(new.len()) {
+                let child = format!("{}[{}]", path, index);
+                match (old.get(index), new.get(index)) {
+                    (Some(old_value), Some(new_value)) => {
+                        diff_into(entries, &child, old_value, new_value)
+                    }
+                    (Some(old_value), None) => entries.push(DiffEntry {
+                        path: child,
+                        kind: ChangeKind::Removed,
+                        before: Some(render(old_value)),
+                        after: None,
+                    }),
+                    (None, Some(new_value)) => entries.push(DiffEntry {
+                        path: child,
+                        kind: ChangeKind::Added,
+                        before: None,
+                        after: Some(render(new_value)),
+                    }),
+                    (None, None) => {}
+                }
+            }
+        }
+        _ if before != after => entries.push(DiffEntry {
+            path: path.to_string(),
+            kind: ChangeKind::Changed,
+            before: Some(render(before)),
+            after: Some(render(after)),
+        }),
+        _ => {}
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn nested_changes_are_reported_by_path() {
+        let before =
+            json!({"habits": {"a": {"title": "Read", "days": [1, 2]}, "b": {"title": "Run"}}});
+        let after =
+            json!({"habits": {"a": {"title": "Read more", "days": [1]}, "c": {"title": "Swim"}}});
+
+        let diff = diff_values(&before, &after);
+        let paths: Vec<(&str, &ChangeKind)> =
+            diff.iter().map(|d| (d.path.as_str(), &d.kind)).collect();
+
+        assert_eq!(
+            paths,
+            vec![
+                ("habits.a.days[1]", &ChangeKind::Removed),
+                ("habits.a.title", &ChangeKind::Changed),
+                ("habits.b", &ChangeKind::Removed),
+                ("habits.c", &ChangeKind::Added),
+            ]
+        );
+        assert_eq!(diff[1].after.as_deref(), Some("Read more"));
+    }
+}
@@ -0,0 +1,337 @@
+// storage/merge.rs
+use super::diff::render;
+use serde::Serialize;
+use serde_json::Value;
+use std::collections::{BTreeMap, BTreeSet};
+
+// A field that both sides changed, each in its own way. `None` means that side
+// deleted it.
+#[derive(Debug, Clone, PartialEq)]
+pub struct MergeConflict {
+    pub path: String,
+    pub mine: Option<String>,
+    pub theirs: Option<String>,
+}
+
+pub struct Merged<T> {
+    pub data: T,
+    pub conflicts: Vec<MergeConflict>,
+}
+
+// A store that can combine two versions edited from the same starting point
+pub trait Mergeable: Clone + Serialize {
+    // Everything changed on only one side is taken over. Where both sides changed
+    // the same field, `mine` wins and the clash is recorded. The default treats the
+    // whole document as a single field.
+    fn merge(base: &Self, mine: &Self, theirs: &Self) -> Merged<Self> {
+        let mut merger = Merger::default();
+        let data = merger.value("", base, mine, theirs);
+        merger.finish(data)
+    }
+}
+
+// An item of a list merged with `Merger::list`
+pub trait Entity {
+    type Key: Ord + Clone;
+
+    // `None` for items that haven't been given an id yet
+    fn key(&self) -> Option<Self::Key>;
+    // Used in conflict paths
+    fn name(&self) -> String;
+}
+
+fn to_value<V: Serialize>(value: &V) -> Value {
+    serde_json::to_value(value).unwrap_or(Value::Null)
+}
+
+fn join(path: &str, key: &str) -> String {
+    if path.is_empty() {
+        key.to_string()
+    } else {
+        format!("{}.{}", path, key)
+    }
+}
+
+#[derive(Default)]
+pub struct Merger {
+    conflicts: Vec<MergeConflict>,
+}
+
+impl Merger {
+    pub fn finish<T>(self, data: T) -> Merged<T> {
+        Merged {
+            data,
+            conflicts: self.conflicts,
+        }
+    }
+
+    fn conflict(&mut self, path: &str, mine: Option<Value>, theirs: Option<Value>) {
+        self.conflicts.push(MergeConflict {
+            path: path.to_string(),
+            mine: mine.as_ref().map(render),
+            theirs: theirs.as_ref().map(render),
+        });
+    }
+
+    // A field that is kept or replaced as a whole
+    pub fn value<V: Clone + Serialize>(&mut self, path: &str, base: &V, mine: &V, theirs: &V) -> V {
+        let (base_value, mine_value, theirs_value) =
+            (to_value(base), to_value(mine), to_value(theirs));
+        if mine_value == theirs_value || theirs_value == base_value {
+            mine.clone()
+        } else if mine_value == base_value {
+            theirs.clone()
+        } else {
+            self.conflict(path, Some(mine_value), Some(theirs_value));
+            mine.clone()
+        }
+    }
+
+    // `value` for one field of an entity, e.g. `field(path, "title", base, mine, theirs, |h| &h.title)`
+    pub fn field<E, V: Clone + Serialize>(
+        &mut self,
+        path: &str,
+        name: &str,
+        base: &E,
+        mine: &E,
+        theirs: &E,
+        get: impl Fn(&E) -> &V,
+    ) -> V {
+        self.value(&join(path, name), get(base), get(mine), get(theirs))
+    }
+
+    // Collections like completion dates, where additions and removals from both
+    // sides can all be kept. The result is sorted.
+    pub fn set<V: Clone + Ord>(&self, base: &[V], mine: &[V], theirs: &[V]) -> Vec<V> {
+        let base: BTreeSet<&V> = base.iter().collect();
+        let mine: BTreeSet<&V> = mine.iter().collect();
+        let theirs: BTreeSet<&V> = theirs.iter().collect();
+        mine.iter()
+            .filter(|item| !base.contains(*item) || theirs.contains(*item))
+            .chain(theirs.iter().filter(|item| !base.contains(*item)))
+            .map(|item| (*item).clone())
+            .collect::<BTreeSet<V>>()
+            .into_iter()
+            .collect()
+    }
+
+    // Entities keyed by id, each merged with `merge_entity`. An entity deleted on one
+    // side and changed on the other is kept, and the clash recorded. The result follows
+    // `mine`'s order, with entities only `theirs` has at the end.
+    pub fn entities<'a, K, V>(
+        &mut self,
+        path: &str,
+        base: impl IntoIterator<Item = (K, &'a V)>,
+        mine: impl IntoIterator<Item = (K, &'a V)>,
+        theirs: impl IntoIterator<Item = (K, &'a V)>,
+        name: impl Fn(&K, &V) -> String,
+        mut merge_entity: impl FnMut(&mut Self, &str, &V, &V, &V) -> V,
+    ) -> Vec<(K, V)>
+    where
+        K: Ord + Clone,
+        V: Clone + Serialize + 'a,
+    {
+        let base: BTreeMap<K, &V> = base.into_iter().collect();
+        let mine: Vec<(K, &V)> = mine.into_iter().collect();
+        let theirs: Vec<(K, &V)> = theirs.into_iter().collect();
+        let mine_keys: BTreeSet<&K> = mine.iter().map(|(key, _)| key).collect();
+        let theirs_by_key: BTreeMap<&K, &V> =
+            theirs.iter().map(|(key, value)| (key, *value)).collect();
+
+        let mut merged = Vec::new();
+        for (key, mine_value) in &mine {
+            let child = join(path, &name(key, mine_value));
+            match (base.get(key), theirs_by_key.get(key)) {
+                (Some(base_value), Some(theirs_value)) => merged.push((
+                    key.clone(),
+                    merge_entity(self, &child, base_value, mine_value, theirs_value),
+                )),
+                // Added on both sides
+                (None, Some(theirs_value)) => {
+                    let mine_json = to_value(*mine_value);
+                    let theirs_json = to_value(*theirs_value);
+                    if mine_json != theirs_json {
+                        self.conflict(&child, Some(mine_json), Some(theirs_json));
+                    }
+                    merged.push((key.clone(), (*mine_value).clone()));
+                }
+                // Deleted elsewhere
+                (Some(base_value), None) => {
+                    if to_value(*base_value) != to_value(*mine_value) {
+                        self.conflict(&child, Some(to_value(*mine_value)), None);
+                        merged.push((key.clone(), (*mine_value).clone()));
+                    }
+                }
+                (None, None) => merged.push((key.clone(), (*mine_value).clone())),
+            }
+        }
+        for (key, theirs_value) in &theirs {
+            if mine_keys.contains(key) {
+                continue;
+            }
+            match base.get(key) {
+                None => merged.push((key.clone(), (*theirs_value).clone())),
+                // Deleted here but changed elsewhere
+                Some(base_value) if to_value(*base_value) != to_value(*theirs_value) => {
+                    let child = join(path, &name(key, theirs_value));
+                    self.conflict(&child, None, Some(to_value(*theirs_value)));
+                    merged.push((key.clone(), (*theirs_value).clone()));
+                }
+                Some(_) => {}
+            }
+        }
+        merged
+    }
+
+    // Like `entities`, for lists whose items carry their own id. Items without one
+    // can't be matched up, so those from both sides are kept, minus duplicates.
+    pub fn list<V: Entity + Clone + Serialize>(
+        &mut self,
+        path: &str,
+        base: &[V],
+        mine: &[V],
+        theirs: &[V],
+        merge_entity: impl FnMut(&mut Self, &str, &V, &V, &V) -> V,
+    ) -> Vec<V> {
+        let mut merged: Vec<V> = self
+            .entities(
+                path,
+                keyed(base),
+                keyed(mine),
+                keyed(theirs),
+                |_, item| item.name(),
+                merge_entity,
+            )
+            .into_iter()
+            .map(|(_, item)| item)
+            .collect();
+
+        let mut unkeyed: Vec<Value> = Vec::new();
+        for item in mine
+            .iter()
+            .chain(theirs)
+            .filter(|item| item.key().is_none())
+        {
+            let value = to_value(item);
+            if !unkeyed.contains(&value) {
+                unkeyed.push(value);
+                merged.push(item.clone());
+            }
+        }
+        merged
+    }
+}
+
+fn keyed<V: Entity>(items: &[V]) -> Vec<(V::Key, &V)> {
+    items
+        .iter()
+        .filter_map(|item| item.key().map(|key| (key, item)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde::Deserialize;
+
+    #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+    struct Habit {
+        id: u32,
+        title: String,
+        color: String,
+        days: Vec<u32>,
+    }
+
+    impl Entity for Habit {
+        type Key = u32;
+
+        fn key(&self) -> Option<u32> {
+            Some(self.id)
+        }
+
+        fn name(&self) -> String {
+            self.title.clone()
+        }
+    }
+
+    fn habit(id: u32, title: &str, color: &str, days: &[u32]) -> Habit {
+        Habit {
+            id,
+            title: title.to_string(),
+            color: color.to_string(),
+            days: days.to_vec(),
+        }
+    }
+
+    fn merge(base: &[Habit], mine: &[Habit], theirs: &[Habit]) -> Merged<Vec<Habit>> {
+        let mut merger = Merger::default();
+        let data = merger.list(
+            "habits",
+            base,
+            mine,
+            theirs,
+            |merger, path, base, mine, theirs| Habit {
+                id: mine.id,
+                title: merger.field(path, "title", base, mine, theirs, |h| &h.title),
+                color: merger.field(path, "color", base, mine, theirs, |h| &h.color),
+                days: merger.set(&base.days, &mine.days, &theirs.days),
+            },
+        );
+        merger.finish(data)
+    }
+
+    #[test]
+    fn changes_from_both_sides_are_combined() {
+        let base = vec![
+            habit(1, "Read", "red", &[1, 2]),
+            habit(2, "Run", "blue", &[]),
+        ];
+        let mine = vec![
+            habit(1, "Read", "green", &[1, 2, 3]),
+            habit(3, "Swim", "teal", &[]),
+        ];
+        let theirs = vec![
+            habit(1, "Read books", "red", &[2, 4]),
+            habit(2, "Run", "blue", &[]),
+        ];
+
+        let merged = merge(&base, &mine, &theirs);
+        assert!(merged.conflicts.is_empty());
+        assert_eq!(
+            merged.data,
+            vec![
+                habit(1, "Read books", "green", &[2, 3, 4]),
+                habit(3, "Swim", "teal", &[])
+            ]
+        );
+    }
+
+    #[test]
+    fn clashing_changes_are_flagged() {
+        let base = vec![habit(1, "Read", "red", &[]), habit(2, "Run", "blue", &[])];
+        let mine = vec![habit(1, "Read", "green", &[])];
+        let theirs = vec![habit(1, "Read", "black", &[]), habit(2, "Run", "pink", &[])];
+
+        let merged = merge(&base, &mine, &theirs);
+        assert_eq!(
+            merged.conflicts,
+            vec![
+                MergeConflict {
+                    path: "habits.Read.color".to_string(),
+                    mine: Some("green".to_string()),
+                    theirs: Some("black".to_string()),
+                },
+                MergeConflict {
+                    path: "habits.Run".to_string(),
+                    mine: None,
+                    theirs: Some("{4 fields}".to_string()),
+                },
+            ]
+        );
+        // Mine wins the clash, and the changed entity survives its deletion
+        assert_eq!(
+            merged.data,
+            vec![habit(1, "Read", "green", &[]), habit(2, "Run", "pink", &[])]
+        );
+    }
+}
@@ -1,13 +1,37 @@
 // storage/mod.rs
+mod backend;
+mod backups;
 mod config;
+mod crypto;
+mod diff;
 mod formats;
+mod merge;
 mod paths;
+mod schema;
+mod sqlite;
 mod storage_manager;
+mod watcher;
+mod writer;
 
+pub use backend::{get_storage_backend, Storage, StorageBackend};
+pub use backups::{BackupInfo, BackupKind};
 pub use config::StorageConfig;
+pub use crypto::{
+    change_passphrase, disable_encryption, enable_encryption, encryption_state, is_sealed,
+    read_document, replace_key, unlock, write_document, EncryptionState,
+};
+pub use diff::{ChangeKind, DiffEntry};
 pub use formats::{DataFormat, JsonFormat, YamlFormat};
-pub use paths::get_path_manager;
-pub use storage_manager::{StorageError, StorageManager};
+pub use merge::{Entity, MergeConflict, Mergeable, Merged, Merger};
+pub use paths::{get_path_manager, DataDirSource};
+pub use schema::{decode, encode, upgrade, Migration, Versioned};
+pub use sqlite::{
+    from_json_column, load_database, parse_column, row_changes, to_json_column, SqliteModel,
+    SqliteStorage,
+};
+pub use storage_manager::{Conflict, ConflictResolution, Corruption, StorageError, StorageManager};
+pub use watcher::StorageEvent;
+pub use writer::block_on_writers;
 
 // Re-export commonly used types
 pub type JsonStorage<T> = StorageManager<T, JsonFormat>;
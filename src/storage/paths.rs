@@ -1,22 +1,124 @@
 // storage/paths.rs
+#[cfg(not(target_os = "android"))]
 use directories::UserDirs;
 use once_cell::sync::Lazy;
-use std::path::PathBuf;
-use tracing::error;
+use std::path::{Path, PathBuf};
+use tracing::{error, info};
 
+#[cfg(target_os = "android")]
 const APP_NAME: &str = "com.example.Myquest";
+const DATA_DIR_ENV: &str = "MYQUEST_DATA_DIR";
+const DATA_DIR_FLAG: &str = "--data-dir";
+const PORTABLE_FLAG: &str = "--portable";
+// Next to the executable; its presence alone turns portable mode on
+const PORTABLE_DIR_NAME: &str = "myquest-data";
+
+// Where the data directory came from, in order of precedence
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DataDirSource {
+    CommandLine,
+    Environment,
+    Portable,
+    Default,
+    // The default location could not be created, so the working directory is used
+    Fallback,
+}
+
+impl DataDirSource {
+    pub fn label(&self) -> &'static str {
+        match self {
+            DataDirSource::CommandLine => "set with --data-dir",
+            DataDirSource::Environment => "set with MYQUEST_DATA_DIR",
+            DataDirSource::Portable => "portable mode",
+            DataDirSource::Default => "default location",
+            DataDirSource::Fallback => "working directory, the default location was unavailable",
+        }
+    }
+}
+
+// Explicit choices from the command line or environment. Later flags win, and
+// both `--data-dir <path>` and `--data-dir=<path>` are accepted.
+fn explicit_data_dir(args: &[String], env_dir: Option<String>) -> Option<(PathBuf, DataDirSource)> {
+    let mut from_args = None;
+    let mut args_iter = args.iter();
+    while let Some(arg) = args_iter.next() {
+        if arg == DATA_DIR_FLAG {
+            from_args = args_iter.next().cloned();
+        } else if let Some(value) = arg.strip_prefix("--data-dir=") {
+            from_args = Some(value.to_string());
+        }
+    }
+
+    from_args
+        .filter(|dir| !dir.is_empty())
+        .map(|dir| (PathBuf::from(dir), DataDirSource::CommandLine))
+        .or_else(|| {
+            env_dir
+                .filter(|dir| !dir.trim().is_empty())
+                .map(|dir| (PathBuf::from(dir), DataDirSource::Environment))
+        })
+}
+
+fn portable_data_dir(args: &[String], exe_dir: Option<&Path>) -> Option<PathBuf> {
+    let dir = exe_dir?.join(PORTABLE_DIR_NAME);
+    (args.iter().any(|arg| arg == PORTABLE_FLAG) || dir.is_dir()).then_some(dir)
+}
+
+// Relative paths are taken from the working directory at startup, so the
+// result doesn't change if the app later changes directory
+fn absolute(path: PathBuf) -> PathBuf {
+    if path.is_absolute() {
+        return path;
+    }
+    std::env::current_dir()
+        .map(|cwd| cwd.join(&path))
+        .unwrap_or(path)
+}
 
 pub struct PathManager {
     root_dir: PathBuf,
+    source: DataDirSource,
 }
 
 impl PathManager {
     pub fn new() -> Self {
-        let root_dir = Self::determine_root_dir();
-        Self { root_dir }
+        let args: Vec<String> = std::env::args().skip(1).collect();
+        let env_dir = std::env::var(DATA_DIR_ENV).ok();
+        let exe_dir = std::env::current_exe()
+            .ok()
+            .and_then(|exe| exe.parent().map(Path::to_path_buf));
+
+        let (root_dir, source) = Self::resolve(&args, env_dir, exe_dir.as_deref());
+        info!("Using data directory {:?} ({})", root_dir, source.label());
+        Self { root_dir, source }
+    }
+
+    fn resolve(
+        args: &[String],
+        env_dir: Option<String>,
+        exe_dir: Option<&Path>,
+    ) -> (PathBuf, DataDirSource) {
+        let chosen = explicit_data_dir(args, env_dir)
+            .or_else(|| portable_data_dir(args, exe_dir).map(|dir| (dir, DataDirSource::Portable)));
+
+        match chosen {
+            // A chosen directory is never swapped for another one, since writing a
+            // fresh data set elsewhere would later clash with the real one when synced
+            Some((dir, source)) => {
+                let dir = absolute(dir);
+                if let Err(e) = std::fs::create_dir_all(&dir) {
+                    error!("Failed to create data directory {:?}: {}", dir, e);
+                }
+                (dir, source)
+            }
+            None => match Self::determine_root_dir() {
+                Some(dir) => (dir, DataDirSource::Default),
+                None => (absolute(PathBuf::from(".")), DataDirSource::Fallback),
+            },
+        }
     }
 
-    fn determine_root_dir() -> PathBuf {
+    fn determine_root_dir() -> Option<PathBuf> {
         #[cfg(target_os = "android")]
         {
             let potential_paths = vec![
@@ -25,32 +127,40 @@ impl PathManager {
             ];
             for path in potential_paths {
                 let dir_path = PathBuf::from(&path);
-                if let Ok(_) = std::fs::create_dir_all(&dir_path) {
-                    return dir_path;
+                if std::fs::create_dir_all(&dir_path).is_ok() {
+                    return Some(dir_path);
                 }
             }
-            PathBuf::from(".")
+            error!("Could not create any of the Android data directories");
+            None
         }
         #[cfg(not(target_os = "android"))]
         {
-            if let Some(user_dirs) = UserDirs::new() {
-                let documents_dir = user_dirs
-                    .document_dir()
-                    .unwrap_or_else(|| user_dirs.home_dir())
-                    .to_path_buf();
-                let myquest_dir = documents_dir.join("myquest");
-                if let Err(e) = std::fs::create_dir_all(&myquest_dir) {
-                    error!("Failed to create myquest directory: {}", e);
-                    return PathBuf::from(".");
-                }
-                myquest_dir
-            } else {
+            let Some(user_dirs) = UserDirs::new() else {
                 error!("Could not determine user directories");
-                PathBuf::from(".")
+                return None;
+            };
+            let documents_dir = user_dirs
+                .document_dir()
+                .unwrap_or_else(|| user_dirs.home_dir())
+                .to_path_buf();
+            let myquest_dir = documents_dir.join("myquest");
+            if let Err(e) = std::fs::create_dir_all(&myquest_dir) {
+                error!("Failed to create myquest directory: {}", e);
+                return None;
             }
+            Some(myquest_dir)
         }
     }
 
+    pub fn root_dir(&self) -> &Path {
+        &self.root_dir
+    }
+
+    pub fn source(&self) -> DataDirSource {
+        self.source
+    }
+
     pub fn habits_dir(&self) -> PathBuf {
         let habits_dir = self.root_dir.join("habits");
         if let Err(e) = std::fs::create_dir_all(&habits_dir) {
@@ -75,13 +185,90 @@ impl PathManager {
         self.root_dir.join("todos.json")
     }
 
+    pub fn routines_file(&self) -> PathBuf {
+        self.root_dir.join("routines.json")
+    }
+
     pub fn timeline_file(&self, name: &str) -> PathBuf {
         self.timelines_dir().join(format!("{}.yaml", name))
     }
+
+    // The files the stores keep their data in, whether they exist yet or not.
+    // Symlinks in the timelines directory aren't followed.
+    pub fn store_files(&self) -> Vec<PathBuf> {
+        let mut files = vec![self.habits_file(), self.todos_file(), self.routines_file()];
+        if let Ok(entries) = std::fs::read_dir(self.timelines_dir()) {
+            let mut timelines: Vec<PathBuf> = entries
+                .flatten()
+                .filter(|entry| entry.file_type().is_ok_and(|kind| kind.is_file()))
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|ext| ext == "yaml"))
+                .collect();
+            timelines.sort();
+            files.extend(timelines);
+        }
+        files
+    }
 }
 
+// Resolved once, on first use, from the command line, environment and executable location
 static PATH_MANAGER: Lazy<PathManager> = Lazy::new(PathManager::new);
 
 pub fn get_path_manager() -> &'static PathManager {
     &PATH_MANAGER
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(values: &[&str]) -> Vec<String> {
+        values.iter().map(|value| value.to_string()).collect()
+    }
+
+    #[test]
+    fn data_dir_precedence() {
+        let dir = tempfile::tempdir().unwrap();
+        let exe_dir = dir.path().join("bin");
+        let cli = dir.path().join("cli").to_string_lossy().to_string();
+        let env = dir.path().join("env").to_string_lossy().to_string();
+
+        let (root, source) = PathManager::resolve(
+            &args(&["--data-dir", &cli, "--portable"]),
+            Some(env.clone()),
+            Some(&exe_dir),
+        );
+        assert_eq!(
+            (root.clone(), source),
+            (PathBuf::from(&cli), DataDirSource::CommandLine)
+        );
+        assert!(root.is_dir());
+
+        let (root, source) =
+            PathManager::resolve(&args(&[&format!("--data-dir={}", cli)]), None, None);
+        assert_eq!(
+            (root, source),
+            (PathBuf::from(&cli), DataDirSource::CommandLine)
+        );
+
+        let (root, source) =
+            PathManager::resolve(&args(&["--portable"]), Some(env.clone()), Some(&exe_dir));
+        assert_eq!(
+            (root, source),
+            (PathBuf::from(&env), DataDirSource::Environment)
+        );
+
+        let (root, source) = PathManager::resolve(&args(&["--portable"]), None, Some(&exe_dir));
+        assert_eq!(
+            (root, source),
+            (exe_dir.join(PORTABLE_DIR_NAME), DataDirSource::Portable)
+        );
+
+        // Once the portable directory exists, the flag is no longer needed
+        let (root, source) = PathManager::resolve(&[], Some("  ".to_string()), Some(&exe_dir));
+        assert_eq!(
+            (root, source),
+            (exe_dir.join(PORTABLE_DIR_NAME), DataDirSource::Portable)
+        );
+    }
+}
@@ -0,0 +1,205 @@
+// storage/schema.rs
+use super::DataFormat;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use serde_json::Value;
+use tracing::info;
+
+// One step in a store's history, upgrading a document from version `from` to `from + 1`
+pub struct Migration {
+    pub from: u32,
+    pub description: &'static str,
+    pub apply: fn(Value) -> Result<Value, String>,
+}
+
+// A document that is written inside a `{ version, data }` envelope
+pub trait Versioned {
+    // The version this build writes
+    const SCHEMA_VERSION: u32;
+    // Every step from the oldest known version up to `SCHEMA_VERSION`
+    const SCHEMA_MIGRATIONS: &'static [Migration];
+
+    // Files written before documents carried a version. The first versioned
+    // format is 1, so stores that only ever had one shape need no migrations.
+    fn unversioned_version(_data: &Value) -> u32 {
+        1
+    }
+}
+
+#[derive(Serialize)]
+struct Envelope<'a, T> {
+    version: u32,
+    data: &'a T,
+}
+
+// The version is checked on the generic value before this is used
+#[derive(Deserialize)]
+struct TypedEnvelope<T> {
+    data: T,
+}
+
+fn is_envelope(value: &Value) -> bool {
+    match value {
+        Value::Object(map) => {
+            map.len() == 2
+                && map.get("version").is_some_and(Value::is_u64)
+                && map.contains_key("data")
+        }
+        _ => false,
+    }
+}
+
+// Applies the registered steps from `from` up to the current version
+pub fn upgrade<T: Versioned>(mut data: Value, from: u32) -> Result<Value, String> {
+    if from > T::SCHEMA_VERSION {
+        return Err(format!(
+            "Written by a newer version of the app (schema {}, this build reads up to {})",
+            from,
+            T::SCHEMA_VERSION
+        ));
+    }
+    for version in from..T::SCHEMA_VERSION {
+        let migration = T::SCHEMA_MIGRATIONS
+            .iter()
+            .find(|migration| migration.from == version)
+            .ok_or_else(|| format!("No migration from schema version {}", version))?;
+        info!(
+            "Migrating from schema {} to {}: {}",
+            version,
+            version + 1,
+            migration.description
+        );
+        data = (migration.apply)(data)?;
+    }
+    Ok(data)
+}
+
+pub fn encode<T: Versioned + Serialize, F: DataFormat>(data: &T) -> Result<String, String> {
+    F::serialize(&Envelope {
+        version: T::SCHEMA_VERSION,
+        data,
+    })
+}
+
+// Reads an enveloped document of any known version, or an unversioned one
+pub fn decode<T: Versioned + DeserializeOwned, F: DataFormat>(content: &str) -> Result<T, String> {
+    let value = F::deserialize::<Value>(content)?;
+    let enveloped = is_envelope(&value);
+    let (version, data) = match value {
+        Value::Object(mut map) if enveloped => {
+            let version = map["version"].as_u64().unwrap_or_default() as u32;
+            (version, map.remove("data").unwrap_or_default())
+        }
+        value => (T::unversioned_version(&value), value),
+    };
+
+    if version == T::SCHEMA_VERSION {
+        // Parsed straight from the text, so nothing is lost to the generic value in between
+        return if enveloped {
+            F::deserialize::<TypedEnvelope<T>>(content).map(|envelope| envelope.data)
+        } else {
+            F::deserialize::<T>(content)
+        };
+    }
+
+    // Back through the format rather than `from_value`, so e.g. a YAML name like
+    // `2020` still reads as a string
+    let upgraded = upgrade::<T>(data, version)?;
+    F::deserialize(&F::serialize(&upgraded)?)
+}
+
+// For a document that `decode` refused because a newer build wrote it: that
+// version, and the data if this build's type still reads it, as it does when the
+// newer schema only added fields
+pub fn decode_newer<T: Versioned + DeserializeOwned, F: DataFormat>(
+    content: &str,
+) -> Option<(u32, Option<T>)> {
+    let value = F::deserialize::<Value>(content).ok()?;
+    if !is_envelope(&value) {
+        return None;
+    }
+    let version = value["version"].as_u64()? as u32;
+    if version <= T::SCHEMA_VERSION {
+        return None;
+    }
+    let data = F::deserialize::<TypedEnvelope<T>>(content)
+        .ok()
+        .map(|envelope| envelope.data);
+    Some((version, data))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{JsonFormat, YamlFormat};
+
+    #[derive(Debug, PartialEq, Serialize, Deserialize)]
+    struct Doc {
+        name: String,
+        tags: Vec<String>,
+    }
+
+    impl Versioned for Doc {
+        const SCHEMA_VERSION: u32 = 3;
+        const SCHEMA_MIGRATIONS: &'static [Migration] = &[
+            Migration {
+                from: 1,
+                description: "Rename title to name",
+                apply: |mut data| {
+                    let title = data["title"].take();
+                    data["name"] = title;
+                    Ok(data)
+                },
+            },
+            Migration {
+                from: 2,
+                description: "Add tags",
+                apply: |mut data| {
+                    data["tags"] = Value::Array(Vec::new());
+                    Ok(data)
+                },
+            },
+        ];
+    }
+
+    #[test]
+    fn documents_are_upgraded_from_every_version() {
+        let doc = Doc {
+            name: "2020".to_string(),
+            tags: Vec::new(),
+        };
+        assert_eq!(decode::<Doc, YamlFormat>("title: 2020\n"), Ok(doc));
+        assert_eq!(
+            decode::<Doc, JsonFormat>(r#"{"version": 2, "data": {"name": "a"}}"#)
+                .unwrap()
+                .name,
+            "a"
+        );
+
+        let current = Doc {
+            name: "b".to_string(),
+            tags: vec!["x".to_string()],
+        };
+        let content = encode::<_, JsonFormat>(&current).unwrap();
+        assert!(content.contains("\"version\": 3"));
+        assert_eq!(decode::<Doc, JsonFormat>(&content), Ok(current));
+
+        assert!(decode::<Doc, JsonFormat>(r#"{"version": 4, "data": {}}"#)
+            .unwrap_err()
+            .contains("newer version"));
+    }
+
+    #[test]
+    fn newer_documents_are_told_apart_from_broken_ones() {
+        let newer = r#"{"version": 4, "data": {"name": "a", "tags": [], "pinned": true}}"#;
+        let (version, data) = decode_newer::<Doc, JsonFormat>(newer).unwrap();
+        assert_eq!(version, 4);
+        assert_eq!(data.unwrap().name, "a");
+
+        let reshaped = r#"{"version": 4, "data": {"title": "a"}}"#;
+        assert_eq!(decode_newer::<Doc, JsonFormat>(reshaped), Some((4, None)));
+
+        let current = r#"{"version": 3, "data": {"name": 1}}"#;
+        assert_eq!(decode_newer::<Doc, JsonFormat>(current), None);
+        assert_eq!(decode_newer::<Doc, JsonFormat>("{"), None);
+    }
+}
@@ -0,0 +1,962 @@
+// storage/sqlite.rs
+use super::backups::{list_snapshots, snapshot_path, snapshots_to_keep};
+use super::diff::{diff_values, DiffEntry};
+use super::merge::Mergeable;
+use super::storage_manager::{find_quarantined, quarantine, OutsideChange, StorageResult};
+use super::watcher::{watch_file, FileWatcher, SETTLE_DELAY};
+use super::writer::{run_debounced, spawn_writer, WriterMessage};
+use super::{
+    BackupInfo, BackupKind, Conflict, ConflictResolution, Corruption, StorageConfig, StorageError,
+    StorageEvent,
+};
+use chrono::Local;
+use rusqlite::types::Type;
+use rusqlite::{Connection, OpenFlags, Transaction};
+use serde::{de::DeserializeOwned, Serialize};
+use std::collections::BTreeMap;
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use std::sync::{Arc, Mutex, OnceLock};
+use std::time::SystemTime;
+use tokio::sync::{broadcast, mpsc, oneshot, OwnedRwLockWriteGuard, RwLock};
+use tracing::{debug, error, info, warn};
+
+// A store that can live in its own SQLite database, one table per kind of row
+pub trait SqliteModel: Sized {
+    // Schema steps applied in order; `PRAGMA user_version` counts how many have run
+    const MIGRATIONS: &'static [&'static str];
+
+    fn load(conn: &Connection) -> rusqlite::Result<Self>;
+
+    // Writes only the rows that differ between the last saved state and the new one
+    fn persist(tx: &Transaction, before: &Self, after: &Self) -> rusqlite::Result<()>;
+}
+
+pub struct RowChanges<'a, K, V> {
+    pub upserted: Vec<(&'a K, &'a V)>,
+    pub removed: Vec<&'a K>,
+}
+
+// Rows that are new or changed in `after`, and keys that no longer exist
+pub fn row_changes<'a, K, V>(
+    before: impl IntoIterator<Item = (&'a K, &'a V)>,
+    after: impl IntoIterator<Item = (&'a K, &'a V)>,
+) -> RowChanges<'a, K, V>
+where
+    K: Ord + 'a,
+    V: PartialEq + 'a,
+{
+    let mut old: BTreeMap<&K, &V> = before.into_iter().collect();
+    let mut upserted = Vec::new();
+    for (key, value) in after {
+        if old.remove(key) != Some(value) {
+            upserted.push((key, value));
+        }
+    }
+    RowChanges {
+        upserted,
+        removed: old.into_keys().collect(),
+    }
+}
+
+// TEXT columns holding ids and enums that round-trip through `FromStr`
+pub fn parse_column<V>(index: usize, value: String) -> rusqlite::Result<V>
+where
+    V: FromStr,
+    V::Err: std::error::Error + Send + Sync + 'static,
+{
+    value
+        .parse()
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+// Nested values without a table of their own are stored as JSON
+pub fn to_json_column<V: Serialize>(value: &V) -> rusqlite::Result<String> {
+    serde_json::to_string(value).map_err(|e| rusqlite::Error::ToSqlConversionFailure(Box::new(e)))
+}
+
+pub fn from_json_column<V: DeserializeOwned>(index: usize, value: String) -> rusqlite::Result<V> {
+    serde_json::from_str(&value)
+        .map_err(|e| rusqlite::Error::FromSqlConversionFailure(index, Type::Text, Box::new(e)))
+}
+
+fn migrate<T: SqliteModel>(conn: &mut Connection, file_path: &Path) -> StorageResult<()> {
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    let version = version as usize;
+    if version > T::MIGRATIONS.len() {
+        return Err(StorageError::NewerVersion(
+            file_path.to_path_buf(),
+            version as u32,
+        ));
+    }
+
+    let tx = conn.transaction()?;
+    for (index, step) in T::MIGRATIONS.iter().enumerate().skip(version) {
+        debug!("Applying database migration {}", index + 1);
+        tx.execute_batch(step)?;
+    }
+    tx.pragma_update(None, "user_version", T::MIGRATIONS.len() as i64)?;
+    tx.commit()?;
+    Ok(())
+}
+
+fn open_connection<T: SqliteModel>(file_path: &Path) -> StorageResult<Connection> {
+    let mut conn = Connection::open(file_path)?;
+    conn.pragma_update(None, "foreign_keys", true)?;
+    migrate::<T>(&mut conn, file_path)?;
+    Ok(conn)
+}
+
+// Loads a database from a newer build as it is, without migrating or writing to it
+fn load_unmigrated<T: SqliteModel>(file_path: &Path) -> StorageResult<T> {
+    let conn = Connection::open_with_flags(file_path, OpenFlags::SQLITE_OPEN_READ_ONLY)?;
+    Ok(T::load(&conn)?)
+}
+
+fn open_and_load<T: SqliteModel>(file_path: &Path) -> StorageResult<(Connection, T)> {
+    let conn = open_connection::<T>(file_path)?;
+    let data = T::load(&conn)?;
+    Ok((conn, data))
+}
+
+// Opens, migrates and loads a database, e.g. a snapshot or another timeline
+pub fn load_database<T: SqliteModel>(file_path: &Path) -> StorageResult<T> {
+    open_and_load(file_path).map(|(_, data)| data)
+}
+
+// What the database held when it was last read or written here, for telling outside
+// changes apart from our own
+struct DiskState<T> {
+    // Also the common ancestor when merging an outside change
+    saved: T,
+    stamp: Option<Stamp>,
+    conflict: Option<PendingConflict<T>>,
+}
+
+struct PendingConflict<T> {
+    info: Conflict,
+    theirs: T,
+    stamp: Stamp,
+}
+
+// `data_version` moves when another connection commits to the database. The file's
+// size and modification time also catch it being replaced, e.g. by a sync client,
+// which the open connection wouldn't see.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct Stamp {
+    data_version: i64,
+    len: u64,
+    modified: Option<SystemTime>,
+}
+
+fn stamp(conn: &Connection, file_path: &Path) -> StorageResult<Stamp> {
+    let metadata = std::fs::metadata(file_path)?;
+    Ok(Stamp {
+        data_version: conn.pragma_query_value(None, "data_version", |row| row.get(0))?,
+        len: metadata.len(),
+        modified: metadata.modified().ok(),
+    })
+}
+
+// Handles shared by the storage and its writer and watcher tasks
+#[derive(Clone)]
+struct Shared<T> {
+    file_path: PathBuf,
+    data: Arc<RwLock<T>>,
+    // Not opened while the database is quarantined, so nothing replaces it until resolved
+    conn: Arc<Mutex<Option<Connection>>>,
+    disk: Arc<Mutex<DiskState<T>>>,
+    events: broadcast::Sender<StorageEvent>,
+}
+
+pub struct SqliteStorage<T> {
+    shared: Shared<T>,
+    config: StorageConfig,
+    corruption: Arc<RwLock<Option<Corruption>>>,
+    // The schema version when a newer build wrote the database, which is then only read
+    newer_version: Option<u32>,
+    writer: OnceLock<mpsc::UnboundedSender<WriterMessage<()>>>,
+    // Started with the writer; kept alive for as long as the storage is
+    watcher: OnceLock<Option<FileWatcher>>,
+}
+
+impl<T> SqliteStorage<T>
+where
+    T: SqliteModel + Mergeable + Default + Serialize + Clone + Send + Sync + 'static,
+{
+    // `import` seeds a database that doesn't exist yet, e.g. from the file it replaces
+    pub fn open(
+        file_path: PathBuf,
+        config: StorageConfig,
+        default_data: Option<T>,
+        import: Option<T>,
+    ) -> StorageResult<Self> {
+        debug!("Initializing SqliteStorage for path: {:?}", file_path);
+
+        if config.create_dirs {
+            if let Some(parent) = file_path.parent() {
+                std::fs::create_dir_all(parent).map_err(|e| {
+                    StorageError::InitError(format!("Failed to create directory structure: {}", e))
+                })?;
+            }
+        }
+
+        let mut corruption = None;
+        let mut newer_version = None;
+        let (conn, data, saved) = if file_path.exists() {
+            match open_and_load::<T>(&file_path) {
+                Ok((conn, data)) => {
+                    info!("Successfully loaded data from database");
+                    (Some(conn), data.clone(), data)
+                }
+                // Not corrupt, so it stays where the newer build can find it
+                Err(StorageError::NewerVersion(_, version)) => {
+                    warn!(
+                        "{:?} was written by a newer version of the app (schema {}), opening it read-only",
+                        file_path, version
+                    );
+                    newer_version = Some(version);
+                    let data = load_unmigrated::<T>(&file_path)
+                        .ok()
+                        .or(default_data)
+                        .unwrap_or_default();
+                    (None, data.clone(), data)
+                }
+                Err(e) => {
+                    error!("Failed to load {:?}, quarantining it: {}", file_path, e);
+                    corruption = Some(quarantine(&file_path, e.to_string())?);
+                    (None, default_data.unwrap_or_default(), T::default())
+                }
+            }
+        } else if let Some(quarantined_path) = find_quarantined(&file_path) {
+            warn!(
+                "Found unresolved quarantined database {:?}",
+                quarantined_path
+            );
+            corruption = Some(Corruption {
+                file_path: file_path.clone(),
+                quarantined_path,
+                reason: "The database was quarantined in an earlier session".to_string(),
+            });
+            (None, default_data.unwrap_or_default(), T::default())
+        } else {
+            let mut conn = open_connection::<T>(&file_path)?;
+            let data = import.or(default_data).unwrap_or_default();
+            let tx = conn.transaction()?;
+            T::persist(&tx, &T::default(), &data)?;
+            tx.commit()?;
+            debug!("Created database {:?}", file_path);
+            (Some(conn), data.clone(), data)
+        };
+
+        let stamp = conn
+            .as_ref()
+            .map(|conn| stamp(conn, &file_path))
+            .transpose()?;
+        Ok(Self {
+            shared: Shared {
+                file_path,
+                data: Arc::new(RwLock::new(data)),
+                conn: Arc::new(Mutex::new(conn)),
+                disk: Arc::new(Mutex::new(DiskState {
+                    saved,
+                    stamp,
+                    conflict: None,
+                })),
+                events: broadcast::channel(16).0,
+            },
+            config,
+            corruption: Arc::new(RwLock::new(corruption)),
+            newer_version,
+            writer: OnceLock::new(),
+            watcher: OnceLock::new(),
+        })
+    }
+
+    pub fn file_path(&self) -> &PathBuf {
+        &self.shared.file_path
+    }
+
+    pub async fn corruption(&self) -> Option<Corruption> {
+        self.corruption.read().await.clone()
+    }
+
+    // Events for changes made to the database outside the app. Also starts watching it.
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        self.writer();
+        self.shared.events.subscribe()
+    }
+
+    pub async fn conflict(&self) -> Option<Conflict> {
+        let disk = self.shared.disk.lock().ok()?;
+        disk.conflict.as_ref().map(|pending| pending.info.clone())
+    }
+
+    pub async fn resolve_conflict(&self, resolution: ConflictResolution) -> StorageResult<()> {
+        {
+            let mut data = self.shared.data.write().await;
+            let mut disk = self
+                .shared
+                .disk
+                .lock()
+                .map_err(|_| StorageError::LockError)?;
+            let Some(pending) = disk.conflict.take() else {
+                return Ok(());
+            };
+            info!(
+                "Resolving conflict on {:?} with {:?}",
+                self.shared.file_path, resolution
+            );
+
+            let merged = match resolution {
+                ConflictResolution::KeepMine => T::merge(&disk.saved, &data, &pending.theirs),
+                ConflictResolution::UseTheirs => T::merge(&disk.saved, &pending.theirs, &data),
+            };
+            *data = merged.data;
+            disk.saved = pending.theirs;
+            disk.stamp = Some(pending.stamp);
+        }
+        let _ = self.shared.events.send(StorageEvent::Reloaded);
+        self.force_save().await
+    }
+
+    pub fn newer_version(&self) -> Option<u32> {
+        self.newer_version
+    }
+
+    async fn ensure_healthy(&self) -> StorageResult<()> {
+        match self.corruption.read().await.as_ref() {
+            Some(corruption) => Err(StorageError::Corrupt(corruption.clone())),
+            None => Ok(()),
+        }
+    }
+
+    async fn ensure_writable(&self) -> StorageResult<()> {
+        self.ensure_healthy().await?;
+        match self.newer_version {
+            Some(version) => Err(StorageError::NewerVersion(
+                self.shared.file_path.clone(),
+                version,
+            )),
+            None => Ok(()),
+        }
+    }
+
+    pub async fn read<R, Func>(&self, f: Func) -> StorageResult<R>
+    where
+        Func: FnOnce(&T) -> R,
+    {
+        self.ensure_healthy().await?;
+        let guard = self.shared.data.read().await;
+        Ok(f(&guard))
+    }
+
+    pub async fn write<R, Func>(&self, f: Func) -> StorageResult<R>
+    where
+        Func: FnOnce(&mut T) -> R,
+    {
+        self.ensure_writable().await?;
+        let result = f(&mut *self.shared.data.write().await);
+
+        if self.writer().send(WriterMessage::Save(())).is_err() {
+            error!("Storage writer for {:?} has stopped", self.shared.file_path);
+        }
+
+        Ok(result)
+    }
+
+    pub async fn get_data(&self) -> StorageResult<T> {
+        self.ensure_healthy().await?;
+        let guard = self.shared.data.read().await;
+        Ok((*guard).clone())
+    }
+
+    // Resolves once every write made before the call is in the database
+    pub async fn flush(&self) -> StorageResult<()> {
+        let (reply, done) = oneshot::channel();
+        if self.writer().send(WriterMessage::Flush(reply)).is_err() {
+            return Self::save(&self.shared, &self.config).await;
+        }
+        done.await.unwrap_or(Err(StorageError::LockError))
+    }
+
+    pub async fn force_save(&self) -> StorageResult<()> {
+        self.ensure_writable().await?;
+        let _ = self.writer().send(WriterMessage::Save(()));
+        self.flush().await
+    }
+
+    fn writer(&self) -> &mpsc::UnboundedSender<WriterMessage<()>> {
+        self.writer.get_or_init(|| {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            let shared = self.shared.clone();
+            let config = self.config.clone();
+            spawn_writer(async move {
+                run_debounced(config.save_debounce, receiver, |()| {
+                    let shared = shared.clone();
+                    let config = config.clone();
+                    async move { Self::save(&shared, &config).await }
+                })
+                .await;
+                debug!("Storage writer for {:?} stopped", shared.file_path);
+            });
+            self.watcher
+                .get_or_init(|| self.start_watcher(sender.clone()));
+            sender
+        })
+    }
+
+    fn start_watcher(
+        &self,
+        writer: mpsc::UnboundedSender<WriterMessage<()>>,
+    ) -> Option<FileWatcher> {
+        match watch_file(&self.shared.file_path) {
+            Ok((watcher, changes)) => {
+                tokio::spawn(Self::run_watcher(
+                    self.shared.clone(),
+                    self.corruption.clone(),
+                    writer,
+                    changes,
+                ));
+                Some(watcher)
+            }
+            Err(e) => {
+                warn!(
+                    "Not watching {:?} for outside changes: {}",
+                    self.shared.file_path, e
+                );
+                None
+            }
+        }
+    }
+
+    async fn run_watcher(
+        shared: Shared<T>,
+        corruption: Arc<RwLock<Option<Corruption>>>,
+        writer: mpsc::UnboundedSender<WriterMessage<()>>,
+        mut changes: mpsc::UnboundedReceiver<()>,
+    ) {
+        while changes.recv().await.is_some() {
+            tokio::time::sleep(SETTLE_DELAY).await;
+            while changes.try_recv().is_ok() {}
+
+            // A quarantined store is resolved by the user, not by whatever shows up on disk
+            if corruption.read().await.is_some() {
+                continue;
+            }
+            let local = shared.data.clone().write_owned().await;
+            let checking = shared.clone();
+            let checked = tokio::task::spawn_blocking(move || {
+                let mut local = local;
+                let mut conn = checking.conn.lock().map_err(|_| StorageError::LockError)?;
+                let mut disk = checking.disk.lock().map_err(|_| StorageError::LockError)?;
+                Self::check_database(&checking, &mut conn, &mut disk, &mut local)
+            })
+            .await
+            .map_err(|e| StorageError::Io(std::io::Error::other(e)));
+            match checked {
+                Ok(Ok(Some(OutsideChange::Reloaded))) => {
+                    let _ = writer.send(WriterMessage::Discard);
+                }
+                Ok(Ok(Some(OutsideChange::Merged))) => {
+                    let _ = writer.send(WriterMessage::Save(()));
+                }
+                Ok(Ok(_)) => {}
+                Ok(Err(e)) | Err(e) => warn!(
+                    "Failed to check {:?} for outside changes: {}",
+                    shared.file_path, e
+                ),
+            }
+        }
+        debug!("Stopped watching {:?}", shared.file_path);
+    }
+
+    // Folds a change made to the database outside the app into the data, the same
+    // way `StorageManager` does for files: taken over as is when there are no unsaved
+    // edits, merged with them otherwise, and flagged when they clash
+    fn check_database(
+        shared: &Shared<T>,
+        conn: &mut Option<Connection>,
+        disk: &mut DiskState<T>,
+        local: &mut T,
+    ) -> StorageResult<Option<OutsideChange>> {
+        // Nothing was read from the database yet, e.g. it is about to be created
+        let (Some(known), Some(current)) = (disk.stamp, conn.as_ref()) else {
+            return Ok(None);
+        };
+        let current = stamp(current, &shared.file_path)?;
+        let already_seen = disk
+            .conflict
+            .as_ref()
+            .is_some_and(|pending| pending.stamp == current);
+        if known == current || already_seen {
+            return Ok(None);
+        }
+
+        // Reopened, since the file may have been replaced under the old connection
+        let reopened = open_connection::<T>(&shared.file_path)?;
+        let theirs = T::load(&reopened)?;
+        let current = stamp(&reopened, &shared.file_path)?;
+        *conn = Some(reopened);
+
+        let unsaved = serde_json::to_value(&*local).ok() != serde_json::to_value(&disk.saved).ok();
+        if !unsaved {
+            info!("Reloading {:?} after an outside change", shared.file_path);
+            *local = theirs.clone();
+            disk.saved = theirs;
+            disk.stamp = Some(current);
+            disk.conflict = None;
+            let _ = shared.events.send(StorageEvent::Reloaded);
+            return Ok(Some(OutsideChange::Reloaded));
+        }
+
+        let merged = T::merge(&disk.saved, local, &theirs);
+        if merged.conflicts.is_empty() {
+            info!("Merged an outside change into {:?}", shared.file_path);
+            *local = merged.data;
+            disk.saved = theirs;
+            disk.stamp = Some(current);
+            disk.conflict = None;
+            let _ = shared.events.send(StorageEvent::Reloaded);
+            return Ok(Some(OutsideChange::Merged));
+        }
+
+        warn!(
+            "{:?} changed outside the app in ways that clash with unsaved changes",
+            shared.file_path
+        );
+        disk.conflict = Some(PendingConflict {
+            info: Conflict {
+                file_path: shared.file_path.clone(),
+                detected_at: Local::now(),
+                fields: merged.conflicts,
+            },
+            theirs,
+            stamp: current,
+        });
+        let _ = shared.events.send(StorageEvent::Conflict);
+        Ok(Some(OutsideChange::Conflict))
+    }
+
+    // A database that can't be read may be mid-replace by a sync client, so it's given
+    // time to settle and then left alone; the data stays in memory for the next save
+    async fn save(shared: &Shared<T>, config: &StorageConfig) -> StorageResult<()> {
+        match Self::save_rows_blocking(shared, config).await {
+            Err(StorageError::Unreadable(_, e)) => {
+                debug!("{:?} can't be read yet, retrying: {}", shared.file_path, e);
+                tokio::time::sleep(SETTLE_DELAY).await;
+                Self::save_rows_blocking(shared, config).await
+            }
+            result => result,
+        }
+    }
+
+    async fn save_rows_blocking(shared: &Shared<T>, config: &StorageConfig) -> StorageResult<()> {
+        let local = shared.data.clone().write_owned().await;
+        let shared = shared.clone();
+        let config = config.clone();
+        tokio::task::spawn_blocking(move || Self::save_rows(&shared, &config, local))
+            .await
+            .map_err(|e| StorageError::Io(std::io::Error::other(e)))?
+    }
+
+    fn save_rows(
+        shared: &Shared<T>,
+        config: &StorageConfig,
+        mut local: OwnedRwLockWriteGuard<T>,
+    ) -> StorageResult<()> {
+        let file_path = &shared.file_path;
+        let mut conn = shared.conn.lock().map_err(|_| StorageError::LockError)?;
+        let mut disk = shared.disk.lock().map_err(|_| StorageError::LockError)?;
+
+        // The watcher may not have caught up with an outside change yet, and saving
+        // now would overwrite it
+        if let Err(e) = Self::check_database(shared, &mut conn, &mut disk, &mut local) {
+            warn!("Not saving over unreadable {:?}: {}", file_path, e);
+            return Err(StorageError::Unreadable(file_path.clone(), e.to_string()));
+        }
+        if disk.conflict.is_some() {
+            return Err(StorageError::Conflict(file_path.clone()));
+        }
+        let data = local.downgrade();
+
+        if conn.is_none() {
+            *conn = Some(open_connection::<T>(file_path)?);
+        }
+        let conn = conn.as_mut().ok_or(StorageError::LockError)?;
+
+        if config.backup_on_save {
+            if let Err(e) = Self::create_snapshot(conn, file_path, config) {
+                error!("Failed to update snapshots: {}", e);
+            }
+        }
+
+        let tx = conn.transaction()?;
+        T::persist(&tx, &disk.saved, &data)?;
+        tx.commit()?;
+        disk.saved = (*data).clone();
+        disk.stamp = Some(stamp(conn, file_path)?);
+        debug!("Successfully saved changed rows to {:?}", file_path);
+        Ok(())
+    }
+
+    // Same daily/weekly retention as file snapshots. There are no per-save backups,
+    // since copying the whole database on every save is what this backend avoids.
+    fn create_snapshot(
+        conn: &Connection,
+        file_path: &Path,
+        config: &StorageConfig,
+    ) -> StorageResult<()> {
+        if config.daily_snapshots == 0 && config.weekly_snapshots == 0 {
+            return Ok(());
+        }
+
+        let today = chrono::Local::now().date_naive();
+        let path = snapshot_path(file_path, today);
+        if !path.exists() {
+            conn.execute("VACUUM INTO ?1", [path.to_string_lossy()])?;
+            debug!("Created snapshot {:?}", path);
+        }
+
+        let snapshots = list_snapshots(file_path);
+        let dates: Vec<_> = snapshots.iter().map(|(date, _)| *date).collect();
+        let keep = snapshots_to_keep(&dates, config.daily_snapshots, config.weekly_snapshots);
+        for (date, path) in snapshots {
+            if !keep.contains(&date) {
+                debug!("Pruning snapshot {:?}", path);
+                std::fs::remove_file(&path)?;
+            }
+        }
+        Ok(())
+    }
+
+    // Snapshots newest first, each loaded so the caller can tell whether it is usable
+    pub async fn list_backups(&self) -> StorageResult<Vec<BackupInfo>> {
+        let file_path = self.shared.file_path.clone();
+        tokio::task::spawn_blocking(move || {
+            let mut backups = Vec::new();
+            for (date, path) in list_snapshots(&file_path) {
+                let metadata = std::fs::metadata(&path)?;
+                let preview = load_database::<T>(&path)
+                    .map_err(|e| e.to_string())
+                    .and_then(|data| serde_json::to_value(data).map_err(|e| e.to_string()));
+                backups.push(BackupInfo {
+                    modified: metadata.modified()?.into(),
+                    size: metadata.len(),
+                    path,
+                    kind: BackupKind::Snapshot(date),
+                    preview,
+                });
+            }
+            Ok(backups)
+        })
+        .await
+        .map_err(|e| StorageError::Io(std::io::Error::other(e)))?
+    }
+
+    async fn load_backup(&self, index: usize) -> StorageResult<T> {
+        let backups = self.list_backups().await?;
+        let path = backups
+            .get(index)
+            .map(|backup| backup.path.clone())
+            .ok_or(StorageError::BackupNotFound(index))?;
+        tokio::task::spawn_blocking(move || load_database::<T>(&path))
+            .await
+            .map_err(|e| StorageError::Io(std::io::Error::other(e)))?
+    }
+
+    pub async fn diff_backup(&self, index: usize) -> StorageResult<Vec<DiffEntry>> {
+        let backup = self.load_backup(index).await?;
+        let current = self.shared.data.read().await;
+        let current = serde_json::to_value(&*current)
+            .map_err(|e| StorageError::Serialization(e.to_string()))?;
+        let backup = serde_json::to_value(&backup)
+            .map_err(|e| StorageError::Serialization(e.to_string()))?;
+        Ok(diff_values(&current, &backup))
+    }
+
+    pub async fn restore_backup(&self, index: usize) -> StorageResult<PathBuf> {
+        let backups = self.list_backups().await?;
+        let path = backups
+            .get(index)
+            .map(|backup| backup.path.clone())
+            .ok_or(StorageError::BackupNotFound(index))?;
+        let data = self.load_backup(index).await?;
+
+        *self.corruption.write().await = None;
+        self.write(|store| *store = data).await?;
+        self.flush().await?;
+        info!("Restored {:?} from {:?}", self.shared.file_path, path);
+        Ok(path)
+    }
+
+    pub async fn restore_from_backup(&self) -> StorageResult<PathBuf> {
+        let backups = self.list_backups().await?;
+        match backups.iter().position(|backup| backup.preview.is_ok()) {
+            Some(index) => self.restore_backup(index).await,
+            None => Err(StorageError::NoValidBackup(self.shared.file_path.clone())),
+        }
+    }
+
+    pub async fn start_fresh(&self) -> StorageResult<()> {
+        *self.corruption.write().await = None;
+        self.force_save().await
+    }
+
+    pub async fn reload(&self) -> StorageResult<()> {
+        let mut local = self.shared.data.clone().write_owned().await;
+        let _ = self.writer().send(WriterMessage::Discard);
+
+        let shared = self.shared.clone();
+        tokio::task::spawn_blocking(move || -> StorageResult<()> {
+            let mut conn = shared.conn.lock().map_err(|_| StorageError::LockError)?;
+            let mut disk = shared.disk.lock().map_err(|_| StorageError::LockError)?;
+            // Reopened, in case the file was replaced under the old connection
+            let reopened = open_connection::<T>(&shared.file_path)?;
+            let data = T::load(&reopened)?;
+            disk.stamp = Some(stamp(&reopened, &shared.file_path)?);
+            disk.saved = data.clone();
+            disk.conflict = None;
+            *conn = Some(reopened);
+            *local = data;
+            Ok(())
+        })
+        .await
+        .map_err(|e| StorageError::Io(std::io::Error::other(e)))?
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{MergeConflict, Merged, Merger};
+    use rusqlite::params;
+
+    #[derive(Debug, Clone, Default, PartialEq, Serialize)]
+    struct Counters(BTreeMap<String, i64>);
+
+    impl SqliteModel for Counters {
+        const MIGRATIONS: &'static [&'static str] = &[
+            "CREATE TABLE counters (name TEXT PRIMARY KEY, value INTEGER NOT NULL);",
+            "CREATE TABLE writes (name TEXT NOT NULL);",
+        ];
+
+        fn load(conn: &Connection) -> rusqlite::Result<Self> {
+            let mut statement = conn.prepare("SELECT name, value FROM counters")?;
+            let rows = statement.query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?;
+            Ok(Counters(rows.collect::<rusqlite::Result<_>>()?))
+        }
+
+        fn persist(tx: &Transaction, before: &Self, after: &Self) -> rusqlite::Result<()> {
+            let changes = row_changes(&before.0, &after.0);
+            for (name, value) in changes.upserted {
+                tx.execute(
+                    "INSERT OR REPLACE INTO counters (name, value) VALUES (?1, ?2)",
+                    params![name, value],
+                )?;
+                tx.execute("INSERT INTO writes (name) VALUES (?1)", [name])?;
+            }
+            for name in changes.removed {
+                tx.execute("DELETE FROM counters WHERE name = ?1", [name])?;
+            }
+            Ok(())
+        }
+    }
+
+    impl Mergeable for Counters {
+        fn merge(base: &Self, mine: &Self, theirs: &Self) -> Merged<Self> {
+            let mut merger = Merger::default();
+            fn entries(counters: &Counters) -> impl Iterator<Item = (String, &i64)> {
+                counters.0.iter().map(|(name, value)| (name.clone(), value))
+            }
+            let merged = merger.entities(
+                "",
+                entries(base),
+                entries(mine),
+                entries(theirs),
+                |name, _| name.clone(),
+                |merger, path, base, mine, theirs| merger.value(path, base, mine, theirs),
+            );
+            merger.finish(Counters(merged.into_iter().collect()))
+        }
+    }
+
+    #[tokio::test]
+    async fn saves_only_changed_rows_and_reopens() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("counters.db");
+        let mut seed = Counters::default();
+        for name in ["a", "b", "c"] {
+            seed.0.insert(name.to_string(), 0);
+        }
+
+        let storage = SqliteStorage::open(
+            file_path.clone(),
+            StorageConfig::default(),
+            None,
+            Some(seed),
+        )
+        .unwrap();
+        storage
+            .write(|counters| {
+                counters.0.insert("b".to_string(), 5);
+                counters.0.remove("c");
+            })
+            .await
+            .unwrap();
+        storage.flush().await.unwrap();
+        drop(storage);
+
+        let conn = Connection::open(&file_path).unwrap();
+        let writes: Vec<String> = conn
+            .prepare("SELECT name FROM writes")
+            .unwrap()
+            .query_map([], |row| row.get(0))
+            .unwrap()
+            .collect::<rusqlite::Result<_>>()
+            .unwrap();
+        // Three rows from the import, then only the one that changed
+        assert_eq!(writes, vec!["a", "b", "c", "b"]);
+        drop(conn);
+
+        let storage =
+            SqliteStorage::<Counters>::open(file_path, StorageConfig::default(), None, None)
+                .unwrap();
+        let data = storage.get_data().await.unwrap();
+        assert_eq!(data.0.get("b"), Some(&5));
+        assert!(!data.0.contains_key("c"));
+
+        // The first save of the day kept the imported state as a snapshot
+        let backups = storage.list_backups().await.unwrap();
+        assert_eq!(backups.len(), 1);
+        assert_eq!(backups[0].summary(), "a, b, c");
+    }
+
+    #[test]
+    fn unreadable_database_is_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("counters.db");
+        std::fs::write(&file_path, "not a database").unwrap();
+
+        let storage = SqliteStorage::<Counters>::open(
+            file_path.clone(),
+            StorageConfig::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(!file_path.exists());
+        drop(storage);
+
+        // Nothing was created in its place, so it stays unresolved after a restart
+        let storage =
+            SqliteStorage::<Counters>::open(file_path, StorageConfig::default(), None, None)
+                .unwrap();
+        assert!(storage.corruption.try_read().unwrap().is_some());
+    }
+
+    #[tokio::test]
+    async fn database_from_a_newer_version_is_read_only() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("counters.db");
+        let conn = Connection::open(&file_path).unwrap();
+        for migration in Counters::MIGRATIONS {
+            conn.execute_batch(migration).unwrap();
+        }
+        conn.execute("INSERT INTO counters (name, value) VALUES ('a', 1)", [])
+            .unwrap();
+        conn.pragma_update(None, "user_version", 3).unwrap();
+        drop(conn);
+
+        let storage = SqliteStorage::<Counters>::open(
+            file_path.clone(),
+            StorageConfig::default(),
+            None,
+            None,
+        )
+        .unwrap();
+        assert!(file_path.exists());
+        assert!(storage.corruption().await.is_none());
+        assert_eq!(storage.newer_version(), Some(3));
+        assert_eq!(storage.get_data().await.unwrap().0.get("a"), Some(&1));
+
+        let result = storage.write(|counters| counters.0.clear()).await;
+        assert!(matches!(result, Err(StorageError::NewerVersion(_, 3))));
+        assert!(storage.force_save().await.is_err());
+        drop(storage);
+
+        let conn = Connection::open(&file_path).unwrap();
+        let version: i64 = conn
+            .pragma_query_value(None, "user_version", |row| row.get(0))
+            .unwrap();
+        assert_eq!(version, 3);
+    }
+
+    #[tokio::test]
+    async fn outside_changes_are_merged_or_flagged_before_saving() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("counters.db");
+        let config = StorageConfig {
+            save_debounce: std::time::Duration::from_secs(60),
+            ..Default::default()
+        };
+        let storage =
+            SqliteStorage::<Counters>::open(file_path.clone(), config, None, None).unwrap();
+        let write_elsewhere = |name: &str, value: i64| {
+            Connection::open(&file_path)
+                .unwrap()
+                .execute(
+                    "INSERT OR REPLACE INTO counters (name, value) VALUES (?1, ?2)",
+                    params![name, value],
+                )
+                .unwrap();
+        };
+        let on_disk = || load_database::<Counters>(&file_path).unwrap();
+
+        // Nothing local to lose, so the outside version is taken over
+        write_elsewhere("a", 1);
+        storage.force_save().await.unwrap();
+        assert_eq!(storage.get_data().await.unwrap().0.get("a"), Some(&1));
+
+        // Different rows changed on each side
+        storage
+            .write(|counters| {
+                counters.0.insert("b".to_string(), 2);
+            })
+            .await
+            .unwrap();
+        write_elsewhere("a", 3);
+        storage.flush().await.unwrap();
+        let saved = on_disk();
+        assert_eq!((saved.0.get("a"), saved.0.get("b")), (Some(&3), Some(&2)));
+
+        // The same row changed on both sides
+        storage
+            .write(|counters| {
+                counters.0.insert("a".to_string(), 4);
+            })
+            .await
+            .unwrap();
+        write_elsewhere("a", 5);
+        assert!(matches!(
+            storage.flush().await,
+            Err(StorageError::Conflict(_))
+        ));
+        assert_eq!(
+            storage.conflict().await.unwrap().fields,
+            vec![MergeConflict {
+                path: "a".to_string(),
+                mine: Some("4".to_string()),
+                theirs: Some("5".to_string()),
+            }]
+        );
+        assert_eq!(on_disk().0.get("a"), Some(&5));
+
+        storage
+            .resolve_conflict(ConflictResolution::KeepMine)
+            .await
+            .unwrap();
+        assert!(storage.conflict().await.is_none());
+        assert_eq!(on_disk().0.get("a"), Some(&4));
+    }
+}
@@ -1,11 +1,25 @@
-use super::{DataFormat, StorageConfig};
-use serde::{de::DeserializeOwned, Serialize};
+use super::backups::{backup_path, list_snapshots, snapshot_path, snapshots_to_keep};
+use super::crypto::{
+    encryption_state, is_sealed, open_document, read_document, write_document, EncryptionState,
+};
+use super::diff::{diff_values, DiffEntry};
+use super::merge::{MergeConflict, Mergeable};
+use super::schema::{decode, decode_newer, Versioned};
+use super::watcher::{watch_file, FileWatcher, StorageEvent, SETTLE_DELAY};
+use super::writer::{run_debounced, spawn_writer, WriterMessage};
+use super::{BackupInfo, BackupKind, DataFormat, StorageConfig};
+use chrono::{DateTime, Local};
+use serde::de::DeserializeOwned;
+use std::collections::hash_map::DefaultHasher;
+use std::hash::{Hash, Hasher};
+use std::io::Write;
 use std::marker::PhantomData;
-use std::path::PathBuf;
-use std::sync::Arc;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, OnceLock};
 use tokio::fs as async_fs;
-use tokio::sync::RwLock;
-use tracing::{debug, error, info};
+use tokio::sync::{broadcast, mpsc, oneshot, Mutex, RwLock};
+use tracing::{debug, error, info, warn};
+use uuid::Uuid;
 
 #[derive(Debug, thiserror::Error)]
 pub enum StorageError {
@@ -20,21 +34,129 @@ pub enum StorageError {
 
     #[error("Failed to initialize directory: {0}")]
     InitError(String),
+
+    #[error("Data file {} is corrupt and was moved to {}: {}", .0.file_path.display(), .0.quarantined_path.display(), .0.reason)]
+    Corrupt(Corruption),
+
+    #[error("No valid backup found for {}", .0.display())]
+    NoValidBackup(PathBuf),
+
+    #[error("Backup {0} does not exist")]
+    BackupNotFound(usize),
+
+    #[error("Database error: {0}")]
+    Sqlite(#[from] rusqlite::Error),
+
+    #[error("{} was changed elsewhere while there were unsaved changes", .0.display())]
+    Conflict(PathBuf),
+
+    #[error("{} is encrypted and the data hasn't been unlocked", .0.display())]
+    Locked(PathBuf),
+
+    #[error("{} can't be read, so it wasn't saved over: {1}", .0.display())]
+    Unreadable(PathBuf, String),
+
+    #[error("{} was written by a newer version of the app (schema {1}). Update the app to make changes.", .0.display())]
+    NewerVersion(PathBuf, u32),
+}
+
+// A data file that failed to parse and was moved aside instead of being overwritten
+#[derive(Debug, Clone, PartialEq)]
+pub struct Corruption {
+    pub file_path: PathBuf,
+    pub quarantined_path: PathBuf,
+    pub reason: String,
+}
+
+// The file changed outside the app while there were unsaved local edits, and some
+// of them clash with it
+#[derive(Debug, Clone, PartialEq)]
+pub struct Conflict {
+    pub file_path: PathBuf,
+    pub detected_at: DateTime<Local>,
+    pub fields: Vec<MergeConflict>,
+}
+
+// Both choices keep every change that didn't clash; they only pick the winner for
+// fields that were changed on both sides
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ConflictResolution {
+    KeepMine,
+    UseTheirs,
 }
 
 pub type StorageResult<T> = Result<T, StorageError>;
 
+// The file as we last read or wrote it, for telling outside changes apart from our own
+struct DiskState<T> {
+    fingerprint: Option<u64>,
+    // What that file holds, i.e. the last state both sides agreed on and the
+    // common ancestor when merging
+    base: T,
+    conflict: Option<PendingConflict<T>>,
+}
+
+struct PendingConflict<T> {
+    info: Conflict,
+    theirs: T,
+    fingerprint: u64,
+}
+
+impl<T> DiskState<T> {
+    // Takes the outside version as the new common state, without touching local data
+    fn accept_theirs(&mut self) {
+        if let Some(pending) = self.conflict.take() {
+            self.base = pending.theirs;
+            self.fingerprint = Some(pending.fingerprint);
+        }
+    }
+}
+
+// Handles shared by the storage and its writer and watcher tasks
+#[derive(Clone)]
+struct Shared<T> {
+    file_path: PathBuf,
+    data: Arc<RwLock<T>>,
+    disk: Arc<Mutex<DiskState<T>>>,
+    events: broadcast::Sender<StorageEvent>,
+}
+
+pub(super) enum OutsideChange {
+    Reloaded,
+    Merged,
+    Conflict,
+}
+
+fn fingerprint(bytes: &[u8]) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    bytes.hash(&mut hasher);
+    hasher.finish()
+}
+
 pub struct StorageManager<T, F: DataFormat> {
     file_path: PathBuf,
     data: Arc<RwLock<T>>,
     _format: PhantomData<F>,
     config: StorageConfig,
+    // Set while the file on disk is quarantined; reads and writes fail until resolved
+    corruption: Arc<RwLock<Option<Corruption>>>,
+    // The file's schema version when a newer build wrote it. The data is then only
+    // read, since saving would replace what the newer build wrote.
+    newer_version: Option<u32>,
+    disk: Arc<Mutex<DiskState<T>>>,
+    events: broadcast::Sender<StorageEvent>,
+    // Started on first use, since managers are built outside the async runtime. It
+    // saves the current data rather than a snapshot, since merging outside changes
+    // can update the data after a save was queued.
+    writer: OnceLock<mpsc::UnboundedSender<WriterMessage<()>>>,
+    // Started with the writer; dropping it ends the watch task
+    watcher: OnceLock<Option<FileWatcher>>,
 }
 
 impl<T, F> StorageManager<T, F>
 where
-    T: Default + Serialize + DeserializeOwned + Clone + Send + Sync + 'static,
-    F: DataFormat,
+    T: Versioned + Mergeable + Default + DeserializeOwned + Send + Sync + 'static,
+    F: DataFormat + 'static,
 {
     pub fn new(file_path: PathBuf) -> StorageResult<Self> {
         Self::with_config_and_default(file_path, StorageConfig::default(), None)
@@ -63,50 +185,140 @@ where
             }
         }
 
+        Self::remove_stale_temp_files(&file_path);
+
+        let mut corruption = None;
+        let mut newer_version = None;
+        let mut disk_fingerprint = None;
         let data = if file_path.exists() {
             debug!("Loading existing data file");
-            let content = std::fs::read_to_string(&file_path)?;
-            match F::deserialize(&content) {
+            let bytes = std::fs::read(&file_path)?;
+            let file_fingerprint = fingerprint(&bytes);
+            let content = String::from_utf8(bytes).map_err(|e| e.to_string());
+            // Not corrupt, just unreadable until the passphrase is entered
+            if content.as_deref().is_ok_and(is_sealed)
+                && encryption_state() == EncryptionState::Locked
+            {
+                return Err(StorageError::Locked(file_path));
+            }
+            let opened = content.and_then(|content| open_document(&content));
+            let loaded = match &opened {
+                Ok(content) => decode::<T, F>(content),
+                Err(e) => Err(e.clone()),
+            };
+            match loaded {
                 Ok(data) => {
                     info!("Successfully loaded data from file");
+                    disk_fingerprint = Some(file_fingerprint);
                     data
                 }
-                Err(e) => {
-                    error!("Failed to parse file, using default data: {}", e);
-                    default_data.unwrap_or_default()
-                }
+                Err(e) => match opened.as_deref().ok().and_then(decode_newer::<T, F>) {
+                    // Not corrupt either, and a synced folder would hand a quarantined
+                    // copy back to the newer build
+                    Some((version, data)) => {
+                        warn!(
+                            "{:?} was written by a newer version of the app (schema {}), opening it read-only",
+                            file_path, version
+                        );
+                        newer_version = Some(version);
+                        disk_fingerprint = Some(file_fingerprint);
+                        data.or(default_data).unwrap_or_default()
+                    }
+                    None => {
+                        error!("Failed to parse {:?}, quarantining it: {}", file_path, e);
+                        corruption = Some(quarantine(&file_path, e)?);
+                        default_data.unwrap_or_default()
+                    }
+                },
             }
+        } else if let Some(quarantined_path) = find_quarantined(&file_path) {
+            // A previous session quarantined the file and it was never resolved
+            warn!("Found unresolved quarantined file {:?}", quarantined_path);
+            corruption = Some(Corruption {
+                file_path: file_path.clone(),
+                quarantined_path,
+                reason: "The file was quarantined in an earlier session".to_string(),
+            });
+            default_data.unwrap_or_default()
         } else {
             debug!("No existing file found, using default data");
             let data = default_data.unwrap_or_default();
             if config.create_dirs {
-                let content = F::serialize(&data).map_err(|e| {
+                let content = write_document::<T, F>(&data).map_err(|e| {
                     error!("Failed to serialize default data: {}", e);
                     StorageError::Serialization(e.to_string())
                 })?;
 
-                std::fs::write(&file_path, &content).map_err(|e| {
+                write_atomic_sync(&file_path, &content).map_err(|e| {
                     error!("Failed to write default data to file: {}", e);
                     StorageError::Io(e)
                 })?;
+                disk_fingerprint = Some(fingerprint(content.as_bytes()));
 
                 debug!("Successfully wrote default data to file: {:?}", file_path);
             }
             data
         };
 
+        let disk = DiskState {
+            fingerprint: disk_fingerprint,
+            base: data.clone(),
+            conflict: None,
+        };
+
         Ok(Self {
             file_path,
             data: Arc::new(RwLock::new(data)),
             _format: PhantomData,
             config,
+            corruption: Arc::new(RwLock::new(corruption)),
+            newer_version,
+            disk: Arc::new(Mutex::new(disk)),
+            events: broadcast::channel(16).0,
+            writer: OnceLock::new(),
+            watcher: OnceLock::new(),
         })
     }
 
+    pub async fn corruption(&self) -> Option<Corruption> {
+        self.corruption.read().await.clone()
+    }
+
+    pub async fn conflict(&self) -> Option<Conflict> {
+        let disk = self.disk.lock().await;
+        disk.conflict.as_ref().map(|pending| pending.info.clone())
+    }
+
+    // Events for changes made to the file outside the app. Also starts watching it.
+    pub fn subscribe(&self) -> broadcast::Receiver<StorageEvent> {
+        self.writer();
+        self.events.subscribe()
+    }
+
+    pub fn newer_version(&self) -> Option<u32> {
+        self.newer_version
+    }
+
+    async fn ensure_healthy(&self) -> StorageResult<()> {
+        match self.corruption.read().await.as_ref() {
+            Some(corruption) => Err(StorageError::Corrupt(corruption.clone())),
+            None => Ok(()),
+        }
+    }
+
+    async fn ensure_writable(&self) -> StorageResult<()> {
+        self.ensure_healthy().await?;
+        match self.newer_version {
+            Some(version) => Err(StorageError::NewerVersion(self.file_path.clone(), version)),
+            None => Ok(()),
+        }
+    }
+
     pub async fn read<R, Func>(&self, f: Func) -> StorageResult<R>
     where
         Func: FnOnce(&T) -> R,
     {
+        self.ensure_healthy().await?;
         let guard = self.data.read().await;
         Ok(f(&guard))
     }
@@ -115,72 +327,793 @@ where
     where
         Func: FnOnce(&mut T) -> R,
     {
-        let mut guard = self.data.write().await;
-        let result = f(&mut guard);
+        self.ensure_writable().await?;
+        let result = f(&mut *self.data.write().await);
 
-        let data_clone = (*guard).clone();
-        let file_path = self.file_path.clone();
-        let config = self.config.clone();
+        if self.writer().send(WriterMessage::Save(())).is_err() {
+            error!("Storage writer for {:?} has stopped", self.file_path);
+        }
 
-        tokio::spawn(async move {
-            if let Err(e) = Self::save_to_disk(&file_path, &data_clone, &config).await {
-                error!("Failed to save data: {}", e);
+        Ok(result)
+    }
+
+    // Resolves once every write made before the call is on disk
+    pub async fn flush(&self) -> StorageResult<()> {
+        let (reply, done) = oneshot::channel();
+        if self.writer().send(WriterMessage::Flush(reply)).is_err() {
+            return Self::save_to_disk(&self.shared(), &self.config).await;
+        }
+        done.await.unwrap_or(Err(StorageError::LockError))
+    }
+
+    fn shared(&self) -> Shared<T> {
+        Shared {
+            file_path: self.file_path.clone(),
+            data: self.data.clone(),
+            disk: self.disk.clone(),
+            events: self.events.clone(),
+        }
+    }
+
+    fn writer(&self) -> &mpsc::UnboundedSender<WriterMessage<()>> {
+        self.writer.get_or_init(|| {
+            let (sender, receiver) = mpsc::unbounded_channel();
+            spawn_writer(Self::run_writer(
+                self.shared(),
+                self.config.clone(),
+                receiver,
+            ));
+            self.watcher
+                .get_or_init(|| self.start_watcher(sender.clone()));
+            sender
+        })
+    }
+
+    async fn run_writer(
+        shared: Shared<T>,
+        config: StorageConfig,
+        receiver: mpsc::UnboundedReceiver<WriterMessage<()>>,
+    ) {
+        run_debounced(config.save_debounce, receiver, |()| {
+            let shared = shared.clone();
+            let config = config.clone();
+            async move { Self::save_to_disk(&shared, &config).await }
+        })
+        .await;
+        debug!("Storage writer for {:?} stopped", shared.file_path);
+    }
+
+    fn start_watcher(
+        &self,
+        writer: mpsc::UnboundedSender<WriterMessage<()>>,
+    ) -> Option<FileWatcher> {
+        match watch_file(&self.file_path) {
+            Ok((watcher, changes)) => {
+                tokio::spawn(Self::run_watcher(
+                    self.shared(),
+                    self.corruption.clone(),
+                    writer,
+                    changes,
+                ));
+                Some(watcher)
+            }
+            Err(e) => {
+                warn!(
+                    "Not watching {:?} for outside changes: {}",
+                    self.file_path, e
+                );
+                None
             }
+        }
+    }
+
+    async fn run_watcher(
+        shared: Shared<T>,
+        corruption: Arc<RwLock<Option<Corruption>>>,
+        writer: mpsc::UnboundedSender<WriterMessage<()>>,
+        mut changes: mpsc::UnboundedReceiver<()>,
+    ) {
+        while changes.recv().await.is_some() {
+            tokio::time::sleep(SETTLE_DELAY).await;
+            while changes.try_recv().is_ok() {}
+
+            // A quarantined store is resolved by the user, not by whatever shows up on disk
+            if corruption.read().await.is_some() {
+                continue;
+            }
+            let mut disk = shared.disk.lock().await;
+            match Self::check_disk(&shared, &mut disk).await {
+                Ok(Some(OutsideChange::Reloaded)) => {
+                    let _ = writer.send(WriterMessage::Discard);
+                }
+                Ok(Some(OutsideChange::Merged)) => {
+                    let _ = writer.send(WriterMessage::Save(()));
+                }
+                Ok(_) => {}
+                Err(e) => warn!(
+                    "Failed to check {:?} for outside changes: {}",
+                    shared.file_path, e
+                ),
+            }
+        }
+        debug!("Stopped watching {:?}", shared.file_path);
+    }
+
+    // Folds a change made to the file outside the app into the data: taken over as is
+    // when there are no unsaved edits, merged with them otherwise. Edits that clash
+    // with it are left alone and flagged until the user picks a side.
+    async fn check_disk(
+        shared: &Shared<T>,
+        disk: &mut DiskState<T>,
+    ) -> StorageResult<Option<OutsideChange>> {
+        // Missing while a sync client replaces it; the next event picks it up
+        let Ok(bytes) = async_fs::read(&shared.file_path).await else {
+            return Ok(None);
+        };
+        let file_fingerprint = fingerprint(&bytes);
+        let already_seen = disk
+            .conflict
+            .as_ref()
+            .is_some_and(|pending| pending.fingerprint == file_fingerprint);
+        if disk.fingerprint == Some(file_fingerprint) || already_seen {
+            return Ok(None);
+        }
+
+        let content =
+            String::from_utf8(bytes).map_err(|e| StorageError::Serialization(e.to_string()))?;
+        let theirs = read_document::<T, F>(&content).map_err(StorageError::Serialization)?;
+
+        let mut local = shared.data.write().await;
+        let unsaved = serde_json::to_value(&*local).ok() != serde_json::to_value(&disk.base).ok();
+        if !unsaved {
+            info!("Reloading {:?} after an outside change", shared.file_path);
+            *local = theirs.clone();
+            disk.base = theirs;
+            disk.fingerprint = Some(file_fingerprint);
+            disk.conflict = None;
+            let _ = shared.events.send(StorageEvent::Reloaded);
+            return Ok(Some(OutsideChange::Reloaded));
+        }
+
+        let merged = T::merge(&disk.base, &local, &theirs);
+        if merged.conflicts.is_empty() {
+            info!("Merged an outside change into {:?}", shared.file_path);
+            *local = merged.data;
+            disk.base = theirs;
+            disk.fingerprint = Some(file_fingerprint);
+            disk.conflict = None;
+            let _ = shared.events.send(StorageEvent::Reloaded);
+            return Ok(Some(OutsideChange::Merged));
+        }
+
+        warn!(
+            "{:?} changed on disk in ways that clash with unsaved changes",
+            shared.file_path
+        );
+        disk.conflict = Some(PendingConflict {
+            info: Conflict {
+                file_path: shared.file_path.clone(),
+                detected_at: Local::now(),
+                fields: merged.conflicts,
+            },
+            theirs,
+            fingerprint: file_fingerprint,
         });
+        let _ = shared.events.send(StorageEvent::Conflict);
+        Ok(Some(OutsideChange::Conflict))
+    }
 
-        Ok(result)
+    pub async fn resolve_conflict(&self, resolution: ConflictResolution) -> StorageResult<()> {
+        {
+            let mut disk = self.disk.lock().await;
+            let Some(pending) = disk.conflict.as_ref() else {
+                return Ok(());
+            };
+            info!(
+                "Resolving conflict on {:?} with {:?}",
+                self.file_path, resolution
+            );
+
+            // Merged again rather than reusing the earlier result, since there may
+            // have been more edits since
+            let mut data = self.data.write().await;
+            let merged = match resolution {
+                ConflictResolution::KeepMine => T::merge(&disk.base, &data, &pending.theirs),
+                ConflictResolution::UseTheirs => T::merge(&disk.base, &pending.theirs, &data),
+            };
+            *data = merged.data;
+            disk.accept_theirs();
+        }
+        let _ = self.events.send(StorageEvent::Reloaded);
+        self.force_save().await
     }
 
     pub async fn get_data(&self) -> StorageResult<T> {
+        self.ensure_healthy().await?;
         let guard = self.data.read().await;
         Ok((*guard).clone())
     }
 
-    async fn save_to_disk(
-        file_path: &PathBuf,
-        data: &T,
-        config: &StorageConfig,
-    ) -> StorageResult<()> {
+    async fn save_to_disk(shared: &Shared<T>, config: &StorageConfig) -> StorageResult<()> {
+        let file_path = &shared.file_path;
+        let mut disk = shared.disk.lock().await;
+
+        // The watcher may not have caught up with an outside change yet, and saving
+        // now would overwrite it. A file that doesn't decode may be half written by
+        // a sync client, or encrypted on another device, so it's given time to
+        // settle and then left alone; the data stays in memory for the next save.
+        if let Err(e) = Self::check_disk(shared, &mut disk).await {
+            debug!("{:?} can't be read yet, retrying: {}", file_path, e);
+            drop(disk);
+            tokio::time::sleep(SETTLE_DELAY).await;
+            disk = shared.disk.lock().await;
+            if let Err(e) = Self::check_disk(shared, &mut disk).await {
+                warn!("Not saving over unreadable {:?}: {}", file_path, e);
+                return Err(StorageError::Unreadable(file_path.clone(), e.to_string()));
+            }
+        }
+        if disk.conflict.is_some() {
+            return Err(StorageError::Conflict(file_path.clone()));
+        }
+
         if config.backup_on_save && file_path.exists() {
             Self::create_backup(file_path, config.max_backups).await?;
+            if let Err(e) = Self::create_snapshot(file_path, config).await {
+                error!("Failed to update snapshots: {}", e);
+            }
         }
 
-        let content = F::serialize(data).map_err(StorageError::Serialization)?;
-        async_fs::write(file_path, content).await?;
+        let data = shared.data.read().await.clone();
+        let content = write_document::<T, F>(&data).map_err(StorageError::Serialization)?;
+        let path = file_path.clone();
+        let content_fingerprint = fingerprint(content.as_bytes());
+        tokio::task::spawn_blocking(move || write_atomic_sync(&path, &content))
+            .await
+            .map_err(|e| StorageError::Io(std::io::Error::other(e)))??;
+        disk.fingerprint = Some(content_fingerprint);
+        disk.base = data;
         debug!("Successfully saved data to disk");
 
         Ok(())
     }
 
     async fn create_backup(file_path: &PathBuf, max_backups: usize) -> StorageResult<()> {
-        let backup_path = file_path.with_extension("backup");
-
         for i in (1..max_backups).rev() {
-            let current = backup_path.with_extension(format!("backup{}", i));
-            let next = backup_path.with_extension(format!("backup{}", i + 1));
+            let current = backup_path(file_path, i);
+            let next = backup_path(file_path, i + 1);
             if current.exists() {
                 async_fs::rename(&current, &next).await?;
             }
         }
 
         if file_path.exists() {
-            async_fs::copy(file_path, backup_path.with_extension("backup1")).await?;
+            async_fs::copy(file_path, backup_path(file_path, 1)).await?;
             debug!("Created backup of data file");
         }
 
         Ok(())
     }
 
+    // Keeps the state from before the first save of each day, pruned to the configured
+    // daily and weekly retention
+    async fn create_snapshot(file_path: &Path, config: &StorageConfig) -> StorageResult<()> {
+        if config.daily_snapshots == 0 && config.weekly_snapshots == 0 {
+            return Ok(());
+        }
+
+        let today = chrono::Local::now().date_naive();
+        let path = snapshot_path(file_path, today);
+        if !path.exists() {
+            async_fs::copy(file_path, &path).await?;
+            debug!("Created snapshot {:?}", path);
+        }
+
+        let snapshots = list_snapshots(file_path);
+        let dates: Vec<_> = snapshots.iter().map(|(date, _)| *date).collect();
+        let keep = snapshots_to_keep(&dates, config.daily_snapshots, config.weekly_snapshots);
+        for (date, path) in snapshots {
+            if !keep.contains(&date) {
+                debug!("Pruning snapshot {:?}", path);
+                async_fs::remove_file(&path).await?;
+            }
+        }
+        Ok(())
+    }
+
+    // Rotated backups newest first, then snapshots newest first. Each one is parsed
+    // so the caller can tell whether it is usable before restoring it.
+    pub async fn list_backups(&self) -> StorageResult<Vec<BackupInfo>> {
+        let mut candidates: Vec<(BackupKind, PathBuf)> = (1..=self.config.max_backups)
+            .map(|i| (BackupKind::Rotated(i), backup_path(&self.file_path, i)))
+            .filter(|(_, path)| path.exists())
+            .collect();
+        candidates.extend(
+            list_snapshots(&self.file_path)
+                .into_iter()
+                .map(|(date, path)| (BackupKind::Snapshot(date), path)),
+        );
+
+        let mut backups = Vec::new();
+        for (kind, path) in candidates {
+            let metadata = async_fs::metadata(&path).await?;
+            let preview = async_fs::read_to_string(&path)
+                .await
+                .map_err(|e| e.to_string())
+                .and_then(|content| read_document::<T, F>(&content))
+                .and_then(|data| serde_json::to_value(data).map_err(|e| e.to_string()));
+            backups.push(BackupInfo {
+                modified: metadata.modified()?.into(),
+                size: metadata.len(),
+                path,
+                kind,
+                preview,
+            });
+        }
+        Ok(backups)
+    }
+
+    // What restoring backup `index` (as ordered by `list_backups`) would change
+    pub async fn diff_backup(&self, index: usize) -> StorageResult<Vec<DiffEntry>> {
+        let backup = self.load_backup(index).await?;
+        let current = self.data.read().await;
+        let current = serde_json::to_value(&*current)
+            .map_err(|e| StorageError::Serialization(e.to_string()))?;
+        let backup = serde_json::to_value(&backup)
+            .map_err(|e| StorageError::Serialization(e.to_string()))?;
+        Ok(diff_values(&current, &backup))
+    }
+
+    // Restores backup `index` (as ordered by `list_backups`). The current state goes
+    // through the normal save path, so it becomes the newest rotated backup.
+    pub async fn restore_backup(&self, index: usize) -> StorageResult<PathBuf> {
+        let backups = self.list_backups().await?;
+        let path = backups
+            .get(index)
+            .map(|backup| backup.path.clone())
+            .ok_or(StorageError::BackupNotFound(index))?;
+        let data = self.load_backup(index).await?;
+
+        *self.corruption.write().await = None;
+        // Restoring is a deliberate overwrite, so a pending conflict is settled by it
+        self.disk.lock().await.accept_theirs();
+        self.write(|store| *store = data).await?;
+        self.flush().await?;
+        info!("Restored {:?} from {:?}", self.file_path, path);
+        Ok(path)
+    }
+
+    async fn load_backup(&self, index: usize) -> StorageResult<T> {
+        let backups = self.list_backups().await?;
+        let backup = backups
+            .get(index)
+            .ok_or(StorageError::BackupNotFound(index))?;
+        let content = async_fs::read_to_string(&backup.path).await?;
+        read_document::<T, F>(&content).map_err(StorageError::Serialization)
+    }
+
+    // Restores the newest backup that parses and clears the quarantine.
+    // Backups are untouched while quarantined, since nothing is saved in that state.
+    pub async fn restore_from_backup(&self) -> StorageResult<PathBuf> {
+        let backups = self.list_backups().await?;
+        match backups.iter().position(|backup| backup.preview.is_ok()) {
+            Some(index) => self.restore_backup(index).await,
+            None => Err(StorageError::NoValidBackup(self.file_path.clone())),
+        }
+    }
+
+    // Gives up on the quarantined file and keeps the default data in its place
+    pub async fn start_fresh(&self) -> StorageResult<()> {
+        *self.corruption.write().await = None;
+        self.force_save().await
+    }
+
+    // Leftovers from a write that was interrupted before its rename
+    fn remove_stale_temp_files(file_path: &Path) {
+        let (Some(dir), Some(name)) = (file_path.parent(), file_path.file_name()) else {
+            return;
+        };
+        let prefix = format!(".{}.", name.to_string_lossy());
+        let Ok(entries) = std::fs::read_dir(dir) else {
+            return;
+        };
+        for entry in entries.flatten() {
+            let entry_name = entry.file_name().to_string_lossy().to_string();
+            if entry_name.starts_with(&prefix) && entry_name.ends_with(".tmp") {
+                debug!("Removing stale temp file {:?}", entry.path());
+                let _ = std::fs::remove_file(entry.path());
+            }
+        }
+    }
+
+    // Queues the current state even without changes, then waits for it to land
     pub async fn force_save(&self) -> StorageResult<()> {
-        let data = self.data.read().await;
-        Self::save_to_disk(&self.file_path, &data, &self.config).await
+        self.ensure_writable().await?;
+        let _ = self.writer().send(WriterMessage::Save(()));
+        self.flush().await
     }
 
     pub async fn reload(&self) -> StorageResult<()> {
-        let content = async_fs::read_to_string(&self.file_path).await?;
-        let new_data = F::deserialize(&content).map_err(StorageError::Serialization)?;
+        let mut disk = self.disk.lock().await;
+        let bytes = async_fs::read(&self.file_path).await?;
+        let file_fingerprint = fingerprint(&bytes);
+        let content =
+            String::from_utf8(bytes).map_err(|e| StorageError::Serialization(e.to_string()))?;
+        let new_data = read_document::<T, F>(&content).map_err(StorageError::Serialization)?;
         let mut guard = self.data.write().await;
-        *guard = new_data;
+        let _ = self.writer().send(WriterMessage::Discard);
+        *guard = new_data.clone();
+        disk.base = new_data;
+        disk.fingerprint = Some(file_fingerprint);
+        disk.conflict = None;
+        Ok(())
+    }
+}
+
+// Moves an unreadable data file aside so nothing overwrites it
+pub(super) fn quarantine(file_path: &Path, reason: String) -> StorageResult<Corruption> {
+    let quarantined_path = PathBuf::from(format!(
+        "{}.corrupt-{}",
+        file_path.display(),
+        chrono::Local::now().format("%Y%m%dT%H%M%S")
+    ));
+    std::fs::rename(file_path, &quarantined_path)?;
+    Ok(Corruption {
+        file_path: file_path.to_path_buf(),
+        quarantined_path,
+        reason,
+    })
+}
+
+// Newest `habits.json.corrupt-<timestamp>` next to the data file, if any
+pub(super) fn find_quarantined(file_path: &Path) -> Option<PathBuf> {
+    let dir = file_path.parent()?;
+    let prefix = format!("{}.corrupt-", file_path.file_name()?.to_string_lossy());
+    std::fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| {
+            path.file_name()
+                .is_some_and(|name| name.to_string_lossy().starts_with(&prefix))
+        })
+        .max()
+}
+
+// Write to a temp file next to the target, fsync it, then rename over the target,
+// so readers only ever see the old or the new content in full
+pub(super) fn write_atomic_sync(file_path: &Path, content: &str) -> std::io::Result<()> {
+    let dir = file_path.parent().unwrap_or_else(|| Path::new("."));
+    let name = file_path
+        .file_name()
+        .map(|n| n.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let temp_path = dir.join(format!(".{}.{}.tmp", name, Uuid::new_v4().simple()));
+
+    let result = (|| {
+        let mut file = std::fs::File::create(&temp_path)?;
+        file.write_all(content.as_bytes())?;
+        file.sync_all()?;
+        std::fs::rename(&temp_path, file_path)?;
+        // Persist the rename itself; directories can't be opened for sync on every platform
+        #[cfg(unix)]
+        if let Ok(dir) = std::fs::File::open(dir) {
+            let _ = dir.sync_all();
+        }
         Ok(())
+    })();
+
+    if result.is_err() {
+        let _ = std::fs::remove_file(&temp_path);
+    }
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::{block_on_writers, decode, encode, JsonFormat, Merged, Merger};
+    use std::collections::HashMap;
+
+    type Store = HashMap<String, u32>;
+
+    impl Versioned for Store {
+        const SCHEMA_VERSION: u32 = 1;
+        const SCHEMA_MIGRATIONS: &'static [crate::storage::Migration] = &[];
+    }
+
+    impl Mergeable for Store {
+        fn merge(base: &Self, mine: &Self, theirs: &Self) -> Merged<Self> {
+            let mut merger = Merger::default();
+            fn entries(store: &Store) -> impl Iterator<Item = (String, &u32)> {
+                store.iter().map(|(key, value)| (key.clone(), value))
+            }
+            let merged = merger.entities(
+                "",
+                entries(base),
+                entries(mine),
+                entries(theirs),
+                |key, _| key.clone(),
+                |merger, path, base, mine, theirs| merger.value(path, base, mine, theirs),
+            );
+            merger.finish(merged.into_iter().collect())
+        }
+    }
+
+    fn read_on_disk(file_path: &Path) -> Store {
+        decode::<Store, JsonFormat>(&std::fs::read_to_string(file_path).unwrap()).unwrap()
+    }
+
+    #[tokio::test]
+    async fn corrupt_file_is_quarantined_until_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("habits.json");
+
+        std::fs::write(&file_path, "{\"a\": 1, \"b\"").unwrap();
+        std::fs::write(backup_path(&file_path, 1), "{\"a\": ").unwrap();
+        std::fs::write(backup_path(&file_path, 2), "{\"a\": 2}").unwrap();
+        std::fs::write(dir.path().join(".habits.json.1234.tmp"), "{").unwrap();
+
+        let storage = StorageManager::<Store, JsonFormat>::new(file_path.clone()).unwrap();
+        let corruption = storage.corruption().await.unwrap();
+        assert!(!file_path.exists());
+        assert_eq!(
+            std::fs::read_to_string(&corruption.quarantined_path).unwrap(),
+            "{\"a\": 1, \"b\""
+        );
+        assert!(!dir.path().join(".habits.json.1234.tmp").exists());
+        assert!(matches!(
+            storage.write(|store| store.clear()).await,
+            Err(StorageError::Corrupt(_))
+        ));
+
+        // Still unresolved after a restart
+        drop(storage);
+        let storage = StorageManager::<Store, JsonFormat>::new(file_path.clone()).unwrap();
+        assert!(storage.corruption().await.is_some());
+
+        let restored_from = storage.restore_from_backup().await.unwrap();
+        assert_eq!(restored_from, backup_path(&file_path, 2));
+        assert!(storage.corruption().await.is_none());
+        assert_eq!(storage.get_data().await.unwrap().get("a"), Some(&2));
+        let on_disk = read_on_disk(&file_path);
+        assert_eq!(on_disk.get("a"), Some(&2));
+    }
+
+    #[tokio::test]
+    async fn file_from_a_newer_version_is_read_only_and_not_quarantined() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("habits.json");
+        let content = "{\"version\": 99, \"data\": {\"a\": 1}}";
+        std::fs::write(&file_path, content).unwrap();
+
+        let storage = StorageManager::<Store, JsonFormat>::new(file_path.clone()).unwrap();
+        assert!(storage.corruption().await.is_none());
+        assert_eq!(storage.newer_version(), Some(99));
+        assert_eq!(storage.read(|store| store["a"]).await.unwrap(), 1);
+        assert!(matches!(
+            storage.write(|store| store.clear()).await,
+            Err(StorageError::NewerVersion(_, 99))
+        ));
+        assert!(storage.force_save().await.is_err());
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), content);
+        assert!(find_quarantined(&file_path).is_none());
+    }
+
+    #[tokio::test]
+    async fn burst_of_writes_saves_latest_state_on_flush() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("todos.json");
+        let config = StorageConfig {
+            save_debounce: std::time::Duration::from_secs(60),
+            ..Default::default()
+        };
+        let storage =
+            StorageManager::<Store, JsonFormat>::with_config(file_path.clone(), config).unwrap();
+
+        for i in 0..50 {
+            storage
+                .write(|store| {
+                    store.insert("count".to_string(), i);
+                })
+                .await
+                .unwrap();
+        }
+
+        // Still inside the debounce window, so nothing but the initial file yet
+        let on_disk = read_on_disk(&file_path);
+        assert!(on_disk.is_empty());
+
+        storage.flush().await.unwrap();
+        let on_disk = read_on_disk(&file_path);
+        assert_eq!(on_disk.get("count"), Some(&49));
+        // One save means exactly one rotated backup
+        assert!(backup_path(&file_path, 1).exists());
+        assert!(!backup_path(&file_path, 2).exists());
+    }
+
+    #[tokio::test(flavor = "current_thread")]
+    async fn blocking_flush_finishes_while_a_single_threaded_runtime_waits() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("todos.json");
+        let config = StorageConfig {
+            save_debounce: std::time::Duration::from_secs(60),
+            ..Default::default()
+        };
+        let storage = Arc::new(
+            StorageManager::<Store, JsonFormat>::with_config(file_path.clone(), config).unwrap(),
+        );
+        storage
+            .write(|store| {
+                store.insert("count".to_string(), 7);
+            })
+            .await
+            .unwrap();
+
+        // Blocks the runtime's only thread, like a drop handler on shutdown
+        let flushing = storage.clone();
+        block_on_writers(async move { flushing.flush().await })
+            .unwrap()
+            .unwrap();
+        assert_eq!(read_on_disk(&file_path).get("count"), Some(&7));
+    }
+
+    #[tokio::test]
+    async fn backups_can_be_listed_diffed_and_restored() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("habits.json");
+        let storage = StorageManager::<Store, JsonFormat>::new(file_path.clone()).unwrap();
+
+        for value in [1, 2] {
+            storage
+                .write(|store| {
+                    store.insert("a".to_string(), value);
+                })
+                .await
+                .unwrap();
+            storage.flush().await.unwrap();
+        }
+
+        let backups = storage.list_backups().await.unwrap();
+        let kinds: Vec<_> = backups.iter().map(|b| b.kind.clone()).collect();
+        let today = chrono::Local::now().date_naive();
+        assert_eq!(
+            kinds,
+            vec![
+                BackupKind::Rotated(1),
+                BackupKind::Rotated(2),
+                BackupKind::Snapshot(today)
+            ]
+        );
+        assert_eq!(backups[0].summary(), "a");
+
+        let diff = storage.diff_backup(0).await.unwrap();
+        assert_eq!(diff.len(), 1);
+        assert_eq!(diff[0].before.as_deref(), Some("2"));
+        assert_eq!(diff[0].after.as_deref(), Some("1"));
+
+        storage.restore_backup(0).await.unwrap();
+        assert_eq!(storage.get_data().await.unwrap().get("a"), Some(&1));
+        // The state before the restore is kept as the newest backup
+        let content = std::fs::read_to_string(backup_path(&file_path, 1)).unwrap();
+        assert!(content.contains('2'));
+    }
+
+    #[tokio::test]
+    async fn unreadable_file_is_not_saved_over() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("habits.json");
+        let storage = StorageManager::<Store, JsonFormat>::new(file_path.clone()).unwrap();
+        storage.flush().await.unwrap();
+
+        // Half written by a sync client
+        write_atomic_sync(&file_path, "{\"version\": 1, \"da").unwrap();
+        storage
+            .write(|store| {
+                store.insert("a".to_string(), 1);
+            })
+            .await
+            .unwrap();
+        assert!(matches!(
+            storage.flush().await,
+            Err(StorageError::Unreadable(..))
+        ));
+        assert_eq!(
+            std::fs::read_to_string(&file_path).unwrap(),
+            "{\"version\": 1, \"da"
+        );
+
+        // Once the sync finishes, the edit is saved on top of it
+        write_atomic_sync(&file_path, &encode::<_, JsonFormat>(&Store::new()).unwrap()).unwrap();
+        storage.force_save().await.unwrap();
+        assert_eq!(read_on_disk(&file_path).get("a"), Some(&1));
+    }
+
+    #[tokio::test]
+    async fn outside_changes_are_merged_or_flagged() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("habits.json");
+        let config = StorageConfig {
+            save_debounce: std::time::Duration::from_secs(60),
+            ..Default::default()
+        };
+        let storage =
+            StorageManager::<Store, JsonFormat>::with_config(file_path.clone(), config).unwrap();
+        let mut events = storage.subscribe();
+        let timeout = std::time::Duration::from_secs(10);
+        let write_elsewhere = |entries: &[(&str, u32)]| {
+            let store: Store = entries
+                .iter()
+                .map(|(key, value)| (key.to_string(), *value))
+                .collect();
+            write_atomic_sync(&file_path, &encode::<_, JsonFormat>(&store).unwrap()).unwrap();
+        };
+
+        // Nothing local to lose, so the outside version is taken over
+        write_elsewhere(&[("a", 1)]);
+        let event = tokio::time::timeout(timeout, events.recv()).await.unwrap();
+        assert_eq!(event.unwrap(), StorageEvent::Reloaded);
+        assert_eq!(storage.get_data().await.unwrap().get("a"), Some(&1));
+
+        // Different keys changed on each side
+        storage
+            .write(|store| {
+                store.insert("b".to_string(), 2);
+            })
+            .await
+            .unwrap();
+        write_elsewhere(&[("a", 3)]);
+        let event = tokio::time::timeout(timeout, events.recv()).await.unwrap();
+        assert_eq!(event.unwrap(), StorageEvent::Reloaded);
+        storage.flush().await.unwrap();
+        let on_disk = read_on_disk(&file_path);
+        assert_eq!((on_disk.get("a"), on_disk.get("b")), (Some(&3), Some(&2)));
+
+        // The same key changed on both sides
+        storage
+            .write(|store| {
+                store.insert("a".to_string(), 4);
+            })
+            .await
+            .unwrap();
+        write_elsewhere(&[("a", 5), ("b", 2)]);
+        let event = tokio::time::timeout(timeout, events.recv()).await.unwrap();
+        assert_eq!(event.unwrap(), StorageEvent::Conflict);
+        let conflict = storage.conflict().await.unwrap();
+        assert_eq!(
+            conflict.fields,
+            vec![MergeConflict {
+                path: "a".to_string(),
+                mine: Some("4".to_string()),
+                theirs: Some("5".to_string()),
+            }]
+        );
+        assert!(matches!(
+            storage.flush().await,
+            Err(StorageError::Conflict(_))
+        ));
+        assert_eq!(read_on_disk(&file_path).get("a"), Some(&5));
+
+        storage
+            .resolve_conflict(ConflictResolution::KeepMine)
+            .await
+            .unwrap();
+        assert!(storage.conflict().await.is_none());
+        let on_disk = read_on_disk(&file_path);
+        assert_eq!((on_disk.get("a"), on_disk.get("b")), (Some(&4), Some(&2)));
+        // The outside version is kept as the newest backup
+        let backup = std::fs::read_to_string(backup_path(&file_path, 1)).unwrap();
+        assert!(backup.contains('5'));
+    }
+
+    #[test]
+    fn atomic_write_replaces_content() {
+        let dir = tempfile::tempdir().unwrap();
+        let file_path = dir.path().join("todos.json");
+
+        write_atomic_sync(&file_path, "first").unwrap();
+        write_atomic_sync(&file_path, "second").unwrap();
+
+        assert_eq!(std::fs::read_to_string(&file_path).unwrap(), "second");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
     }
 }
@@ -0,0 +1,54 @@
+// storage/watcher.rs
+use notify::{Config, Event, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+use tokio::sync::mpsc;
+
+// inotify tells us about changes as they happen; elsewhere the file is polled
+#[cfg(target_os = "linux")]
+pub(super) type FileWatcher = notify::INotifyWatcher;
+#[cfg(not(target_os = "linux"))]
+pub(super) type FileWatcher = notify::PollWatcher;
+
+// Only used by the polling watcher
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+// Sync clients tend to write a file in several steps, so events are allowed to
+// settle before the file is read
+pub(super) const SETTLE_DELAY: Duration = Duration::from_millis(200);
+
+// Sent to subscribers when the file changed outside the app
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum StorageEvent {
+    // The outside change was taken over; the in-memory data now matches the file
+    Reloaded,
+    // The file changed while there were unsaved local edits. Nothing is saved
+    // until the conflict is resolved.
+    Conflict,
+}
+
+// Watches the directory rather than the file itself, since atomic saves
+// (ours and most sync clients') replace the file with a new one
+pub(super) fn watch_file(
+    file_path: &Path,
+) -> notify::Result<(FileWatcher, mpsc::UnboundedReceiver<()>)> {
+    let dir = file_path
+        .parent()
+        .unwrap_or_else(|| Path::new("."))
+        .to_path_buf();
+    let target: PathBuf = file_path.to_path_buf();
+    let (sender, receiver) = mpsc::unbounded_channel();
+
+    let mut watcher = FileWatcher::new(
+        move |event: notify::Result<Event>| {
+            if let Ok(event) = event {
+                if event.paths.iter().any(|path| path == &target) {
+                    let _ = sender.send(());
+                }
+            }
+        },
+        Config::default().with_poll_interval(POLL_INTERVAL),
+    )?;
+    watcher.watch(&dir, RecursiveMode::NonRecursive)?;
+    Ok((watcher, receiver))
+}
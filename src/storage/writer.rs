@@ -0,0 +1,105 @@
+// storage/writer.rs
+use super::storage_manager::StorageResult;
+use once_cell::sync::Lazy;
+use std::future::Future;
+use std::time::Duration;
+use tokio::runtime::Runtime;
+use tokio::sync::{mpsc, oneshot};
+use tokio::time::Instant;
+use tracing::error;
+
+pub(super) enum WriterMessage<T> {
+    Save(T),
+    Flush(oneshot::Sender<StorageResult<()>>),
+    // Drop the pending snapshot, e.g. because the data was reloaded from disk
+    Discard,
+}
+
+// Writers get a runtime of their own. On shutdown the app's runtime is blocked
+// while it waits for pending saves, so those saves have to run elsewhere.
+static WRITER_RUNTIME: Lazy<Runtime> = Lazy::new(|| {
+    tokio::runtime::Builder::new_multi_thread()
+        .worker_threads(1)
+        .thread_name("storage-writer")
+        .enable_all()
+        .build()
+        .expect("Failed to start the storage writer runtime")
+});
+
+pub(super) fn spawn_writer<F>(task: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    WRITER_RUNTIME.spawn(task);
+}
+
+// Runs `future` to completion from synchronous code, e.g. a drop handler that
+// flushes. Runs on a thread of its own, so it works while the caller's runtime,
+// whatever its flavor, is blocked waiting for it.
+pub fn block_on_writers<F>(future: F) -> Result<F::Output, String>
+where
+    F: Future + Send + 'static,
+    F::Output: Send + 'static,
+{
+    std::thread::spawn(move || WRITER_RUNTIME.block_on(future))
+        .join()
+        .map_err(|_| "Blocking on the storage writers panicked".to_string())
+}
+
+// Keeps only the newest snapshot and saves it once the debounce window
+// closes, on flush, or when the storage is dropped
+pub(super) async fn run_debounced<T, S, Fut>(
+    debounce: Duration,
+    mut receiver: mpsc::UnboundedReceiver<WriterMessage<T>>,
+    mut save: S,
+) where
+    S: FnMut(T) -> Fut,
+    Fut: Future<Output = StorageResult<()>>,
+{
+    let mut pending: Option<(T, Instant)> = None;
+
+    loop {
+        let message = match &pending {
+            Some((_, deadline)) => {
+                match tokio::time::timeout_at(*deadline, receiver.recv()).await {
+                    Ok(message) => message,
+                    Err(_) => {
+                        if let Some((data, _)) = pending.take() {
+                            if let Err(e) = save(data).await {
+                                error!("Failed to save data: {}", e);
+                            }
+                        }
+                        continue;
+                    }
+                }
+            }
+            None => receiver.recv().await,
+        };
+
+        match message {
+            Some(WriterMessage::Save(data)) => {
+                let deadline = pending
+                    .take()
+                    .map(|(_, deadline)| deadline)
+                    .unwrap_or_else(|| Instant::now() + debounce);
+                pending = Some((data, deadline));
+            }
+            Some(WriterMessage::Flush(reply)) => {
+                let result = match pending.take() {
+                    Some((data, _)) => save(data).await,
+                    None => Ok(()),
+                };
+                let _ = reply.send(result);
+            }
+            Some(WriterMessage::Discard) => pending = None,
+            None => {
+                if let Some((data, _)) = pending.take() {
+                    if let Err(e) = save(data).await {
+                        error!("Failed to save data: {}", e);
+                    }
+                }
+                break;
+            }
+        }
+    }
+}
@@ -0,0 +1,274 @@
+// Calendar math for the timeline grids. Cells step by whole calendar months, ISO
+// weeks or days, so they stay lined up with the dates periods and events start on.
+use crate::utils::date_utils::week_start_monday;
+use chrono::{Datelike, Duration, Months, NaiveDate};
+
+// Columns of the weeks grid, one row per year of life
+pub const WEEKS_PER_YEAR: u32 = 52;
+
+// `YYYY-MM` -> the first day of that month
+pub fn month_start(year_month: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(&format!("{}-01", year_month), "%Y-%m-%d").ok()
+}
+
+// `YYYY-MM-DD`
+pub fn day(date: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(date, "%Y-%m-%d").ok()
+}
+
+// `n` calendar months after `start`. Days past the end of the target month are
+// clamped to its last day; month starts are always exact.
+pub fn nth_month(start: NaiveDate, n: u32) -> NaiveDate {
+    start
+        .checked_add_months(Months::new(n))
+        .unwrap_or(NaiveDate::MAX)
+}
+
+// The first `count` months from `start`, one date per month
+pub fn months(start: NaiveDate, count: u32) -> impl Iterator<Item = NaiveDate> {
+    (0..count).map(move |n| nth_month(start, n))
+}
+
+// Every day from `start` up to, but not including, `end`
+pub fn days(start: NaiveDate, end: NaiveDate) -> impl Iterator<Item = NaiveDate> {
+    let count = (end - start).num_days().max(0);
+    (0..count).map(move |n| start + Duration::days(n))
+}
+
+// Whole calendar months from `from` to `to`
+pub fn months_between(from: NaiveDate, to: NaiveDate) -> i32 {
+    let months = (to.year() - from.year()) * 12 + to.month0() as i32 - from.month0() as i32;
+    if to.day() < from.day() {
+        months - 1
+    } else {
+        months
+    }
+}
+
+// Consecutive stretches of `step` months from `start`, the last one cut off at `end`
+pub fn month_steps(start: NaiveDate, end: NaiveDate, step: u32) -> Vec<(NaiveDate, NaiveDate)> {
+    (0..)
+        .map(|n| nth_month(start, n * step))
+        .take_while(|from| *from < end)
+        .map(|from| (from, nth_month(from, step).min(end)))
+        .collect()
+}
+
+// The ISO weeks overlapping `start` up to `end`, each as its Monday and the next one
+pub fn week_steps(start: NaiveDate, end: NaiveDate) -> Vec<(NaiveDate, NaiveDate)> {
+    std::iter::successors(Some(week_start_monday(start)), |week| {
+        Some(*week + Duration::weeks(1))
+    })
+    .take_while(|week| *week < end)
+    .map(|week| (week, week + Duration::weeks(1)))
+    .collect()
+}
+
+// The ISO weeks (starting on Monday) of each year of life, as each cell's first day
+// and the day after its last. Row `n` begins with the week holding the first day
+// of the `n`th birthday month. 52 weeks fall a day or two short of a year, so in
+// the years with a 53rd week the last cell of the row covers two weeks.
+pub fn life_weeks(birth: NaiveDate, years: u32) -> impl Iterator<Item = (NaiveDate, NaiveDate)> {
+    let row_start = move |year: u32| week_start_monday(nth_month(birth, year * 12));
+    (0..years).flat_map(move |year| {
+        let first = row_start(year);
+        let next_row = row_start(year + 1);
+        (0..WEEKS_PER_YEAR).map(move |week| {
+            let start = first + Duration::weeks(week as i64);
+            let end = if week + 1 == WEEKS_PER_YEAR {
+                next_row
+            } else {
+                start + Duration::weeks(1)
+            };
+            (start, end)
+        })
+    })
+}
+
+// The stretch of time an item covers: from its own start until the next item starts
+#[derive(Debug, PartialEq)]
+pub struct Span<'a, T> {
+    pub start: NaiveDate,
+    pub end: NaiveDate,
+    pub item: &'a T,
+}
+
+// Lays `items` end to end by start date, the last one running until `last_end`.
+// Items whose start can't be parsed are left out.
+pub fn spans<'a, T>(
+    items: &'a [T],
+    start: impl Fn(&T) -> Option<NaiveDate>,
+    last_end: NaiveDate,
+) -> Vec<Span<'a, T>> {
+    bounded_spans(items, start, |_| None, last_end)
+}
+
+// Like `spans`, but an item with an `end` of its own stops there if that comes
+// before the next item starts, leaving a gap
+pub fn bounded_spans<'a, T>(
+    items: &'a [T],
+    start: impl Fn(&T) -> Option<NaiveDate>,
+    end: impl Fn(&T) -> Option<NaiveDate>,
+    last_end: NaiveDate,
+) -> Vec<Span<'a, T>> {
+    let mut starts: Vec<(NaiveDate, &T)> = items
+        .iter()
+        .filter_map(|item| start(item).map(|date| (date, item)))
+        .collect();
+    starts.sort_by_key(|(date, _)| *date);
+
+    let next_starts: Vec<Option<NaiveDate>> = starts
+        .iter()
+        .skip(1)
+        .map(|(date, _)| Some(*date))
+        .chain(std::iter::once(None))
+        .collect();
+    starts
+        .into_iter()
+        .zip(next_starts)
+        .map(|((start, item), next)| {
+            let end = match (end(item), next) {
+                (Some(end), Some(next)) => end.min(next),
+                (Some(end), None) => end,
+                (None, Some(next)) => next,
+                (None, None) => last_end,
+            };
+            Span { start, end, item }
+        })
+        .collect()
+}
+
+// The span covering `date`, if any
+pub fn span_at<'s, 'a, T>(spans: &'s [Span<'a, T>], date: NaiveDate) -> Option<&'s Span<'a, T>> {
+    let index = spans.partition_point(|span| span.start <= date);
+    spans[..index]
+        .last()
+        .filter(|span| date >= span.start && date < span.end)
+}
+
+// The span covering most of `start` up to `end`, if any covers part of it. Cells
+// that fall on a boundary go to the later span when both cover as much.
+pub fn dominant_span<'s, 'a, T>(
+    spans: &'s [Span<'a, T>],
+    start: NaiveDate,
+    end: NaiveDate,
+) -> Option<&'s Span<'a, T>> {
+    let first = spans
+        .partition_point(|span| span.start <= start)
+        .saturating_sub(1);
+    spans[first..]
+        .iter()
+        .take_while(|span| span.start < end)
+        .map(|span| (span, (span.end.min(end) - span.start.max(start)).num_days()))
+        .filter(|(_, overlap)| *overlap > 0)
+        .max_by_key(|(_, overlap)| *overlap)
+        .map(|(span, _)| span)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Datelike;
+    use proptest::prelude::*;
+
+    fn month_index(date: NaiveDate) -> i64 {
+        date.year() as i64 * 12 + date.month0() as i64
+    }
+
+    proptest! {
+        #[test]
+        fn cell_n_starts_on_the_nth_month_after_birth(
+            year in 1900i32..2100,
+            month in 1u32..=12,
+            n in 0u32..150 * 12,
+        ) {
+            let birth = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+            let cell = nth_month(birth, n);
+            prop_assert_eq!(cell.day(), 1);
+            prop_assert_eq!(month_index(cell) - month_index(birth), n as i64);
+            prop_assert_eq!(months(birth, n + 1).last(), Some(cell));
+        }
+
+        #[test]
+        fn week_rows_start_with_the_birthday_week(
+            year in 1900i32..2100,
+            month in 1u32..=12,
+            years in 1u32..120,
+        ) {
+            let birth = NaiveDate::from_ymd_opt(year, month, 1).unwrap();
+            let weeks: Vec<(NaiveDate, NaiveDate)> = life_weeks(birth, years).collect();
+            prop_assert_eq!(weeks.len() as u32, years * WEEKS_PER_YEAR);
+            prop_assert!(weeks.iter().all(|(start, _)| start.weekday() == chrono::Weekday::Mon));
+            let lengths_ok = weeks.iter().all(|(start, end)| {
+                *end == *start + Duration::weeks(1) || *end == *start + Duration::weeks(2)
+            });
+            prop_assert!(lengths_ok);
+            // Back to back, so every day of life has a cell
+            prop_assert!(weeks.windows(2).all(|pair| pair[0].1 == pair[1].0));
+            for (row, (first, _)) in weeks.iter().step_by(WEEKS_PER_YEAR as usize).enumerate() {
+                let birthday = nth_month(birth, row as u32 * 12);
+                prop_assert!(*first <= birthday && birthday < *first + Duration::weeks(1));
+            }
+        }
+
+        #[test]
+        fn every_date_falls_in_the_span_that_starts_last_before_it(
+            starts in proptest::collection::vec(0i64..20_000, 0..8),
+            offset in -100i64..21_000,
+        ) {
+            let epoch = NaiveDate::from_ymd_opt(1950, 1, 1).unwrap();
+            let last_end = epoch + Duration::days(20_000);
+            let dates: Vec<NaiveDate> =
+                starts.iter().map(|days| epoch + Duration::days(*days)).collect();
+            let spans = spans(&dates, |date| Some(*date), last_end);
+            let date = epoch + Duration::days(offset);
+
+            let expected = dates
+                .iter()
+                .filter(|start| **start <= date && date < last_end)
+                .max();
+            prop_assert_eq!(span_at(&spans, date).map(|span| span.start), expected.copied());
+        }
+    }
+
+    #[test]
+    fn month_steps_do_not_drift() {
+        let birth = month_start("1990-03").unwrap();
+        assert_eq!(nth_month(birth, 12 * 80), month_start("2070-03").unwrap());
+        // Clamped rather than spilling into the next month
+        let end_of_january = day("2024-01-31").unwrap();
+        assert_eq!(nth_month(end_of_january, 1), day("2024-02-29").unwrap());
+        assert_eq!(days(birth, birth + Duration::days(3)).count(), 3);
+    }
+
+    #[test]
+    fn zoomed_cells_cover_their_parent() {
+        let birth = month_start("1990-03").unwrap();
+        let end = nth_month(birth, 12 * 85);
+        let decades = month_steps(birth, end, 120);
+        assert_eq!(decades.len(), 9);
+        assert_eq!(decades.last().unwrap().1, end);
+        assert_eq!(months_between(birth, decades[8].0), 12 * 80);
+
+        let (march, april) = month_steps(birth, end, 1)[0];
+        let weeks = week_steps(march, april);
+        assert_eq!(weeks.first().unwrap().0, day("1990-02-26").unwrap());
+        assert!(weeks.last().unwrap().1 >= april);
+        assert_eq!(weeks.len(), 5);
+    }
+
+    #[test]
+    fn a_cell_belongs_to_the_span_covering_most_of_it() {
+        let starts = [day("2020-01-01").unwrap(), day("2020-01-08").unwrap()];
+        let spans = spans(&starts, |date| Some(*date), day("2021-01-01").unwrap());
+        let week = |monday: &str| {
+            let start = day(monday).unwrap();
+            dominant_span(&spans, start, start + Duration::weeks(1)).map(|span| span.start)
+        };
+        // Mon 2019-12-30 to Sun 2020-01-05: five days fall in the first span
+        assert_eq!(week("2019-12-30"), Some(starts[0]));
+        // Mon 2020-01-06: two days in the first, five in the second
+        assert_eq!(week("2020-01-06"), Some(starts[1]));
+        assert_eq!(week("2019-12-16"), None);
+    }
+}
@@ -1,4 +1,4 @@
-use chrono::NaiveDate;
+use chrono::{Datelike, Duration, NaiveDate, Weekday};
 
 pub fn is_valid_date(date: &str, year_month_only: bool) -> bool {
     if year_month_only {
@@ -18,3 +18,14 @@ pub fn is_valid_date(date: &str, year_month_only: bool) -> bool {
         NaiveDate::parse_from_str(date, "%Y-%m-%d").is_ok()
     }
 }
+
+// Monday of the week containing the given date
+pub fn week_start_monday(date: NaiveDate) -> NaiveDate {
+    date - Duration::days(date.weekday().num_days_from_monday() as i64)
+}
+
+// Resolve a weekday name like "Tuesday" to its date within the week starting on `week_start`
+pub fn date_for_day_name(week_start: NaiveDate, day: &str) -> Option<NaiveDate> {
+    let weekday = day.parse::<Weekday>().ok()?;
+    Some(week_start + Duration::days(weekday.num_days_from_monday() as i64))
+}
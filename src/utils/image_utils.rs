@@ -105,7 +105,7 @@ pub fn render_legend(legend_items: &[LegendItem], width: u32) -> RgbaImage {
         }
 
         // Draw text
-        let text = format!("{} ({})", item.name, item.start);
+        let text = format!("{} ({})", item.name, item.dates());
         let text_x = rect_x + rect_width + padding;
         let text_y = y + (item_height - scale.y as u32) / 2;
         draw_text_mut(
@@ -1,4 +1,6 @@
+pub mod calendar;
 pub mod compression;
 pub mod date_utils;
 pub mod image_utils;
 pub mod screenshot;
+pub mod validation;
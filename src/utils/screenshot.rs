@@ -49,6 +49,8 @@ pub fn take_screenshot(is_landscape: bool) -> Result<String, String> {
             start: period.start.clone(),
             color: period.color.clone(),
             is_event: false,
+            end: period.end.clone(),
+            track: period.track.clone(),
         })
         .collect::<Vec<_>>();
 
@@ -0,0 +1,403 @@
+// Checks for hand-edited timelines. Everything here still loads; the diagnostics
+// point at what will look wrong and how to fix it.
+use crate::models::timeline::{LifePeriod, Yaml};
+use crate::utils::calendar;
+use crate::utils::date_utils::is_valid_date;
+use serde::Deserialize;
+use std::fmt;
+
+// Longer lives than this are most likely a typo
+const MAX_LIFE_EXPECTANCY: u32 = 150;
+
+pub fn is_valid_hex_color(color: &str) -> bool {
+    color.len() == 7 && color.starts_with('#') && color[1..].chars().all(|c| c.is_ascii_hexdigit())
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Severity {
+    // The value can't be used, e.g. a date that doesn't parse
+    Error,
+    // The value is used, but probably not as intended
+    Warning,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub severity: Severity,
+    // Dotted path such as `life_periods[2].color`
+    pub path: String,
+    pub message: String,
+    // Index of the period the problem is in, so it can be opened for editing
+    pub period: Option<usize>,
+}
+
+impl fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{:?} at {}: {}", self.severity, self.path, self.message)
+    }
+}
+
+#[derive(Default)]
+struct Diagnostics(Vec<Diagnostic>);
+
+impl Diagnostics {
+    fn push(&mut self, severity: Severity, path: String, period: Option<usize>, message: String) {
+        self.0.push(Diagnostic {
+            severity,
+            path,
+            message,
+            period,
+        });
+    }
+
+    fn error(&mut self, path: String, period: Option<usize>, message: String) {
+        self.push(Severity::Error, path, period, message);
+    }
+
+    fn warning(&mut self, path: String, period: Option<usize>, message: String) {
+        self.push(Severity::Warning, path, period, message);
+    }
+}
+
+// Everything that looks off in `yaml`, errors first, each group in file order
+pub fn validate(yaml: &Yaml) -> Vec<Diagnostic> {
+    let mut found = Diagnostics::default();
+
+    let dob = calendar::month_start(&yaml.date_of_birth)
+        .filter(|_| is_valid_date(&yaml.date_of_birth, true));
+    if dob.is_none() {
+        found.error(
+            "date_of_birth".to_string(),
+            None,
+            format!(
+                "\"{}\" isn't a month like 1990-04, so the timeline can't be drawn",
+                yaml.date_of_birth
+            ),
+        );
+    }
+    if yaml.life_expectancy == 0 {
+        found.error(
+            "life_expectancy".to_string(),
+            None,
+            "A life expectancy of 0 leaves the timeline empty; set it in years, e.g. 80"
+                .to_string(),
+        );
+    } else if yaml.life_expectancy > MAX_LIFE_EXPECTANCY {
+        found.warning(
+            "life_expectancy".to_string(),
+            None,
+            format!(
+                "{} years is more than {}, is it a typo?",
+                yaml.life_expectancy, MAX_LIFE_EXPECTANCY
+            ),
+        );
+    }
+
+    let life_end = dob
+        .filter(|_| yaml.life_expectancy > 0)
+        .map(|dob| calendar::nth_month(dob, yaml.life_expectancy * 12));
+    for (index, period) in yaml.life_periods.iter().enumerate() {
+        check_period(&mut found, yaml, index, period, dob, life_end);
+    }
+    if let Some(life_end) = life_end {
+        check_events(&mut found, yaml, life_end);
+    }
+
+    // Stable, so each severity keeps file order
+    found.0.sort_by_key(|diagnostic| diagnostic.severity);
+    found.0
+}
+
+// Timelines are stored in a version envelope, but hand-written ones may be bare
+#[derive(Deserialize)]
+struct Enveloped {
+    data: Yaml,
+}
+
+// Why a timeline file doesn't load, pointing at the line serde gave up on. None
+// when it parses.
+pub fn parse_diagnostic(content: &str) -> Option<Diagnostic> {
+    let enveloped = serde_yaml::from_str::<serde_yaml::Value>(content)
+        .ok()
+        .is_some_and(|value| value.get("version").is_some() && value.get("data").is_some());
+    let error = if enveloped {
+        serde_yaml::from_str::<Enveloped>(content)
+            .map(|envelope| envelope.data)
+            .err()?
+    } else {
+        serde_yaml::from_str::<Yaml>(content).err()?
+    };
+
+    let message = error.to_string();
+    // The location is in the path already
+    let message = match message.rsplit_once(" at line ") {
+        Some((message, _)) => message.to_string(),
+        None => message,
+    };
+    Some(Diagnostic {
+        severity: Severity::Error,
+        path: error.location().map_or_else(
+            || "timeline".to_string(),
+            |location| format!("line {}, column {}", location.line(), location.column()),
+        ),
+        message: format!("{}; the timeline couldn't be loaded", message),
+        period: None,
+    })
+}
+
+fn check_period(
+    found: &mut Diagnostics,
+    yaml: &Yaml,
+    index: usize,
+    period: &LifePeriod,
+    dob: Option<chrono::NaiveDate>,
+    life_end: Option<chrono::NaiveDate>,
+) {
+    let path = format!("life_periods[{}]", index);
+    let name = if period.name.trim().is_empty() {
+        found.warning(
+            format!("{}.name", path),
+            Some(index),
+            "The period has no name, so it can't be told apart in the legend".to_string(),
+        );
+        format!("Period {}", index + 1)
+    } else {
+        period.name.clone()
+    };
+
+    if !is_valid_hex_color(&period.color) {
+        found.error(
+            format!("{}.color", path),
+            Some(index),
+            format!("{}: \"{}\" isn't a color like #1E90FF", name, period.color),
+        );
+    }
+
+    let start = calendar::month_start(&period.start).filter(|_| is_valid_date(&period.start, true));
+    let Some(start) = start else {
+        found.error(
+            format!("{}.start", path),
+            Some(index),
+            format!(
+                "{}: \"{}\" isn't a month like 2010-09, so the period isn't shown",
+                name, period.start
+            ),
+        );
+        return;
+    };
+    if dob.is_some_and(|dob| start < dob) {
+        found.warning(
+            format!("{}.start", path),
+            Some(index),
+            format!(
+                "{} starts before the date of birth ({}), so its start isn't shown",
+                name, yaml.date_of_birth
+            ),
+        );
+    }
+    if life_end.is_some_and(|life_end| start >= life_end) {
+        found.warning(
+            format!("{}.start", path),
+            Some(index),
+            format!("{} starts after the end of the life expectancy", name),
+        );
+    }
+    if let Some(previous) = index
+        .checked_sub(1)
+        .and_then(|previous| yaml.life_periods.get(previous))
+    {
+        if calendar::month_start(&previous.start).is_some_and(|previous| start < previous) {
+            found.warning(
+                format!("{}.start", path),
+                Some(index),
+                format!(
+                    "{} starts before {}, the period above it; sort the periods by start",
+                    name, previous.name
+                ),
+            );
+        }
+    }
+
+    if let Some(end) = &period.end {
+        let last_month = calendar::month_start(end).filter(|_| is_valid_date(end, true));
+        match last_month {
+            None => found.error(
+                format!("{}.end", path),
+                Some(index),
+                format!(
+                    "{}: \"{}\" isn't a month like 2014-06; the end is ignored",
+                    name, end
+                ),
+            ),
+            Some(last_month) if last_month < start => found.error(
+                format!("{}.end", path),
+                Some(index),
+                format!(
+                    "{} ends ({}) before it starts ({})",
+                    name, end, period.start
+                ),
+            ),
+            Some(last_month) => {
+                // The next period on the track cuts this one short
+                let next = yaml
+                    .life_periods
+                    .iter()
+                    .filter(|other| other.track_name() == period.track_name())
+                    .filter_map(|other| {
+                        calendar::month_start(&other.start).map(|other_start| (other_start, other))
+                    })
+                    .filter(|(other_start, _)| *other_start > start)
+                    .min_by_key(|(other_start, _)| *other_start);
+                if let Some((_, next)) = next.filter(|(next_start, _)| last_month >= *next_start) {
+                    found.warning(
+                        format!("{}.end", path),
+                        Some(index),
+                        format!(
+                            "{} ends after {} starts on the same track, so it stops there; move it to a track of its own to let them overlap",
+                            name, next.name
+                        ),
+                    );
+                }
+            }
+        }
+    }
+}
+
+fn check_events(found: &mut Diagnostics, yaml: &Yaml, life_end: chrono::NaiveDate) {
+    let tracks = yaml.period_tracks(life_end);
+    for (index, period) in yaml.life_periods.iter().enumerate() {
+        // Periods whose start doesn't parse have no span and were reported already
+        let span = tracks
+            .iter()
+            .flat_map(|(_, spans)| spans)
+            .find(|span| std::ptr::eq(span.item, period));
+        for (event_index, event) in period.events.iter().enumerate() {
+            let path = format!("life_periods[{}].events[{}]", index, event_index);
+            if !is_valid_hex_color(&event.color) {
+                found.error(
+                    format!("{}.color", path),
+                    Some(index),
+                    format!(
+                        "{}: \"{}\" isn't a color like #1E90FF",
+                        event.name, event.color
+                    ),
+                );
+            }
+            let Some(date) = calendar::day(&event.start) else {
+                found.error(
+                    format!("{}.start", path),
+                    Some(index),
+                    format!(
+                        "{}: \"{}\" isn't a date like 2012-05-17, so the event isn't shown",
+                        event.name, event.start
+                    ),
+                );
+                continue;
+            };
+            if let Some(span) = span.filter(|span| date < span.start || date >= span.end) {
+                let last_day = span.end.pred_opt().unwrap_or(span.end);
+                found.warning(
+                    format!("{}.start", path),
+                    Some(index),
+                    format!(
+                        "{} on {} is outside {} ({} to {}); move it to the period it falls in",
+                        event.name, event.start, period.name, span.start, last_day
+                    ),
+                );
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn paths(yaml: &str) -> Vec<(Severity, String)> {
+        let yaml: Yaml = serde_yaml::from_str(yaml).unwrap();
+        validate(&yaml)
+            .into_iter()
+            .map(|diagnostic| (diagnostic.severity, diagnostic.path))
+            .collect()
+    }
+
+    #[test]
+    fn a_clean_timeline_has_no_diagnostics() {
+        let found = paths(
+            "
+name: Test
+date_of_birth: 2000-01
+life_expectancy: 80
+life_periods:
+- { name: Childhood, start: 2000-01, color: '#111111', events: [{ name: Bike, start: 2006-05-01, color: '#222222' }] }
+- { name: School, start: 2006-09, end: 2018-06, color: '#333333' }
+- { name: Berlin, start: 2016-03, track: Home, color: '#444444' }
+",
+        );
+        assert_eq!(found, vec![]);
+    }
+
+    #[test]
+    fn hand_edited_mistakes_are_pointed_out() {
+        let found = paths(
+            "{ name: Test, date_of_birth: April 1990, life_expectancy: 0, life_periods: [] }",
+        );
+        assert_eq!(
+            found,
+            vec![
+                (Severity::Error, "date_of_birth".to_string()),
+                (Severity::Error, "life_expectancy".to_string()),
+            ]
+        );
+
+        let found = paths(
+            "
+name: Test
+date_of_birth: 2000-01
+life_expectancy: 90
+life_periods:
+- { name: Before, start: 1998-01, color: red }
+- { name: School, start: 2006-09, end: 2005-01, color: '#333333', events: [{ name: Trip, start: 1997-07-01, color: '#222222' }] }
+- { name: Earlier, start: 2003-01, color: '#444444' }
+",
+        );
+        assert_eq!(
+            found,
+            vec![
+                (Severity::Error, "life_periods[0].color".to_string()),
+                (Severity::Error, "life_periods[1].end".to_string()),
+                (Severity::Warning, "life_periods[0].start".to_string()),
+                (Severity::Warning, "life_periods[2].start".to_string()),
+                (
+                    Severity::Warning,
+                    "life_periods[1].events[0].start".to_string()
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn files_that_dont_parse_point_at_the_line() {
+        let found = parse_diagnostic(
+            "name: Test\ndate_of_birth: 2000-01\nlife_expectancy: eighty\nlife_periods: []\n",
+        )
+        .unwrap();
+        assert_eq!(found.severity, Severity::Error);
+        assert_eq!(found.path, "line 3, column 18");
+        assert!(found.message.starts_with("life_expectancy: invalid type"));
+
+        let found = parse_diagnostic(
+            "version: 2\ndata:\n  name: Test\n  date_of_birth: 2000-01\n  life_expectancy: 80\n  life_periods:\n  - { name: School, start: 2006-09 }\n",
+        )
+        .unwrap();
+        assert!(found.path.starts_with("line 7"));
+        assert!(found.message.contains("missing field `color`"));
+
+        assert_eq!(
+            parse_diagnostic(
+                "name: Test\ndate_of_birth: 2000-01\nlife_expectancy: 80\nlife_periods: []\n"
+            ),
+            None
+        );
+    }
+}
@@ -1,7 +1,10 @@
+use crate::components::habit_notes::HabitNotesTimeline;
 use crate::components::habit_tab_bar::TabBar;
 use crate::components::habit_tracker::HabitTracker;
 use crate::managers::habit_manager::get_habit_manager;
+use crate::managers::DataStore;
 use crate::models::habit::{HabitData, WeekStart};
+use crate::storage::StorageEvent;
 use chrono::Local;
 use dioxus::prelude::*;
 use tracing::{error, info};
@@ -10,7 +13,7 @@ use uuid::Uuid;
 #[component]
 pub fn HabitsPage() -> Element {
     let mut selected_habit_id = use_signal(|| None::<Uuid>);
-    let current_habit_data = use_signal(|| None::<HabitData>);
+    let mut current_habit_data = use_signal(|| None::<HabitData>);
 
     info!(
         "HabitsPage rendering with selected_habit_id: {:?}",
@@ -27,7 +30,13 @@ pub fn HabitsPage() -> Element {
 
                 match manager.get_all_habits().await {
                     Ok(habits) => {
-                        if let Some((first_id, first_data)) = habits.into_iter().next() {
+                        // Prefer the first active habit, fall back to an archived one
+                        let first = habits
+                            .iter()
+                            .find(|(_, data)| !data.archived)
+                            .or(habits.first())
+                            .cloned();
+                        if let Some((first_id, first_data)) = first {
                             info!("Found first habit: {:?}", first_id);
                             selected_habit_id.set(Some(first_id));
                             current_habit_data.set(Some(first_data));
@@ -35,13 +44,12 @@ pub fn HabitsPage() -> Element {
                             // Create default habit with better error handling
                             info!("No habits found, creating default habit");
                             let new_id = Uuid::new_v4();
-                            let default_data = HabitData {
-                                title: "Meditation".to_string(),
-                                start_date: Local::now().date_naive(),
-                                color: "#800080".to_string(),
-                                week_start: WeekStart::Sunday,
-                                completed_days: Vec::new(),
-                            };
+                            let default_data = HabitData::new(
+                                "Meditation".to_string(),
+                                Local::now().date_naive(),
+                                "#800080".to_string(),
+                                WeekStart::Sunday,
+                            );
 
                             if let Err(e) = manager.create_habit(new_id, default_data.clone()).await
                             {
@@ -80,6 +88,25 @@ pub fn HabitsPage() -> Element {
         }
     });
 
+    // Pick up changes synced in from another device
+    use_future(move || async move {
+        let mut events = DataStore::Habits.subscribe().await;
+        loop {
+            if DataStore::Habits.next_event(&mut events).await != StorageEvent::Reloaded {
+                continue;
+            }
+            let Some(id) = selected_habit_id() else {
+                continue;
+            };
+            match get_habit_manager().get_habit(id).await {
+                Ok(Some(data)) => current_habit_data.set(Some(data)),
+                // Deleted elsewhere; the first effect picks another habit
+                Ok(None) => selected_habit_id.set(None),
+                Err(e) => error!("Failed to reload habit: {:?}", e),
+            }
+        }
+    });
+
     rsx! {
         div { class: "habits-container",
             TabBar {
@@ -111,6 +138,7 @@ pub fn HabitsPage() -> Element {
                             });
                         }
                     }
+                    HabitNotesTimeline { habit_data: data.clone() }
                 },
                 _ => rsx! { div { "Loading..." } }
             }}
@@ -33,12 +33,12 @@ pub fn HomePage() -> Element {
                         "View your life's journey and important moments"
                     }
                 }
-                // Placeholder for future Routine page
-                div {
+                Link {
+                    to: Route::RoutinesPage {},
                     class: "nav-card",
-                    div { class: "card-title", "Routine" }
+                    div { class: "card-title", "Routines" }
                     div { class: "card-description",
-                        "Coming soon: Plan and track your daily routines"
+                        "Plan and track your daily routines"
                     }
                 }
             }
@@ -4,6 +4,12 @@ pub use home::HomePage;
 mod habits;
 pub use habits::HabitsPage;
 
+mod routines;
+pub use routines::RoutinesPage;
+
+mod settings;
+pub use settings::SettingsPage;
+
 mod todos;
 pub use todos::TodosPage;
 
@@ -0,0 +1,145 @@
+use crate::components::routine_editor::RoutineEditor;
+use crate::components::routine_schedule::DaySchedule;
+use crate::components::todo_day_tabs::{DayTabs, DAYS};
+use crate::managers::routine_manager::{
+    get_routine_manager, ScheduledBlock, DEFAULT_ROUTINE_COLOR,
+};
+use crate::models::routine::RoutineData;
+use chrono::{Datelike, Local, Weekday};
+use dioxus::prelude::*;
+use tracing::{error, info};
+use uuid::Uuid;
+
+const ROUTINES_CSS: Asset = asset!("/assets/styling/routines.css");
+
+#[component]
+pub fn RoutinesPage() -> Element {
+    let mut active_day = use_signal(|| {
+        DAYS[Local::now().weekday().num_days_from_monday() as usize]
+            .name
+            .to_string()
+    });
+    let mut routines = use_signal(Vec::<(Uuid, RoutineData)>::new);
+    let mut selected_routine_id = use_signal(|| None::<Uuid>);
+    let mut schedule = use_signal(Vec::<ScheduledBlock>::new);
+    let mut refresh = use_signal(|| 0u32);
+
+    // Reload routines and the day schedule whenever the day changes or data is edited
+    use_effect(move || {
+        let day = active_day.read().clone();
+        let _ = refresh.read();
+
+        spawn(async move {
+            let manager = get_routine_manager();
+            match manager.get_all_routines().await {
+                Ok(loaded) => {
+                    if selected_routine_id.peek().is_none() {
+                        if let Some((first_id, _)) = loaded.first() {
+                            selected_routine_id.set(Some(*first_id));
+                        }
+                    }
+                    routines.set(loaded);
+                }
+                Err(e) => error!("Failed to load routines: {:?}", e),
+            }
+
+            let weekday = day.parse::<Weekday>().unwrap_or(Weekday::Mon);
+            match manager.get_day_schedule(weekday).await {
+                Ok(blocks) => schedule.set(blocks),
+                Err(e) => error!("Failed to load schedule: {:?}", e),
+            }
+        });
+    });
+
+    let create_routine = move |_| {
+        spawn(async move {
+            let new_id = Uuid::new_v4();
+            let data = RoutineData {
+                name: "New Routine".to_string(),
+                color: DEFAULT_ROUTINE_COLOR.to_string(),
+                blocks: Vec::new(),
+            };
+            info!("Creating new routine with id: {}", new_id);
+            match get_routine_manager().create_routine(new_id, data).await {
+                Ok(_) => {
+                    selected_routine_id.set(Some(new_id));
+                    refresh += 1;
+                }
+                Err(e) => error!("Failed to create routine: {:?}", e),
+            }
+        });
+    };
+
+    let delete_routine = move |id: Uuid| {
+        spawn(async move {
+            match get_routine_manager().delete_routine(id).await {
+                Ok(_) => {
+                    selected_routine_id.set(None);
+                    refresh += 1;
+                }
+                Err(e) => error!("Failed to delete routine: {:?}", e),
+            }
+        });
+    };
+
+    let selected = selected_routine_id().and_then(|id| {
+        routines
+            .read()
+            .iter()
+            .find(|(routine_id, _)| *routine_id == id)
+            .cloned()
+    });
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: ROUTINES_CSS }
+        div { class: "routines-container",
+            DayTabs {
+                active_day: active_day.read().clone(),
+                on_day_change: move |day| active_day.set(day)
+            }
+            div { class: "routines-layout",
+                DaySchedule { blocks: schedule.read().clone() }
+                div { class: "routines-sidebar",
+                    div { class: "routine-tabs",
+                        {routines.read().iter().map(|(id, data)| {
+                            let id = *id;
+                            let is_selected = selected_routine_id() == Some(id);
+                            rsx! {
+                                button {
+                                    key: "{id}",
+                                    class: if is_selected { "routine-tab selected" } else { "routine-tab" },
+                                    style: "border-color: {data.color};",
+                                    onclick: move |_| selected_routine_id.set(Some(id)),
+                                    "{data.name}"
+                                }
+                            }
+                        })}
+                        button {
+                            class: "new-routine-tab",
+                            onclick: create_routine,
+                            "+"
+                        }
+                    }
+                    {match selected {
+                        Some((id, data)) => rsx! {
+                            RoutineEditor {
+                                key: "{id}",
+                                routine_id: id,
+                                routine_data: data,
+                                on_data_change: move |_| refresh += 1
+                            }
+                            button {
+                                class: "delete-button",
+                                onclick: move |_| delete_routine(id),
+                                "Delete Routine"
+                            }
+                        },
+                        None => rsx! {
+                            div { class: "routines-empty", "Create a routine to start planning your day" }
+                        }
+                    }}
+                }
+            }
+        }
+    }
+}
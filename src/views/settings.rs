@@ -0,0 +1,221 @@
+// SettingsPage
+use crate::components::encryption::EncryptionSettings;
+use crate::managers::DataStore;
+use crate::storage::{get_path_manager, BackupInfo, ChangeKind, DiffEntry};
+use dioxus::prelude::*;
+use tracing::error;
+
+const SETTINGS_CSS: Asset = asset!("/assets/styling/settings.css");
+
+// Diffs of large stores can run into thousands of lines
+const MAX_DIFF_ENTRIES: usize = 200;
+
+fn format_size(bytes: u64) -> String {
+    if bytes < 1024 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} KB", bytes as f64 / 1024.0)
+    }
+}
+
+#[component]
+pub fn SettingsPage() -> Element {
+    let mut store = use_signal(|| DataStore::Habits);
+    let mut backups = use_signal(Vec::<BackupInfo>::new);
+    let mut selected = use_signal(|| None::<usize>);
+    let mut diff = use_signal(Vec::<DiffEntry>::new);
+    let mut confirm_restore = use_signal(|| false);
+    let mut status = use_signal(|| None::<String>);
+    let mut refresh = use_signal(|| 0u32);
+
+    // Reload the backup list whenever the store changes or after a restore
+    use_effect(move || {
+        let current = store();
+        refresh();
+        spawn(async move {
+            // Pending writes would otherwise be missing from the comparison
+            let _ = current.flush().await;
+            match current.list_backups().await {
+                Ok(list) => backups.set(list),
+                Err(e) => {
+                    error!("Failed to list backups: {}", e);
+                    backups.set(Vec::new());
+                }
+            }
+            selected.set(None);
+            diff.set(Vec::new());
+        });
+    });
+
+    let mut select_backup = move |index: usize| {
+        selected.set(Some(index));
+        confirm_restore.set(false);
+        let current = store();
+        spawn(async move {
+            match current.diff_backup(index).await {
+                Ok(entries) => diff.set(entries),
+                Err(e) => {
+                    diff.set(Vec::new());
+                    status.set(Some(e));
+                }
+            }
+        });
+    };
+
+    let restore = move |index: usize| {
+        let current = store();
+        spawn(async move {
+            match current.restore_backup(index).await {
+                Ok(path) => {
+                    let name = path
+                        .file_name()
+                        .map(|name| name.to_string_lossy().to_string())
+                        .unwrap_or_default();
+                    status.set(Some(format!(
+                        "{} restored from {}. The previous state was kept as the newest backup.",
+                        current.label(),
+                        name
+                    )));
+                    refresh += 1;
+                }
+                Err(e) => status.set(Some(format!("Restore failed: {}", e))),
+            }
+            confirm_restore.set(false);
+        });
+    };
+
+    let selected_backup = selected().and_then(|index| backups.read().get(index).cloned());
+    let diff_entries = diff.read().clone();
+    let data_dir = get_path_manager().root_dir().display().to_string();
+    let data_dir_source = get_path_manager().source().label();
+
+    rsx! {
+        document::Link { rel: "stylesheet", href: SETTINGS_CSS }
+        div { class: "settings-container",
+            h2 { "Data folder" }
+            div { class: "settings-data-dir",
+                code { "{data_dir}" }
+                span { class: "settings-data-dir-source", "{data_dir_source}" }
+            }
+
+            h2 { "Encryption" }
+            EncryptionSettings {}
+
+            h2 { "Backups" }
+            div { class: "settings-store-tabs",
+                {DataStore::ALL.iter().map(|option| {
+                    let option = *option;
+                    rsx! {
+                        button {
+                            key: "{option.label()}",
+                            class: if store() == option { "active" } else { "" },
+                            onclick: move |_| {
+                                status.set(None);
+                                store.set(option);
+                            },
+                            "{option.label()}"
+                        }
+                    }
+                })}
+            }
+
+            if let Some(message) = status() {
+                div { class: "settings-status", "{message}" }
+            }
+
+            div { class: "backup-browser",
+                ul { class: "backup-list",
+                    if backups.read().is_empty() {
+                        li { class: "backup-empty", "No backups yet" }
+                    }
+                    {backups.read().iter().enumerate().map(|(index, backup)| {
+                        let is_selected = selected() == Some(index);
+                        let valid = backup.preview.is_ok();
+                        rsx! {
+                            li {
+                                key: "{backup.path.display()}",
+                                class: format!(
+                                    "backup-item {} {}",
+                                    if is_selected { "selected" } else { "" },
+                                    if valid { "" } else { "invalid" }
+                                ),
+                                onclick: move |_| {
+                                    if valid {
+                                        select_backup(index);
+                                    }
+                                },
+                                div { class: "backup-label", "{backup.label()}" }
+                                div { class: "backup-meta",
+                                    {backup.modified.format("%Y-%m-%d %H:%M").to_string()}
+                                    " · "
+                                    {format_size(backup.size)}
+                                }
+                                div { class: "backup-summary", "{backup.summary()}" }
+                            }
+                        }
+                    })}
+                }
+
+                div { class: "backup-diff",
+                    match (selected(), selected_backup) {
+                        (Some(index), Some(backup)) => rsx! {
+                            div { class: "backup-diff-header",
+                                span {
+                                    "Restoring {backup.label()} would make "
+                                    {diff_entries.len().to_string()}
+                                    " change(s)"
+                                }
+                                if confirm_restore() {
+                                    div { class: "confirm-actions",
+                                        span { "Replace current {store().label()} data?" }
+                                        button { class: "confirm-yes", onclick: move |_| restore(index), "Restore" }
+                                        button { class: "confirm-no", onclick: move |_| confirm_restore.set(false), "Cancel" }
+                                    }
+                                } else {
+                                    button {
+                                        class: "restore-btn",
+                                        disabled: diff_entries.is_empty(),
+                                        onclick: move |_| confirm_restore.set(true),
+                                        "Restore this backup"
+                                    }
+                                }
+                            }
+                            ul { class: "diff-list",
+                                {diff_entries.iter().take(MAX_DIFF_ENTRIES).map(|entry| {
+                                    let (class, symbol) = match entry.kind {
+                                        ChangeKind::Added => ("diff-added", "+"),
+                                        ChangeKind::Removed => ("diff-removed", "−"),
+                                        ChangeKind::Changed => ("diff-changed", "~"),
+                                    };
+                                    rsx! {
+                                        li { key: "{entry.path}", class: "{class}",
+                                            span { class: "diff-symbol", "{symbol}" }
+                                            span { class: "diff-path", "{entry.path}" }
+                                            if let Some(before) = &entry.before {
+                                                span { class: "diff-before", "{before}" }
+                                            }
+                                            if entry.before.is_some() && entry.after.is_some() {
+                                                span { " → " }
+                                            }
+                                            if let Some(after) = &entry.after {
+                                                span { class: "diff-after", "{after}" }
+                                            }
+                                        }
+                                    }
+                                })}
+                                if diff_entries.len() > MAX_DIFF_ENTRIES {
+                                    li { class: "diff-more",
+                                        {format!("…and {} more", diff_entries.len() - MAX_DIFF_ENTRIES)}
+                                    }
+                                }
+                            }
+                        },
+                        _ => rsx! {
+                            div { class: "backup-diff-empty", "Select a backup to compare it with the current data" }
+                        },
+                    }
+                }
+            }
+        }
+    }
+}
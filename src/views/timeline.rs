@@ -1,10 +1,12 @@
 use crate::components::timeline::bottom_panel::BottomPanel;
 use crate::components::timeline::central_panel::CentralPanel;
+use crate::components::timeline::problems_panel::ProblemsPanel;
 use crate::components::timeline::top_panel::TopPanel;
 use crate::managers::timeline_manager::get_timeline_manager;
+use crate::managers::DataStore;
 use crate::state::life_state::initialize_state;
+use crate::storage::StorageEvent;
 use dioxus::prelude::*;
-use tokio::time::Duration;
 use tracing::{debug, error};
 const TIMELINE_VIEW_CSS: Asset = asset!("/assets/styling/timeline_view.css");
 const TIMELINE_ITEMS_CSS: Asset = asset!("/assets/styling/timeline_items.css");
@@ -31,23 +33,24 @@ pub fn TimelinePage(y: String) -> Element {
         }
     });
 
-    use_effect(move || {
+    // Pick up edits made to the timeline file outside the app
+    use_future(move || {
         to_owned![yaml_state];
-        spawn(async move {
-            let timeline_manager = get_timeline_manager();
+        async move {
+            let mut events = DataStore::Timeline.subscribe().await;
             loop {
-                tokio::time::sleep(Duration::from_secs(1)).await;
-                match timeline_manager.check_for_file_changes().await {
-                    Ok(Some(new_yaml)) => {
+                if DataStore::Timeline.next_event(&mut events).await != StorageEvent::Reloaded {
+                    continue;
+                }
+                match get_timeline_manager().get_timeline().await {
+                    Ok(new_yaml) => {
                         debug!("File changes detected, updating state");
                         yaml_state.set(new_yaml);
                     }
-                    Ok(None) => (),
-                    Err(e) => error!("Error checking for file changes: {}", e),
+                    Err(e) => error!("Error reloading timeline: {}", e),
                 }
             }
-        });
-        (|| ())()
+        }
     });
     // Show loading state while initializing
     if loading() {
@@ -83,6 +86,7 @@ pub fn TimelinePage(y: String) -> Element {
         div {
             class: "app-container",
             TopPanel { y: y_two.clone() }
+            ProblemsPanel {}
             CentralPanel {}
             BottomPanel {}
         }
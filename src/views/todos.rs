@@ -1,35 +1,88 @@
 // TodosPage
-use crate::components::todo_day_tabs::DayTabs;
+use crate::components::todo_day_tabs::{DayTabs, WeekNavigation, DAYS};
 use crate::components::todo_list::TodoList;
 use crate::managers::todo_manager::get_todo_manager;
-use crate::models::todo::Todo;
+use crate::managers::DataStore;
+use crate::models::todo::{CarryOverPolicy, Todo};
+use crate::storage::StorageEvent;
+use crate::utils::date_utils::{date_for_day_name, week_start_monday};
+use chrono::{Datelike, Local, NaiveDate};
 use dioxus::prelude::*;
+use tracing::error;
 
 #[component]
 pub fn TodosPage() -> Element {
-    let mut active_day = use_signal(|| "Monday".to_string());
+    let today = Local::now().date_naive();
+    let mut active_day = use_signal(|| {
+        DAYS[today.weekday().num_days_from_monday() as usize]
+            .name
+            .to_string()
+    });
+    let mut week_start = use_signal(|| week_start_monday(today));
     let todos = use_signal(Vec::<Todo>::new);
+    let history = use_signal(Vec::<NaiveDate>::new);
+    let mut carry_over_policy = use_signal(CarryOverPolicy::default);
+    let mut carried_over = use_signal(|| false);
+    let mut reloads = use_signal(|| 0u32);
+
+    let active_date = use_memo(move || {
+        date_for_day_name(week_start(), &active_day.read()).unwrap_or(week_start())
+    });
+
+    // Move unfinished todos from earlier days forward once per visit
+    use_future(move || async move {
+        let manager = get_todo_manager();
+        if let Ok(policy) = manager.get_carry_over_policy().await {
+            carry_over_policy.set(policy);
+        }
+        if let Err(e) = manager.carry_over(Local::now().date_naive()).await {
+            error!("Failed to carry over todos: {}", e);
+        }
+        carried_over.set(true);
+    });
+
+    // Pick up changes synced in from another device
+    use_future(move || async move {
+        let mut events = DataStore::Todos.subscribe().await;
+        loop {
+            if DataStore::Todos.next_event(&mut events).await != StorageEvent::Reloaded {
+                continue;
+            }
+            if let Ok(policy) = get_todo_manager().get_carry_over_policy().await {
+                carry_over_policy.set(policy);
+            }
+            reloads += 1;
+        }
+    });
 
-    // Load todos whenever active day changes
+    // Load todos whenever active day or week changes, or the file was reloaded
     use_effect(move || {
         let mut todos = todos.clone();
-        let day = active_day.read().clone();
+        let mut history = history.clone();
+        let date = active_date();
+        reloads();
+        if !carried_over() {
+            return;
+        }
 
         spawn(async move {
             let manager = get_todo_manager();
-            if let Ok(loaded_todos) = manager.get_todos_by_day(&day).await {
+            if let Ok(loaded_todos) = manager.get_todos_by_date(date).await {
                 todos.set(loaded_todos);
             }
+            if let Ok(weeks) = manager.get_history_weeks(Local::now().date_naive()).await {
+                history.set(weeks);
+            }
         });
     });
 
     let handle_todos_change = move || {
-        let day = active_day.read().clone();
+        let date = active_date();
         let mut todos = todos.clone();
 
         spawn(async move {
             let manager = get_todo_manager();
-            if let Ok(loaded_todos) = manager.get_todos_by_day(&day).await {
+            if let Ok(loaded_todos) = manager.get_todos_by_date(date).await {
                 todos.set(loaded_todos);
             }
         });
@@ -37,12 +90,37 @@ pub fn TodosPage() -> Element {
 
     rsx! {
         div { class: "todos-container",
+            WeekNavigation {
+                week_start: week_start(),
+                history: history.read().clone(),
+                on_week_change: move |date| week_start.set(week_start_monday(date))
+            }
+            div {
+                class: "carry-over-setting",
+                label { "Unfinished todos: " }
+                select {
+                    value: carry_over_policy().to_string(),
+                    onchange: move |ev: Event<FormData>| {
+                        let policy = CarryOverPolicy::from_string(&ev.value());
+                        carry_over_policy.set(policy);
+                        spawn(async move {
+                            if let Err(e) = get_todo_manager().set_carry_over_policy(policy).await {
+                                error!("Failed to update carry-over policy: {}", e);
+                            }
+                        });
+                    },
+                    option { value: "today", "Carry to today" }
+                    option { value: "same_weekday", "Carry to same weekday" }
+                    option { value: "leave", "Leave on their day" }
+                }
+            }
             DayTabs {
                 active_day: active_day.read().clone(),
-                on_day_change: move |day| active_day.set(day)
+                on_day_change: move |day| active_day.set(day),
+                week_start: week_start()
             }
             TodoList {
-                day: active_day.read().clone(),
+                date: active_date(),
                 todos: todos.read().clone(),
                 on_todos_change: handle_todos_change
             }